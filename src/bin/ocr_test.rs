@@ -13,7 +13,7 @@ use nz_rust::input::{
     InputBackend,
 };
 use nz_rust::ocr::{init_ocr, ocr_screen, ocr_screen_small, OcrResultItem};
-use nz_rust::screen::capture_region;
+use nz_rust::screen::{capture_region, Region};
 
 /// 测试动作类型
 #[derive(Clone)]
@@ -266,7 +266,7 @@ impl OcrTestApp {
 
         // 生成代码
         let code = format!(
-            "let results = ocr_screen({}, {}, {}, {}, false, IS_DEBUG)?;",
+            "let results = ocr_screen(Region::new({}, {}, {}, {}), false, IS_DEBUG)?;",
             start_x, start_y, width, height
         );
 
@@ -521,7 +521,7 @@ impl OcrTestApp {
         }
 
         // 截图并更新预览
-        match capture_region(start_x, start_y, width, height) {
+        match capture_region(Region::new(start_x, start_y, width, height)) {
             Ok(img) => {
                 // 转换为 egui 可用的格式
                 let size = [img.width() as usize, img.height() as usize];
@@ -545,10 +545,11 @@ impl OcrTestApp {
 
         // 执行 OCR
         let start_time = std::time::Instant::now();
+        let region = Region::new(start_x, start_y, width, height);
         let ocr_result = if self.use_preprocess {
-            ocr_screen_small(start_x, start_y, width, height, self.preprocess_scale, true)
+            ocr_screen_small(region, self.preprocess_scale, true)
         } else {
-            ocr_screen(start_x, start_y, width, height, false, false)
+            ocr_screen(region, false, false)
         };
         match ocr_result {
             Ok(results) => {
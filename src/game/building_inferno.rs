@@ -1,19 +1,22 @@
 //! 大厦 - 炼狱难度
 //!
 //! 每个波次一个函数，可单独调试。
-//! 调试时在 run_all_waves() 中注释掉不需要的波次即可。
+//! 调试时在 GUI 中设置波次范围（或单波模式）即可，勾选 "先执行设置步骤" 会先购买陷阱。
 //!
 //! 装备顺序（购买顺序决定热键）:
 //!   天网 → 4键, 自修复磁暴塔 → 5键, 天启 → 6键, 防空导弹 → 7键
+//! 放置/升级时直接用陷阱名称，热键按实际购买结果换算（见 `Loadout`）。
 
 use anyhow::{Context, Result};
 use std::thread;
 use std::time::Duration;
 
 use super::common::{
-    buy_traps_ordered, find_game_window, place_trap, setup_window, upgrade_trap, wait_for_game_end,
-    wait_gold, wait_wave, IS_DEBUG,
+    buy_traps_ordered, find_game_window, place_trap, setup_window, upgrade_trap, wait_gold,
+    wait_wave_with, Deadline, WaitOptions, IS_DEBUG,
 };
+use super::loadout::{self, Loadout};
+use super::strategy::{Ctx, Metadata, ParamValue, Parameter, Strategy};
 use crate::input::{click_at, press_key, VK_SPACE};
 use crate::ocr::{find_text_contains, ocr_screen};
 use crate::screen::dev_x;
@@ -21,18 +24,85 @@ use crate::screen::dev_y;
 use crate::screen::full_screen_region;
 use crate::stop_flag::should_stop;
 
-// ===== 陷阱热键 =====
+// ===== 陷阱 =====
 
-const TIANWANG: &str = "4"; // 天网
-const CIBAO: &str = "5"; // 自修复磁暴塔
-const TIANQI: &str = "6"; // 天启
-const FANGKONG: &str = "7"; // 防空导弹
+const TIANWANG: &str = "天网";
+const CIBAO: &str = "自修复磁暴塔";
+const TIANQI: &str = "天启";
+const FANGKONG: &str = "防空导弹";
 
-const EQUIPPED_TRAPS: &[&str] = &["天网", "自修复磁暴塔", "天启", "防空导弹"];
+const EQUIPPED_TRAPS: &[&str] = &[TIANWANG, CIBAO, TIANQI, FANGKONG];
+
+/// 等待函数的默认超时（炼狱单波较长）
+const WAIT_TIMEOUT: Duration = Duration::from_secs(20 * 60);
+
+// ===== 策略 =====
+
+/// 大厦 - 炼狱
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BuildingInferno;
+
+impl Strategy for BuildingInferno {
+    fn metadata(&self) -> Metadata {
+        Metadata::new("大厦", "炼狱")
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![Parameter::new(
+            "skip_intro",
+            "长按空格跳过开场动画",
+            ParamValue::Bool(true),
+        )]
+    }
+
+    fn start(&mut self, ctx: &mut Ctx) -> Result<()> {
+        start_game(ctx.bool("skip_intro").unwrap_or(true), None)
+    }
+
+    fn waves(&self) -> Vec<u32> {
+        (1..=9).collect()
+    }
+
+    fn resume(&mut self, _ctx: &mut Ctx, _wave: u32) -> Result<()> {
+        // 陷阱在停止前已经买好
+        loadout::set_current(Some(Loadout::assume_bought(EQUIPPED_TRAPS)?));
+        Ok(())
+    }
+
+    fn setup(&mut self, _ctx: &mut Ctx, first: u32) -> Result<()> {
+        // 波次 1 的购买陷阱
+        if first > 1 {
+            buy_traps_ordered(EQUIPPED_TRAPS)?;
+        }
+        Ok(())
+    }
+
+    fn wait_timeout(&self) -> Option<Duration> {
+        Some(WAIT_TIMEOUT)
+    }
+
+    fn on_wave(&mut self, _ctx: &mut Ctx, wave: u32) -> Result<()> {
+        match wave {
+            1 => wave_1(),
+            2 => wave_2(),
+            3 => wave_3(),
+            4 => wave_4(),
+            5 => wave_5(),
+            6 => wave_6(),
+            7 => wave_7(),
+            8 => wave_8(),
+            9 => wave_9(),
+            _ => Ok(()),
+        }
+    }
+}
 
 // ===== 开始游戏 =====
 
-pub fn start_game() -> Result<()> {
+/// `skip_intro` 为 false 时不等待 "长按跳过"，直接等开场动画播完
+///
+/// 等待 "长按跳过" 和波次 1 各自最多 `timeout`（None 时使用默认超时）
+pub fn start_game(skip_intro: bool, timeout: Option<Duration>) -> Result<()> {
     println!("[大厦:炼狱] 开始游戏...");
 
     // 查找并设置游戏窗口
@@ -40,15 +110,15 @@ pub fn start_game() -> Result<()> {
     setup_window(hwnd)?;
 
     // 1. 全屏 OCR，确认在正确界面
-    let (fx, fy, fw, fh) = full_screen_region();
-    let mut results = ocr_screen(fx, fy, fw, fh, false, IS_DEBUG)?;
+    let screen = full_screen_region();
+    let mut results = ocr_screen(screen, false, IS_DEBUG)?;
 
     // 如果出现"挑战模式"，点击切换到经典模式
     if find_text_contains(&results, "挑战模式").is_some() {
         println!("[大厦:炼狱] 检测到 '挑战模式'，切换到经典模式");
         click_at(dev_x(2906), dev_y(443));
         thread::sleep(Duration::from_millis(500));
-        results = ocr_screen(fx, fy, fw, fh, false, IS_DEBUG)?;
+        results = ocr_screen(screen, false, IS_DEBUG)?;
     }
 
     if find_text_contains(&results, "联盟大厦").is_none()
@@ -69,7 +139,7 @@ pub fn start_game() -> Result<()> {
     }
 
     // 3. 判断是否有"创建房间"，有则点击"单人挑战"
-    let results = ocr_screen(fx, fy, fw, fh, false, IS_DEBUG)?;
+    let results = ocr_screen(screen, false, IS_DEBUG)?;
     if find_text_contains(&results, "创建房间").is_some() {
         if let Some(r) = find_text_contains(&results, "单人挑战") {
             let cx = dev_x(2665);
@@ -81,7 +151,7 @@ pub fn start_game() -> Result<()> {
     }
 
     // 4. 再次判断，没有"创建房间"则点击"开始"
-    let results = ocr_screen(fx, fy, fw, fh, false, IS_DEBUG)?;
+    let results = ocr_screen(screen, false, IS_DEBUG)?;
     if find_text_contains(&results, "创建房间").is_none() {
         if let Some(r) = find_text_contains(&results, "开始") {
             let (cx, cy) = r.center();
@@ -92,24 +162,34 @@ pub fn start_game() -> Result<()> {
     }
 
     // 5. 等待出现"长按跳过"，然后长按空格跳过
-    println!("[大厦:炼狱] 等待 '长按跳过'...");
-    loop {
-        if should_stop() {
-            println!("[STOP] start_game: 检测到停止信号");
-            return Ok(());
-        }
-        let results = ocr_screen(fx, fy, fw, fh, false, IS_DEBUG)?;
-        if find_text_contains(&results, "跳过").is_some() {
-            println!("[大厦:炼狱] 找到 '跳过'，长按空格");
-            press_key(VK_SPACE, 3.0);
-            break;
+    if skip_intro {
+        println!("[大厦:炼狱] 等待 '长按跳过'...");
+        let deadline = Deadline::new("'长按跳过'", timeout);
+        loop {
+            if should_stop() {
+                println!("[STOP] start_game: 检测到停止信号");
+                return Ok(());
+            }
+            deadline.check()?;
+            let results = ocr_screen(screen, false, IS_DEBUG)?;
+            if find_text_contains(&results, "跳过").is_some() {
+                println!("[大厦:炼狱] 找到 '跳过'，长按空格");
+                press_key(VK_SPACE, 3.0);
+                break;
+            }
+            thread::sleep(Duration::from_secs(1));
         }
-        thread::sleep(Duration::from_secs(1));
     }
 
     // 6. 等待波次为 1
     println!("[大厦:炼狱] 等待波次 1...");
-    wait_wave(1)?;
+    wait_wave_with(
+        1,
+        WaitOptions {
+            timeout,
+            ..WaitOptions::default()
+        },
+    )?;
     println!("[大厦:炼狱] 游戏开始！");
 
     Ok(())
@@ -215,37 +295,3 @@ pub fn wave_9() -> Result<()> {
 
     Ok(())
 }
-
-// ===== 执行所有波次 =====
-
-/// 调试时注释掉不需要的波次，只跑某一段。
-pub fn run_all_waves() -> Result<()> {
-    wave_1()?;
-
-    wait_wave(2)?;
-    wave_2()?;
-
-    wait_wave(3)?;
-    wave_3()?;
-
-    wait_wave(4)?;
-    wave_4()?;
-
-    wait_wave(5)?;
-    wave_5()?;
-
-    wait_wave(6)?;
-    wave_6()?;
-
-    wait_wave(7)?;
-    wave_7()?;
-
-    wait_wave(8)?;
-    wave_8()?;
-
-    wait_wave(9)?;
-    wave_9()?;
-
-    wait_for_game_end()?;
-    Ok(())
-}
@@ -3,21 +3,30 @@
 //! 包含所有版本共用的函数
 
 use anyhow::{Context, Result};
+use std::sync::Mutex;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use windows::Win32::Foundation::HWND;
 use windows::Win32::UI::WindowsAndMessaging::{
     FindWindowW, MoveWindow,
 };
 
+use super::debugger::{self, Checkpoint};
+use super::hotbar;
+use super::interrupt;
+use super::loadout::{self, Loadout};
+use super::shop;
 use crate::input::{
     click_at, get_vk_code, left_click, move_to, press_key, send_relative, tap_key, VK_5, VK_6,
     VK_G, VK_N, VK_SPACE,
 };
-use crate::monitor;
+use crate::monitor::{self, Reading, WatchValue};
 use crate::ocr::{clear_frame_cache, find_text_contains, ocr_screen};
-use crate::screen::{full_screen_region, get_screen_resolution, scale_region, scale_x, scale_y};
+use crate::screen::{
+    full_screen_region, get_screen_resolution, scale_region, scale_x, scale_y, ScreenPoint,
+};
 use crate::stop_flag::should_stop;
+use crate::timeline;
 
 /// 移动基础值
 pub const MOVE_VALUE: i32 = 22;
@@ -53,6 +62,11 @@ pub fn setup_window(hwnd: HWND) -> Result<()> {
 
 /// 开始游戏 - difficulty 参数指定要点击的难度文字（如 "困难"、"炼狱"、"普通"）
 pub fn start_game_with_difficulty(difficulty: &str) -> Result<()> {
+    start_game_with_timeout(difficulty, None)
+}
+
+/// 开始游戏，等待游戏开始最多 `timeout`（None 时使用默认超时）
+pub fn start_game_with_timeout(difficulty: &str, timeout: Option<Duration>) -> Result<()> {
     println!("[startGame] 查找游戏窗口...");
 
     let hwnd = find_game_window().context("未找到游戏窗口 '逆战：未来'")?;
@@ -60,8 +74,8 @@ pub fn start_game_with_difficulty(difficulty: &str) -> Result<()> {
 
     // 设置窗口
     setup_window(hwnd)?;
-    let (rx, ry, rw, rh) = scale_region(84, 230, 393, 61);
-    let results = ocr_screen(rx, ry, rw, rh, false, IS_DEBUG)?;
+    let region = scale_region(84, 230, 393, 61);
+    let results = ocr_screen(region, false, IS_DEBUG)?;
 
     // 判断如果不是空间站，则停止
     if find_text_contains(&results, "空间站").is_none() {
@@ -69,8 +83,8 @@ pub fn start_game_with_difficulty(difficulty: &str) -> Result<()> {
     }
 
     // OCR 识别屏幕
-    let (rx, ry, rw, rh) = scale_region(1182, 0, 738, 1080);
-    let results = ocr_screen(rx, ry, rw, rh, false, IS_DEBUG)?;
+    let region = scale_region(1182, 0, 738, 1080);
+    let results = ocr_screen(region, false, IS_DEBUG)?;
 
     for result in &results {
         if should_stop() {
@@ -104,8 +118,8 @@ pub fn start_game_with_difficulty(difficulty: &str) -> Result<()> {
         }
     }
 
-    let (rx, ry, rw, rh) = scale_region(674, 585, 570, 140);
-    let results = ocr_screen(rx, ry, rw, rh, false, IS_DEBUG)?;
+    let region = scale_region(674, 585, 570, 140);
+    let results = ocr_screen(region, false, IS_DEBUG)?;
     for result in &results {
         if should_stop() {
             println!("[STOP] startGame: 检测到停止信号");
@@ -114,7 +128,8 @@ pub fn start_game_with_difficulty(difficulty: &str) -> Result<()> {
 
         let (center_x, center_y) = result.center();
 
-        if result.text.contains("今日不再提醒") {
+        // 弹窗处理器在运行时由它处理，避免重复点击取消勾选
+        if result.text.contains("今日不再提醒") && !interrupt::is_running() {
             println!("[startGame] 找到 '今日不再提醒'，点击");
             click_at(scale_x(898), scale_y(609));
             thread::sleep(Duration::from_millis(200));
@@ -136,14 +151,16 @@ pub fn start_game_with_difficulty(difficulty: &str) -> Result<()> {
 
     // 循环等待游戏开始
     println!("[startGame] 等待游戏开始...");
+    let deadline = Deadline::new("游戏开始", timeout);
     loop {
         if should_stop() {
             println!("[STOP] startGame: 检测到停止信号");
             break;
         }
+        deadline.check()?;
 
-        let (fx, fy, fw, fh) = full_screen_region();
-        let results = ocr_screen(fx, fy, fw, fh, false, IS_DEBUG)?;
+        let screen = full_screen_region();
+        let results = ocr_screen(screen, false, IS_DEBUG)?;
 
         let found = results
             .iter()
@@ -161,103 +178,110 @@ pub fn start_game_with_difficulty(difficulty: &str) -> Result<()> {
 }
 
 /// 购买陷阱 - 默认顺序：防空导弹, 自修复磁暴塔, 破坏者, 修理站
-pub fn buy_traps() -> Result<()> {
+pub fn buy_traps() -> Result<Loadout> {
     buy_traps_ordered(&["防空导弹", "自修复磁暴塔", "破坏者", "修理站"])
 }
 
 /// 按指定顺序购买陷阱
 ///
-/// 购买顺序决定热键分配：第一个→4键, 第二个→5键, 第三个→6键, 第四个→7键
+/// 购买顺序决定热键分配：第一个→4键, 第二个→5键, 第三个→6键, 第四个→7键（见 `Loadout`）
+///
+/// 逻辑：打开商店（默认"全部"页面），按顺序逐个购买（见 `shop::buy`）。
+/// 当前页面找不到某个陷阱时先向下翻页，再依次切换到"地面"、"墙面"页面查找；
+/// 购买后确认陷阱进入了装备栏。
 ///
-/// 逻辑：打开商店（默认"全部"页面），按顺序逐个购买。
-/// 如果当前页面找不到某个陷阱，依次切换到"地面"、"墙面"页面查找。
-pub fn buy_traps_ordered(trap_names: &[&str]) -> Result<()> {
+/// 关闭商店后读取快捷栏（`hotbar::verify`），确认每个热键上是预期的陷阱。
+///
+/// # Returns
+/// 购买结果（找不到或没有进入装备栏的陷阱标记为未购买，不占热键），同时记为本回合的装备，
+/// 之后 `place_trap` / `upgrade_trap` 可以直接用陷阱名称。
+/// 快捷栏与购买结果不符时返回 `HotbarError`
+pub fn buy_traps_ordered(trap_names: &[&str]) -> Result<Loadout> {
+    let mut loadout = Loadout::new(trap_names)?;
     if should_stop() {
         println!("[STOP] buy_traps: 检测到停止信号，跳过");
-        return Ok(());
+        return Ok(loadout);
     }
 
+    if !debugger::checkpoint(Checkpoint::new("buy_traps", format!("{:?}", trap_names))) {
+        return Ok(loadout);
+    }
     println!("[buy_traps] 打开商店，购买顺序: {:?}", trap_names);
-    tap_key(VK_N);
-    thread::sleep(Duration::from_secs(1));
+    shop::open()?;
 
-    if should_stop() {
-        tap_key(VK_N);
-        return Ok(());
-    }
+    let result = buy_into(&mut loadout, trap_names);
 
-    let (fx, fy, fw, fh) = full_screen_region();
-    let tabs = ["地面", "墙面"];
+    // 关闭商店
+    shop::close();
+    println!("[buy_traps] 装备: {}", loadout);
+    loadout::set_current(Some(loadout.clone()));
+    result?;
 
+    if !should_stop() {
+        if let Err(e) = hotbar::verify(&loadout) {
+            println!("[buy_traps] 快捷栏检查失败: {:#}", e);
+            timeline::step("hotbar_mismatch", format!("{:#}", e));
+            return Err(e);
+        }
+    }
+    Ok(loadout)
+}
+
+/// 在已打开的商店中按顺序购买，买到的陷阱标记到 `loadout`（内部辅助）
+fn buy_into(loadout: &mut Loadout, trap_names: &[&str]) -> Result<()> {
     for trap_name in trap_names {
         if should_stop() {
-            tap_key(VK_N);
             return Ok(());
         }
-
-        let mut found = false;
-
-        // 先在当前页面找
-        let results = ocr_screen(fx, fy, fw, fh, false, IS_DEBUG)?;
-        if let Some(result) = find_text_contains(&results, trap_name) {
-            println!("[buy_traps] 在当前页面找到 '{}'，购买", trap_name);
-            buy_trap_click(result.center());
-            found = true;
+        if shop::buy(trap_name)? {
+            loadout.mark_bought(trap_name);
         }
+    }
+    Ok(())
+}
 
-        // 当前页面没找到，依次切换"地面"、"墙面"
-        if !found {
-            for tab in &tabs {
-                if should_stop() {
-                    tap_key(VK_N);
-                    return Ok(());
-                }
-
-                let results = ocr_screen(fx, fy, fw, fh, false, IS_DEBUG)?;
-                if let Some(tab_result) = find_text_contains(&results, tab) {
-                    println!("[buy_traps] 切换到 '{}' 页面", tab);
-                    let (tx, ty) = tab_result.center();
-                    click_at(tx, ty);
-                    thread::sleep(Duration::from_millis(500));
-
-                    let results = ocr_screen(fx, fy, fw, fh, false, IS_DEBUG)?;
-                    if let Some(result) = find_text_contains(&results, trap_name) {
-                        println!("[buy_traps] 在 '{}' 页面找到 '{}'，购买", tab, trap_name);
-                        buy_trap_click(result.center());
-                        found = true;
-                        break;
-                    }
-                }
-            }
+/// 检测是否已在对局中（中途停止后重新开始时），返回当前波次
+///
+/// 先等待监控读到本回合的波次（最多 `wait`），再按 N 打开商店，
+/// 出现 "地面" / "墙面" 页签才算确认（随后关闭商店）。不在对局中时返回 None。
+///
+/// 本回合须以 `monitor::begin_round_resumable` 开始，否则第 N 波会被当作残留值拒绝。
+pub fn detect_in_round(wait: Duration) -> Result<Option<u32>> {
+    let round = monitor::round_generation();
+    let deadline = Instant::now() + wait;
+    let wave = loop {
+        if should_stop() {
+            return Ok(None);
         }
-
-        if !found {
-            println!("[buy_traps] 未找到 '{}', 跳过", trap_name);
+        let reading = monitor::wave_reading().filter(|r| r.generation == round && r.value > 0);
+        if let Some(reading) = reading {
+            break reading.value;
         }
-    }
+        if Instant::now() >= deadline {
+            println!("[detect_in_round] 未读到波次，不在对局中");
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(200));
+    };
 
-    // 关闭商店
     tap_key(VK_N);
-    Ok(())
-}
+    thread::sleep(Duration::from_secs(1));
+    if !shop::is_open()? {
+        println!("[detect_in_round] 读到波次 {} 但商店未响应，不在对局中", wave);
+        return Ok(None);
+    }
+    shop::close();
 
-/// 点击购买陷阱（内部辅助）
-fn buy_trap_click((center_x, center_y): (i32, i32)) {
-    move_to(center_x + scale_x(50), center_y + scale_y(50));
-    thread::sleep(Duration::from_millis(300));
-    left_click();
-    thread::sleep(Duration::from_millis(300));
-    left_click();
-    thread::sleep(Duration::from_millis(300));
-    left_click();
-    thread::sleep(Duration::from_millis(500));
+    println!("[detect_in_round] 已在对局中，当前波次 {}", wave);
+    timeline::step("resume", format!("波次 {}", wave));
+    Ok(Some(wave))
 }
 
-/// 批量放置陷阱（坐标为 1920x1080 基准，自动缩放到实际分辨率）
+/// 批量放置陷阱（`BasePoint` / `DevPoint` 自动缩放到实际分辨率）
 ///
 /// 内部循环调用 place_trap，每次放置前检查停止信号。
-pub fn place_traps(positions: &[(i32, i32)], trap_key: &str) -> Result<()> {
-    for (i, &(bx, by)) in positions.iter().enumerate() {
+pub fn place_traps<P: Into<ScreenPoint> + Copy>(positions: &[P], trap: &str) -> Result<()> {
+    for (i, &at) in positions.iter().enumerate() {
         if should_stop() {
             println!(
                 "[STOP] place_traps: 第{}/{}个时停止",
@@ -266,23 +290,30 @@ pub fn place_traps(positions: &[(i32, i32)], trap_key: &str) -> Result<()> {
             );
             return Ok(());
         }
-        place_trap(scale_x(bx), scale_y(by), trap_key)?;
+        place_trap(at, trap)?;
     }
     Ok(())
 }
 
 /// 等待游戏结束
 pub fn wait_for_game_end() -> Result<()> {
+    wait_for_game_end_with(None)
+}
+
+/// 等待游戏结束，最多 `timeout`（None 时使用默认超时）
+pub fn wait_for_game_end_with(timeout: Option<Duration>) -> Result<()> {
     println!("[wait_for_game_end] 等待游戏结束...");
 
+    let deadline = Deadline::new("游戏结束", timeout);
     loop {
         if should_stop() {
             println!("[STOP] wait_for_game_end: 检测到停止信号");
             break;
         }
+        deadline.check()?;
 
-        let (fx, fy, fw, fh) = full_screen_region();
-        let results = ocr_screen(fx, fy, fw, fh, false, IS_DEBUG)?;
+        let screen = full_screen_region();
+        let results = ocr_screen(screen, false, IS_DEBUG)?;
 
         // 检测游戏结束
         let game_ended = results.iter().any(|r| {
@@ -294,8 +325,9 @@ pub fn wait_for_game_end() -> Result<()> {
             break;
         }
 
-        // 处理"返回游戏"弹窗
-        if let Some(result) = find_text_contains(&results, "返回游戏") {
+        // 处理"返回游戏"弹窗（弹窗处理器未运行时）
+        let popup = find_text_contains(&results, "返回游戏").filter(|_| !interrupt::is_running());
+        if let Some(result) = popup {
             let (x, y) = result.center();
             move_to(x + scale_x(50), y + scale_y(50));
             thread::sleep(Duration::from_millis(200));
@@ -317,7 +349,9 @@ pub fn wait_for_game_end() -> Result<()> {
             let filename = format!("game_end_{}.png", timestamp);
 
             if let Ok(img) = crate::screen::capture_fullscreen() {
-                let _ = crate::screen::save_screenshot(&img, &filename);
+                if crate::screen::save_screenshot(&img, &filename).is_ok() {
+                    timeline::screenshot(&filename);
+                }
             }
             break;
         }
@@ -342,7 +376,7 @@ pub fn clear_cache() {
 }
 
 /// 简易随机：返回 [min, max] 范围内的整数
-fn random_range(min: u64, max: u64) -> u64 {
+pub(super) fn random_range(min: u64, max: u64) -> u64 {
     let nanos = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
@@ -351,7 +385,7 @@ fn random_range(min: u64, max: u64) -> u64 {
 }
 
 /// 空闲防挂机：随机等待 3-5 秒后按一次空格
-fn idle_action() {
+pub(super) fn idle_action() {
     let wait_secs = random_range(3, 5);
     thread::sleep(Duration::from_secs(wait_secs));
     if !should_stop() {
@@ -359,44 +393,256 @@ fn idle_action() {
     }
 }
 
+/// 等待超时
+///
+/// 作为 `anyhow::Error` 返回，回合循环用 `downcast_ref::<WaitTimeout>()` 识别后恢复。
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("等待{what}超时（{} 秒）", .timeout.as_secs())]
+pub struct WaitTimeout {
+    /// 等待的内容（如 "波次 >= 3"）
+    pub what: String,
+    pub timeout: Duration,
+}
+
+/// 等待函数的默认超时（由回合循环按策略的 `wait_timeout` 设置，None = 不限时）
+static DEFAULT_TIMEOUT: Mutex<Option<Duration>> = Mutex::new(None);
+
+/// 设置等待函数的默认超时
+pub fn set_default_timeout(timeout: Option<Duration>) {
+    if let Ok(mut t) = DEFAULT_TIMEOUT.lock() {
+        *t = timeout;
+    }
+}
+
+/// 等待函数的默认超时
+pub fn default_timeout() -> Option<Duration> {
+    DEFAULT_TIMEOUT.lock().ok().and_then(|t| *t)
+}
+
+/// 等待循环的截止时间
+///
+/// # Example
+/// ```ignore
+/// let deadline = Deadline::new("'跳过'", None); // None = 使用默认超时
+/// loop {
+///     deadline.check()?;
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Deadline {
+    what: String,
+    timeout: Option<Duration>,
+    start: Instant,
+}
+
+impl Deadline {
+    /// `timeout` 为 None 时使用 `default_timeout()`
+    pub fn new(what: impl Into<String>, timeout: Option<Duration>) -> Self {
+        Self {
+            what: what.into(),
+            timeout: timeout.or_else(default_timeout),
+            start: Instant::now(),
+        }
+    }
+
+    /// 超时返回 `WaitTimeout`
+    pub fn check(&self) -> Result<(), WaitTimeout> {
+        match self.timeout {
+            Some(timeout) if self.start.elapsed() >= timeout => Err(WaitTimeout {
+                what: self.what.clone(),
+                timeout,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 等待选项
+#[derive(Debug, Clone, Copy)]
+pub struct WaitOptions {
+    /// 监控读数的最低置信度（0.0 = 不限制），低于此值的读数不算满足条件
+    pub min_confidence: f32,
+    /// 超时时间，None 时使用策略的默认超时
+    pub timeout: Option<Duration>,
+}
+
+impl Default for WaitOptions {
+    fn default() -> Self {
+        Self {
+            min_confidence: 0.0,
+            timeout: None,
+        }
+    }
+}
+
 /// 等待金币达到指定数额
 ///
 /// 循环检查后台监控的金币数，直到达到目标或收到停止信号。
 /// 等待期间每 3-5 秒按一次空格防止挂机检测。
 pub fn wait_gold(amount: i64) -> Result<()> {
-    println!("[wait_gold] 等待金币 >= {}", amount);
+    wait_gold_with(amount, WaitOptions::default())
+}
+
+/// 等待金币达到指定数额（可要求读数置信度）
+///
+/// 只接受本回合（调用时的回合代数）的读数；等待期间回合切换会返回错误。
+/// 开始时按当前收入速率打印预计等待时间，等待期间目标显示在 GUI 上。
+pub fn wait_gold_with(amount: i64, opts: WaitOptions) -> Result<()> {
+    match (monitor::gold_eta(amount), monitor::income_rate()) {
+        (Some(eta), Some(rate)) => println!(
+            "[wait_gold] 收入约 {:.0}/秒，预计 {:.0} 秒后达到 {}",
+            rate,
+            eta.as_secs_f64(),
+            amount
+        ),
+        _ => println!("[wait_gold] 收入速率未知，无法估算等待时间"),
+    }
+
+    monitor::set_gold_target(Some(amount));
+    let result = wait_reading(
+        "wait_gold",
+        &format!("金币 >= {}", amount),
+        opts,
+        monitor::gold_reading,
+        |gold| *gold >= amount,
+    );
+    monitor::set_gold_target(None);
+    result
+}
+
+/// 等待波次到达指定值
+///
+/// 循环检查后台监控的波次数，直到达到目标或收到停止信号。
+/// 等待期间每 3-5 秒按一次空格防止挂机检测。
+pub fn wait_wave(wave: u32) -> Result<()> {
+    wait_wave_with(wave, WaitOptions::default())
+}
+
+/// 等待波次到达指定值（可要求读数置信度）
+///
+/// 只接受本回合（调用时的回合代数）的读数；等待期间回合切换会返回错误。
+pub fn wait_wave_with(wave: u32, opts: WaitOptions) -> Result<()> {
+    wait_reading(
+        "wait_wave",
+        &format!("波次 >= {}", wave),
+        opts,
+        monitor::wave_reading,
+        |current| *current >= wave,
+    )
+}
+
+/// 等待指定监控项满足条件
+///
+/// # Example
+/// ```ignore
+/// // 基地血量低于 30% 时补陷阱
+/// wait_until("core_hp", |v| v.as_ratio().is_some_and(|r| r < 0.3))?;
+/// ```
+pub fn wait_until(name: &str, pred: impl Fn(&WatchValue) -> bool) -> Result<()> {
+    wait_until_with(name, WaitOptions::default(), pred)
+}
+
+/// 等待指定监控项满足条件（可要求读数置信度）
+///
+/// 与 `wait_wave_with` 相同，只接受本回合的读数；等待期间回合切换会返回错误。
+pub fn wait_until_with(
+    name: &str,
+    opts: WaitOptions,
+    pred: impl Fn(&WatchValue) -> bool,
+) -> Result<()> {
+    wait_reading(
+        "wait_until",
+        &format!("监控项 '{}' 满足条件", name),
+        opts,
+        || monitor::reading(name),
+        pred,
+    )
+}
+
+/// 等待下一波倒计时剩余不超过 `secs` 秒（需要在监控配置中设置倒计时区域）
+///
+/// 用于在准备阶段的固定时间点放置陷阱。倒计时按最近一次读数外推，每 200ms 检查一次，
+/// 不执行防挂机空格。只在本回合内等待，回合切换会返回错误。
+///
+/// # Example
+/// ```ignore
+/// wait_until_wave_countdown(10)?; // 离下一波还有 10 秒时开始布置
+/// place_trap(BasePoint::new(960, 540), "4")?;
+/// ```
+pub fn wait_until_wave_countdown(secs: u32) -> Result<()> {
+    println!("[wait_countdown] 等待下一波倒计时 <= {} 秒", secs);
+    timeline::step("wait_countdown", format!("<= {} 秒", secs));
+    let round = monitor::round_generation();
+    let target = Duration::from_secs(secs as u64);
+    let deadline = Deadline::new(format!("倒计时 <= {} 秒", secs), None);
     loop {
         if should_stop() {
-            println!("[STOP] wait_gold: 检测到停止信号");
+            println!("[STOP] wait_countdown: 检测到停止信号");
             return Ok(());
         }
+        deadline.check()?;
 
-        let gold = monitor::current_gold();
-        if gold >= amount {
-            println!("[wait_gold] 金币 {} >= {}，继续", gold, amount);
-            return Ok(());
+        if monitor::round_generation() != round {
+            anyhow::bail!("等待倒计时 <= {} 秒期间回合已切换", secs);
         }
 
-        idle_action();
+        if let Some(remaining) = monitor::time_to_next_wave() {
+            if remaining <= target {
+                println!(
+                    "[wait_countdown] 剩余 {:.1} 秒 <= {} 秒，继续",
+                    remaining.as_secs_f64(),
+                    secs
+                );
+                timeline::step(
+                    "wait_countdown_done",
+                    format!("剩余 {:.1} 秒", remaining.as_secs_f64()),
+                );
+                return Ok(());
+            }
+        }
+
+        thread::sleep(Duration::from_millis(200));
     }
 }
 
-/// 等待波次到达指定值
+/// 等待监控读数满足条件的公共循环
 ///
-/// 循环检查后台监控的波次数，直到达到目标或收到停止信号。
-/// 等待期间每 3-5 秒按一次空格防止挂机检测。
-pub fn wait_wave(wave: u32) -> Result<()> {
-    println!("[wait_wave] 等待波次 >= {}", wave);
+/// `desc` 描述等待的条件（用于日志和错误信息）。
+fn wait_reading<T: std::fmt::Display>(
+    tag: &str,
+    desc: &str,
+    opts: WaitOptions,
+    read: impl Fn() -> Option<Reading<T>>,
+    pred: impl Fn(&T) -> bool,
+) -> Result<()> {
+    println!("[{}] 等待{}", tag, desc);
+    timeline::step(tag, desc);
+    let round = monitor::round_generation();
+    let deadline = Deadline::new(desc, opts.timeout);
     loop {
         if should_stop() {
-            println!("[STOP] wait_wave: 检测到停止信号");
+            println!("[STOP] {}: 检测到停止信号", tag);
             return Ok(());
         }
+        deadline.check()?;
 
-        let current = monitor::current_wave();
-        if current >= wave {
-            println!("[wait_wave] 波次 {} >= {}，继续", current, wave);
-            return Ok(());
+        if monitor::round_generation() != round {
+            anyhow::bail!("等待{}期间回合已切换", desc);
+        }
+
+        if let Some(reading) = read() {
+            if reading.generation == round
+                && reading.confidence >= opts.min_confidence
+                && pred(&reading.value)
+            {
+                println!(
+                    "[{}] 当前 {}，{} (置信度 {:.2})，继续",
+                    tag, reading.value, desc, reading.confidence
+                );
+                timeline::step(&format!("{}_done", tag), reading.value.to_string());
+                return Ok(());
+            }
         }
 
         idle_action();
@@ -406,31 +652,268 @@ pub fn wait_wave(wave: u32) -> Result<()> {
 /// 放置陷阱（选择陷阱快捷键 + 点击坐标）
 ///
 /// # Arguments
-/// * `x`, `y` - 屏幕坐标
-/// * `trap_key` - 陷阱快捷键字符串（如 "4", "5", "6", "7"）
-pub fn place_trap(x: i32, y: i32, trap_key: &str) -> Result<()> {
+/// * `at` - 放置位置（`ScreenPoint`，或自动缩放的 `BasePoint` / `DevPoint`）
+/// * `trap` - 陷阱名称（按本回合装备换成热键，见 `Loadout`）或快捷键字符串（如 "4", "5"）
+///
+/// 按下热键后读取快捷栏确认选中了正确的格子（使用陷阱名称时还确认格子上的陷阱），
+/// 不符时不点击，返回 `HotbarError`。
+/// 陷阱不在装备中或没有购买成功时返回 `LoadoutError`
+pub fn place_trap(at: impl Into<ScreenPoint>, trap: &str) -> Result<()> {
+    place_trap_clicked(at.into(), trap).map(|_| ())
+}
+
+/// `place_trap`，返回是否点击了（停止信号、调试器跳过时为 false）
+fn place_trap_clicked(at: ScreenPoint, trap: &str) -> Result<bool> {
+    let ScreenPoint { x, y } = at;
     if should_stop() {
-        return Ok(());
+        return Ok(false);
     }
 
-    let vk = get_vk_code(trap_key).context(format!("未知的陷阱快捷键: {}", trap_key))?;
-    println!("[place_trap] 放置陷阱 key={} @ ({}, {})", trap_key, x, y);
+    let (trap_key, vk) = trap_vk(trap)?;
+    if !debugger::checkpoint(Checkpoint::new("place_trap", trap_label(trap, &trap_key)).at(x, y)) {
+        return Ok(false);
+    }
+    let label = trap_label(trap, &trap_key);
+    println!("[place_trap] 放置陷阱 {} @ ({}, {})", label, x, y);
+    timeline::step("place_trap", format!("key={} @ ({}, {})", trap_key, x, y));
 
     tap_key(vk);
     thread::sleep(Duration::from_millis(1000));
+    let expected = (trap != trap_key).then_some(trap);
+    if let Err(e) = hotbar::confirm_selected(&trap_key, expected) {
+        println!("[place_trap] 快捷栏检查失败: {:#}", e);
+        timeline::step("hotbar_mismatch", format!("{:#}", e));
+        return Err(e);
+    }
+    // 移动到点击完成期间持有输入闸门，避免弹窗处理器移走鼠标
+    let _action = crate::pause::action();
     move_to(x, y);
     thread::sleep(Duration::from_millis(1000));
     left_click();
     thread::sleep(Duration::from_millis(200));
     left_click();
     thread::sleep(Duration::from_millis(300));
-    Ok(())
+    Ok(true)
+}
+
+/// 确认放置的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaceOutcome {
+    /// 放置成功，`cost` 为金币减少量，`at` 为实际点击的位置
+    Placed { cost: i64, at: ScreenPoint },
+    /// 金币不足（放置前已不足，或点击后金币没变且少于价格）
+    NotEnoughGold { gold: i64, price: i64 },
+    /// 所有位置都没能放下（金币没有减少）
+    InvalidSpot,
+    /// 收到停止信号或调试器跳过，没有放置
+    Skipped,
+}
+
+/// 确认放置的选项
+#[derive(Debug, Clone)]
+pub struct PlaceOptions {
+    /// 陷阱价格，None 时金币有减少就算放置成功
+    pub price: Option<i64>,
+    /// 原位置放不下时依次尝试的偏移（1920x1080 基准），原位置总是第一个
+    ///
+    /// 只在知道价格时使用：没有价格时收入可能抵掉放置的花费，重试会重复放置
+    pub offsets: Vec<(i32, i32)>,
+    /// 每次点击后等待金币读数变化的时间，None 时按金币监控的检测间隔和中值窗口计算
+    pub settle: Option<Duration>,
+}
+
+impl Default for PlaceOptions {
+    fn default() -> Self {
+        Self {
+            price: None,
+            offsets: vec![(15, 0), (-15, 0), (0, 15), (0, -15)],
+            settle: None,
+        }
+    }
+}
+
+/// 花费金币的操作（放置、升级）前后的金币变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum GoldSpend {
+    /// 金币减少了这么多
+    Spent(i64),
+    /// 金币没有减少，且少于价格
+    NotEnoughGold { gold: i64, price: i64 },
+    /// 金币没有减少
+    NoChange,
+}
+
+/// 操作前后的金币判断是否花费成功（`after` 为 None 表示读数没有变化）
+///
+/// 金币减少至少价格的一半算成功（期间的收入会抵掉一部分），没有价格时有减少就算成功；
+/// 否则金币少于价格为金币不足。
+pub(super) fn judge_spend(before: i64, after: Option<i64>, price: Option<i64>) -> GoldSpend {
+    let drop = after.map_or(0, |after| before - after);
+    let enough = match price {
+        Some(price) => drop * 2 >= price,
+        None => drop > 0,
+    };
+    match price {
+        _ if enough => GoldSpend::Spent(drop),
+        Some(price) if before < price => GoldSpend::NotEnoughGold {
+            gold: before,
+            price,
+        },
+        _ => GoldSpend::NoChange,
+    }
+}
+
+/// 放置前后的金币判断在 `at` 点击的结果，没有花费即为放不下
+fn judge_placement(
+    before: i64,
+    after: Option<i64>,
+    price: Option<i64>,
+    at: ScreenPoint,
+) -> PlaceOutcome {
+    match judge_spend(before, after, price) {
+        GoldSpend::Spent(cost) => PlaceOutcome::Placed { cost, at },
+        GoldSpend::NotEnoughGold { gold, price } => PlaceOutcome::NotEnoughGold { gold, price },
+        GoldSpend::NoChange => PlaceOutcome::InvalidSpot,
+    }
+}
+
+/// 操作后等待金币读数变化的时间：指定值优先，否则按金币监控计算（监控未启动时 3 秒）
+pub(super) fn gold_settle(settle: Option<Duration>) -> Duration {
+    settle
+        .or_else(monitor::gold_settle_time)
+        .unwrap_or(Duration::from_secs(3))
+}
+
+/// 本回合最新的金币读数
+pub(super) fn round_gold(round: u64) -> Option<Reading<i64>> {
+    monitor::gold_reading().filter(|r| r.generation == round)
+}
+
+/// 等待 `since` 之后的金币读数，最多 `settle`；金币少于 `before` 时立即返回
+///
+/// 等待结束时还没有减少的，返回前再读一次最新值（读数可能在等待结束后才更新）。
+///
+/// # Returns
+/// 最后一个新读数，期间没有新读数时为 None
+pub(super) fn wait_gold_drop(
+    round: u64,
+    before: i64,
+    since: Instant,
+    settle: Duration,
+) -> Option<i64> {
+    let mut after = None;
+    while since.elapsed() < settle && !should_stop() {
+        if let Some(reading) = round_gold(round).filter(|r| r.at > since) {
+            after = Some(reading.value);
+            if reading.value < before {
+                break;
+            }
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+    if !matches!(after, Some(after) if after < before) {
+        if let Some(late) = round_gold(round).filter(|r| r.value < before) {
+            println!("[gold] 金币在等待之后才减少 ({} → {})", before, late.value);
+            after = Some(late.value);
+        }
+    }
+    after
+}
+
+/// 放置陷阱并用金币变化确认（`place_trap` 的可选确认模式）
+///
+/// 放置前后读取后台监控的金币：金币减少与价格相符为放置成功；没有变化时在
+/// `opts.offsets` 的偏移位置重试（需要 `opts.price`，没有价格时不重试）。
+/// 需要金币监控正在运行，没有本回合的金币读数时返回错误。
+///
+/// # Returns
+/// `Placed` / `NotEnoughGold` / `InvalidSpot`，停止或调试器跳过时为 `Skipped`
+pub fn place_trap_verified(
+    at: impl Into<ScreenPoint>,
+    trap: &str,
+    opts: &PlaceOptions,
+) -> Result<PlaceOutcome> {
+    let at = at.into();
+    let round = monitor::round_generation();
+    let settle = gold_settle(opts.settle);
+    // 没有价格时无法区分 "放不下" 和 "收入抵掉了花费"，不换位置重试
+    let offsets = if opts.price.is_some() {
+        &opts.offsets[..]
+    } else {
+        &[]
+    };
+    let positions = std::iter::once((0, 0))
+        .chain(offsets.iter().copied())
+        .map(|(dx, dy)| at.offset(scale_x(dx), scale_y(dy)));
+
+    for pos in positions {
+        let before = round_gold(round).context("没有本回合的金币读数，无法确认放置")?;
+        if let Some(price) = opts.price {
+            if before.value < price {
+                println!("[place_trap] 金币 {} 不足 {}，不放置", before.value, price);
+                return Ok(PlaceOutcome::NotEnoughGold {
+                    gold: before.value,
+                    price,
+                });
+            }
+        }
+
+        if !place_trap_clicked(pos, trap)? {
+            return Ok(PlaceOutcome::Skipped);
+        }
+        let after = wait_gold_drop(round, before.value, Instant::now(), settle);
+        let mut outcome = judge_placement(before.value, after, opts.price, pos);
+        // 金币减少得不到价格的一半时也不再换位置重试，避免重复放置
+        if let (PlaceOutcome::InvalidSpot, Some(after)) = (outcome, after) {
+            if after < before.value {
+                outcome = PlaceOutcome::Placed {
+                    cost: before.value - after,
+                    at: pos,
+                };
+            }
+        }
+        match outcome {
+            outcome @ PlaceOutcome::Placed { cost, .. } => {
+                println!("[place_trap] 放置成功 @ {}，花费 {}", pos, cost);
+                timeline::step("place_trap_ok", format!("@ {} cost={}", pos, cost));
+                return Ok(outcome);
+            }
+            PlaceOutcome::InvalidSpot => {
+                println!("[place_trap] {} 金币没有减少，换位置重试", pos);
+            }
+            outcome => return Ok(outcome),
+        }
+        if should_stop() {
+            return Ok(PlaceOutcome::Skipped);
+        }
+    }
+
+    println!("[place_trap] 所有位置都放不下");
+    timeline::step("place_trap_invalid", format!("{} @ {}", trap, at));
+    Ok(PlaceOutcome::InvalidSpot)
+}
+
+/// 陷阱名称或快捷键换成 (快捷键, 虚拟键码)（内部辅助）
+fn trap_vk(trap: &str) -> Result<(String, u16)> {
+    let trap_key = loadout::resolve_key(trap)?;
+    let vk = get_vk_code(&trap_key).context(format!("未知的陷阱快捷键: {}", trap_key))?;
+    Ok((trap_key, vk))
+}
+
+/// 日志中的陷阱描述，如 "天网 (key=4)" / "key=4"（内部辅助）
+fn trap_label(trap: &str, trap_key: &str) -> String {
+    if trap == trap_key {
+        format!("key={}", trap_key)
+    } else {
+        format!("{} (key={})", trap, trap_key)
+    }
 }
 
 /// 鼠标移动到某个坐标，放置某个陷阱
-/// - x, y: 放置坐标
+/// - at: 放置位置
 /// - trap_key: 陷阱快捷键 (如 VK_4, VK_5 等)
-pub fn place_trap_at(x: i32, y: i32, trap_key: u16) -> Result<()> {
+pub fn place_trap_at(at: impl Into<ScreenPoint>, trap_key: u16) -> Result<()> {
+    let ScreenPoint { x, y } = at.into();
     tap_key(trap_key);
     thread::sleep(Duration::from_millis(1000));
     move_to(x, y);
@@ -445,16 +928,25 @@ pub fn place_trap_at(x: i32, y: i32, trap_key: u16) -> Result<()> {
 /// 升级陷阱（长按热键 3 秒）
 ///
 /// 陷阱的升级方式是长按对应热键，例如防空导弹是 4 键，长按 4 即升级。
-/// trap_key: 陷阱快捷键字符串（如 "4", "5", "6", "7"）
-pub fn upgrade_trap(trap_key: &str) -> Result<()> {
+/// trap: 陷阱名称（按本回合装备换成热键）或快捷键字符串（如 "4", "5", "6", "7"）
+pub fn upgrade_trap(trap: &str) -> Result<()> {
+    upgrade_trap_pressed(trap).map(|_| ())
+}
+
+/// `upgrade_trap`，返回是否按下了热键（停止信号、调试器跳过时为 false）
+pub(super) fn upgrade_trap_pressed(trap: &str) -> Result<bool> {
     if should_stop() {
-        return Ok(());
+        return Ok(false);
+    }
+    let (trap_key, vk) = trap_vk(trap)?;
+    if !debugger::checkpoint(Checkpoint::new("upgrade_trap", trap_label(trap, &trap_key))) {
+        return Ok(false);
     }
-    let vk = get_vk_code(trap_key).context(format!("未知的陷阱快捷键: {}", trap_key))?;
-    println!("[upgrade_trap] 长按 {} 升级", trap_key);
+    println!("[upgrade_trap] 长按 {} 升级", trap_label(trap, &trap_key));
+    timeline::step("upgrade_trap", format!("key={}", trap_key));
     press_key(vk, 3.0);
     thread::sleep(Duration::from_millis(500));
-    Ok(())
+    Ok(true)
 }
 
 /// 游戏动作枚举 - 用于批量执行并自动检查停止信号
@@ -471,10 +963,34 @@ pub enum GameAction {
     Click,
     /// 移动鼠标到坐标
     MoveTo(i32, i32),
+    /// 移动到坐标并点击
+    ClickAt(i32, i32),
+}
+
+impl GameAction {
+    /// 调试器检查点（步骤类型为 "press_key"、"tap_key"、"send_relative"、"sleep"、"click"、"move_to"，
+    /// `ClickAt` 为带坐标的 "click"）
+    fn checkpoint(&self) -> Checkpoint {
+        match *self {
+            Self::PressKey(vk, secs) => {
+                Checkpoint::new("press_key", format!("vk=0x{:02X} {}s", vk, secs))
+            }
+            Self::TapKey(vk) => Checkpoint::new("tap_key", format!("vk=0x{:02X}", vk)),
+            Self::SendRelative(dx, dy) => {
+                Checkpoint::new("send_relative", format!("({}, {})", dx, dy))
+            }
+            Self::Sleep(secs) => Checkpoint::new("sleep", format!("{}s", secs)),
+            Self::Click => Checkpoint::new("click", "左键"),
+            Self::MoveTo(x, y) => Checkpoint::new("move_to", format!("({}, {})", x, y)).at(x, y),
+            Self::ClickAt(x, y) => Checkpoint::new("click", format!("({}, {})", x, y)).at(x, y),
+        }
+    }
 }
 
 /// 执行动作序列，每个动作后自动检查停止信号
 ///
+/// 开启步骤调试（`debugger`）时，每个动作前经过一个检查点，被跳过的动作不执行。
+///
 /// # Returns
 /// - Ok(true) 全部执行完成
 /// - Ok(false) 检测到停止信号，提前退出
@@ -484,10 +1000,13 @@ pub fn execute_actions(actions: &[GameAction]) -> Result<bool> {
             println!("[STOP] execute_actions: 检测到停止信号");
             return Ok(false);
         }
+        if !debugger::checkpoint(action.checkpoint()) {
+            continue;
+        }
 
         match action {
             GameAction::PressKey(vk, duration) => {
-                press_key(*vk, *duration);
+                press_key(*vk, valid_secs(*duration));
             }
             GameAction::TapKey(vk) => {
                 tap_key(*vk);
@@ -496,7 +1015,7 @@ pub fn execute_actions(actions: &[GameAction]) -> Result<bool> {
                 send_relative(*dx, *dy);
             }
             GameAction::Sleep(secs) => {
-                thread::sleep(Duration::from_secs_f64(*secs));
+                thread::sleep(Duration::from_secs_f64(valid_secs(*secs)));
             }
             GameAction::Click => {
                 left_click();
@@ -504,7 +1023,71 @@ pub fn execute_actions(actions: &[GameAction]) -> Result<bool> {
             GameAction::MoveTo(x, y) => {
                 move_to(*x, *y);
             }
+            GameAction::ClickAt(x, y) => {
+                click_at(*x, *y);
+            }
         }
     }
     Ok(true)
 }
+
+/// 动作的秒数：负数和 NaN/无穷按 0 处理（脚本传入的值不经过加载时校验）
+pub(super) fn valid_secs(secs: f64) -> f64 {
+    if secs.is_finite() {
+        secs.max(0.0)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deadline() {
+        let deadline = Deadline::new("波次 >= 3", Some(Duration::ZERO));
+        let err = deadline.check().unwrap_err();
+        assert_eq!(err.what, "波次 >= 3");
+
+        // 包装成 anyhow 后仍可识别
+        let err: anyhow::Error = err.into();
+        assert!(err.downcast_ref::<WaitTimeout>().is_some());
+        assert_eq!(err.to_string(), "等待波次 >= 3超时（0 秒）");
+
+        assert!(Deadline::new("x", Some(Duration::from_secs(60))).check().is_ok());
+    }
+
+    #[test]
+    fn test_judge_placement() {
+        let at = ScreenPoint::new(100, 200);
+        assert_eq!(
+            judge_placement(3000, Some(2000), Some(1000), at),
+            PlaceOutcome::Placed { cost: 1000, at }
+        );
+        // 期间有收入，减少量略小于价格
+        assert_eq!(
+            judge_placement(3000, Some(2300), Some(1000), at),
+            PlaceOutcome::Placed { cost: 700, at }
+        );
+        assert_eq!(
+            judge_placement(3000, None, Some(1000), at),
+            PlaceOutcome::InvalidSpot
+        );
+        assert_eq!(
+            judge_placement(800, Some(850), Some(1000), at),
+            PlaceOutcome::NotEnoughGold {
+                gold: 800,
+                price: 1000
+            }
+        );
+        assert_eq!(
+            judge_placement(800, Some(790), None, at),
+            PlaceOutcome::Placed { cost: 10, at }
+        );
+        assert_eq!(
+            judge_placement(800, None, None, at),
+            PlaceOutcome::InvalidSpot
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! 策略步骤调试器
+//!
+//! 开启后，每个波次开始和每个输入动作（`place_trap`、`upgrade_trap`、`execute_actions` 的动作等）
+//! 之前都会经过一个检查点。命中断点或处于单步模式时，策略线程在检查点暂停，
+//! 截一张全屏图并标出将要点击的位置，等待 GUI 发出 "单步" / "继续" / "跳过"。
+//!
+//! 未开启时检查点只记录当前步骤，不暂停。
+
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use image::RgbImage;
+
+use crate::screen::capture_fullscreen;
+use crate::stop_flag::should_stop;
+
+/// 暂停期间检查停止信号的间隔
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 断点
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// 第 N 波开始时
+    Wave(u32),
+    /// 指定类型的步骤之前（`Checkpoint::kind`，如 "place_trap"、"click"）
+    Step(String),
+}
+
+impl Breakpoint {
+    /// 解析逗号分隔的断点列表：数字为波次，其余为步骤类型
+    ///
+    /// # Example
+    /// ```ignore
+    /// Breakpoint::parse_list("3, place_trap"); // [Wave(3), Step("place_trap")]
+    /// ```
+    pub fn parse_list(s: &str) -> Vec<Self> {
+        s.split([',', '，'])
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(|t| match t.parse() {
+                Ok(wave) => Self::Wave(wave),
+                Err(_) => Self::Step(t.to_string()),
+            })
+            .collect()
+    }
+
+    fn matches(&self, checkpoint: &Checkpoint) -> bool {
+        match self {
+            Self::Wave(wave) => checkpoint.kind == "wave" && checkpoint.wave == Some(*wave),
+            Self::Step(kind) => checkpoint.kind == *kind,
+        }
+    }
+}
+
+/// 检查点（将要执行的步骤）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    /// 步骤类型（断点按它匹配）
+    pub kind: String,
+    /// 步骤说明（GUI 显示）
+    pub detail: String,
+    /// 波次检查点的波次
+    pub wave: Option<u32>,
+    /// 将要点击/移动到的屏幕坐标
+    pub target: Option<(i32, i32)>,
+}
+
+impl Checkpoint {
+    pub fn new(kind: &str, detail: impl Into<String>) -> Self {
+        Self {
+            kind: kind.to_string(),
+            detail: detail.into(),
+            wave: None,
+            target: None,
+        }
+    }
+
+    /// 波次开始
+    pub fn wave(wave: u32) -> Self {
+        Self {
+            wave: Some(wave),
+            ..Self::new("wave", format!("波次 {}", wave))
+        }
+    }
+
+    /// 标记将要点击的屏幕坐标
+    pub fn at(mut self, x: i32, y: i32) -> Self {
+        self.target = Some((x, y));
+        self
+    }
+}
+
+impl fmt::Display for Checkpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.detail)?;
+        if let Some((x, y)) = self.target {
+            write!(f, " @ ({}, {})", x, y)?;
+        }
+        Ok(())
+    }
+}
+
+/// GUI 发出的命令
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// 执行当前步骤，在下一个检查点再暂停
+    Step,
+    /// 执行当前步骤，之后只在断点暂停
+    Continue,
+    /// 不执行当前步骤，在下一个检查点再暂停
+    Skip,
+}
+
+/// 暂停中的检查点
+#[derive(Clone)]
+pub struct Paused {
+    /// 每次暂停递增（GUI 据此判断是否需要刷新截图）
+    pub id: u64,
+    pub checkpoint: Checkpoint,
+    /// 暂停时的全屏截图（截图失败时为 None）
+    pub screenshot: Option<Arc<RgbImage>>,
+}
+
+#[derive(Default)]
+struct State {
+    enabled: bool,
+    /// 单步：每个检查点都暂停
+    stepping: bool,
+    breakpoints: Vec<Breakpoint>,
+    current: Option<Checkpoint>,
+    paused: Option<Paused>,
+    command: Option<Command>,
+    next_id: u64,
+}
+
+static STATE: Mutex<Option<State>> = Mutex::new(None);
+static WOKEN: Condvar = Condvar::new();
+
+fn with_state<T>(f: impl FnOnce(&mut State) -> T) -> T {
+    let mut guard = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    f(guard.get_or_insert_with(State::default))
+}
+
+/// 开启/关闭调试；关闭时放行正在暂停的检查点
+pub fn set_enabled(enabled: bool) {
+    with_state(|s| {
+        s.enabled = enabled;
+        s.stepping = enabled && s.stepping;
+        if !enabled && s.paused.is_some() {
+            s.command = Some(Command::Continue);
+        }
+    });
+    WOKEN.notify_all();
+}
+
+pub fn is_enabled() -> bool {
+    with_state(|s| s.enabled)
+}
+
+/// 单步模式：每个检查点都暂停
+pub fn set_stepping(stepping: bool) {
+    with_state(|s| s.stepping = stepping);
+}
+
+pub fn is_stepping() -> bool {
+    with_state(|s| s.stepping)
+}
+
+pub fn set_breakpoints(breakpoints: Vec<Breakpoint>) {
+    with_state(|s| s.breakpoints = breakpoints);
+}
+
+/// 最近经过的检查点（调试开启时记录）
+pub fn current() -> Option<Checkpoint> {
+    with_state(|s| s.current.clone())
+}
+
+/// 正在暂停的检查点
+pub fn paused() -> Option<Paused> {
+    with_state(|s| s.paused.clone())
+}
+
+/// 向暂停中的检查点发出命令（没有暂停时忽略）
+pub fn send(command: Command) {
+    with_state(|s| {
+        if s.paused.is_some() {
+            s.command = Some(command);
+        }
+    });
+    WOKEN.notify_all();
+}
+
+/// 检查点：调试开启且命中断点或单步时暂停，等待 GUI 命令
+///
+/// # Returns
+/// - true 执行这一步
+/// - false 跳过这一步（GUI 选择了 "跳过"，或暂停期间收到停止信号）
+pub fn checkpoint(checkpoint: Checkpoint) -> bool {
+    let should_pause = with_state(|s| {
+        if !s.enabled {
+            return false;
+        }
+        s.current = Some(checkpoint.clone());
+        s.stepping || s.breakpoints.iter().any(|b| b.matches(&checkpoint))
+    });
+    if !should_pause {
+        return true;
+    }
+
+    println!("[debugger] 暂停: {}", checkpoint);
+    let screenshot = capture_fullscreen().ok().map(Arc::new);
+
+    let mut guard = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    let state = guard.get_or_insert_with(State::default);
+    state.next_id += 1;
+    state.command = None;
+    state.paused = Some(Paused {
+        id: state.next_id,
+        checkpoint,
+        screenshot,
+    });
+
+    let command = loop {
+        if should_stop() {
+            break None;
+        }
+        let state = guard.get_or_insert_with(State::default);
+        if let Some(command) = state.command.take() {
+            break Some(command);
+        }
+        guard = WOKEN
+            .wait_timeout(guard, STOP_CHECK_INTERVAL)
+            .unwrap_or_else(|e| e.into_inner())
+            .0;
+    };
+
+    let state = guard.get_or_insert_with(State::default);
+    state.paused = None;
+    match command {
+        Some(Command::Step) => {
+            state.stepping = true;
+            true
+        }
+        Some(Command::Continue) => {
+            state.stepping = false;
+            true
+        }
+        Some(Command::Skip) => {
+            state.stepping = true;
+            println!("[debugger] 跳过");
+            false
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_parse_breakpoints() {
+        assert_eq!(
+            Breakpoint::parse_list("3, place_trap，5,,"),
+            vec![
+                Breakpoint::Wave(3),
+                Breakpoint::Step("place_trap".into()),
+                Breakpoint::Wave(5)
+            ]
+        );
+        assert!(Breakpoint::Wave(3).matches(&Checkpoint::wave(3)));
+        assert!(!Breakpoint::Wave(3).matches(&Checkpoint::wave(4)));
+        assert!(Breakpoint::Step("click".into()).matches(&Checkpoint::new("click", "")));
+    }
+
+    #[test]
+    fn test_checkpoint_waits_for_command() {
+        set_breakpoints(vec![Breakpoint::Step("test_step".into())]);
+        set_enabled(true);
+
+        // 未命中断点时直接放行
+        assert!(checkpoint(Checkpoint::new("other", "")));
+
+        let worker = thread::spawn(|| {
+            let first = checkpoint(Checkpoint::new("test_step", "a").at(10, 20));
+            // 上一步选了 "跳过"，进入单步，下一步也会暂停
+            let second = checkpoint(Checkpoint::new("other", "b"));
+            (first, second)
+        });
+
+        let wait_paused = |detail: &str| loop {
+            if let Some(p) = paused().filter(|p| p.checkpoint.detail == detail) {
+                break p;
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(wait_paused("a").checkpoint.target, Some((10, 20)));
+        send(Command::Skip);
+        wait_paused("b");
+        send(Command::Continue);
+
+        assert_eq!(worker.join().unwrap(), (false, true));
+        assert!(!is_stepping());
+        set_enabled(false);
+    }
+}
@@ -0,0 +1,280 @@
+//! 陷阱快捷栏读取
+//!
+//! 截取屏幕底部的快捷栏，OCR 识别每个格子的热键数字和陷阱名称，得到 热键 → 陷阱 的对应关系；
+//! 选中的格子高亮，按格子平均亮度判断。用于确认购买结果（`verify`）和放置前确认选中了正确的陷阱
+//! （`confirm_selected`，`place_trap` 按下热键后自动调用）。
+//!
+//! 识别不到的信息（格子没有名称、亮度区分不出选中）不作判断，只有读到的内容与预期矛盾时才报错。
+
+use anyhow::Result;
+use image::RgbImage;
+use thiserror::Error;
+
+use super::common::IS_DEBUG;
+use super::loadout::{Loadout, HOTKEYS};
+use crate::ocr::{ocr_image, OcrResultItem};
+use crate::screen::{average_color, capture_region, scale_x, Region};
+
+/// 快捷栏区域，1920x1080 基准
+const HOTBAR_REGION: Region = Region::new(660, 960, 600, 120);
+/// 格子半宽：名称与热键数字的最大水平偏移，也是计算亮度的格子范围（1920x1080 基准）
+const SLOT_HALF_WIDTH: i32 = 50;
+/// 选中格子的亮度至少比其它格子高出多少（0-255）
+const SELECTED_MARGIN: f64 = 15.0;
+
+/// 快捷栏与预期不符
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum HotbarError {
+    #[error("{key} 键的陷阱是 '{actual}'，应为 '{expected}'")]
+    Mismatch {
+        key: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("选中的是 {selected} 键，应为 {key} 键")]
+    WrongSelection { key: String, selected: String },
+}
+
+/// 快捷栏中的一个格子
+#[derive(Debug, Clone, PartialEq)]
+pub struct HotbarSlot {
+    /// 热键（"4" ~ "7"）
+    pub key: String,
+    /// 陷阱名称（识别不到时为 None）
+    pub trap: Option<String>,
+    /// 热键数字的中心（屏幕坐标）
+    pub center: (i32, i32),
+    /// 是否为选中的格子
+    pub selected: bool,
+}
+
+/// OCR 名称与陷阱名称是否一致（OCR 可能只识别出部分文字）
+///
+/// 只识别出部分文字时，片段须至少 2 个字（或超过名称的一半），避免 "塔" 之类的单字匹配任意名称。
+fn names_match(actual: &str, expected: &str) -> bool {
+    if actual.contains(expected) {
+        return true;
+    }
+    let overlap = actual.chars().count();
+    expected.contains(actual) && (overlap >= 2 || overlap * 2 > expected.chars().count())
+}
+
+/// 把快捷栏的 OCR 结果分组为格子：热键数字为格子，水平偏移 `half_width` 以内最近的文字为陷阱名称
+pub fn group_slots(results: &[OcrResultItem], half_width: i32) -> Vec<HotbarSlot> {
+    let mut slots: Vec<HotbarSlot> = results
+        .iter()
+        .filter(|r| HOTKEYS.contains(&r.text.trim()))
+        .map(|label| {
+            let (kx, ky) = label.center();
+            let trap = results
+                .iter()
+                .filter(|r| {
+                    let text = r.text.trim();
+                    !text.is_empty() && !text.chars().all(|c| c.is_ascii_digit())
+                })
+                .filter(|r| (r.center().0 - kx).abs() <= half_width)
+                .min_by_key(|r| (r.center().0 - kx).abs() + (r.center().1 - ky).abs())
+                .map(|r| r.text.trim().to_string());
+            HotbarSlot {
+                key: label.text.trim().to_string(),
+                trap,
+                center: (kx, ky),
+                selected: false,
+            }
+        })
+        .collect();
+    slots.sort_by_key(|s| s.center.0);
+    slots
+}
+
+/// 选中格子的下标：亮度最高且比其它格子都高出 `SELECTED_MARGIN`，否则 None
+pub fn selected_index(brightness: &[f64]) -> Option<usize> {
+    let (best, &max) = brightness
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))?;
+    let clear = brightness
+        .iter()
+        .enumerate()
+        .all(|(i, &b)| i == best || max - b >= SELECTED_MARGIN);
+    (brightness.len() > 1 && clear).then_some(best)
+}
+
+/// 格子（热键数字左右 `half_width`、整个快捷栏高度）的平均亮度
+fn slot_brightness(img: &RgbImage, center_x: i32, half_width: i32) -> f64 {
+    let x0 = (center_x - half_width).clamp(0, img.width() as i32) as u32;
+    let x1 = (center_x + half_width).clamp(0, img.width() as i32) as u32;
+    let cell = image::imageops::crop_imm(img, x0, 0, x1 - x0, img.height()).to_image();
+    let color = average_color(&cell);
+    let [r, g, b] = [color >> 16, (color >> 8) & 0xFF, color & 0xFF].map(|c| c as f64);
+    0.299 * r + 0.587 * g + 0.114 * b
+}
+
+/// 读取快捷栏
+pub fn read() -> Result<Vec<HotbarSlot>> {
+    let region = HOTBAR_REGION.base_to_screen();
+    let half_width = scale_x(SLOT_HALF_WIDTH);
+    let img = capture_region(region)?;
+    let results = ocr_image(&img, false, IS_DEBUG)?;
+
+    let mut slots = group_slots(&results, half_width);
+    let brightness: Vec<f64> = slots
+        .iter()
+        .map(|s| slot_brightness(&img, s.center.0, half_width))
+        .collect();
+    if let Some(i) = selected_index(&brightness) {
+        slots[i].selected = true;
+    }
+    for slot in &mut slots {
+        slot.center = (slot.center.0 + region.x, slot.center.1 + region.y);
+    }
+    Ok(slots)
+}
+
+/// 检查快捷栏与装备是否一致（识别不到名称的格子跳过）
+pub fn check_loadout(slots: &[HotbarSlot], loadout: &Loadout) -> Result<(), HotbarError> {
+    for slot in loadout.slots() {
+        let Ok(key) = loadout.key(&slot.trap) else {
+            continue;
+        };
+        let actual = slots
+            .iter()
+            .find(|s| s.key == key)
+            .and_then(|s| s.trap.as_deref());
+        if let Some(actual) = actual {
+            if !names_match(actual, &slot.trap) {
+                return Err(HotbarError::Mismatch {
+                    key: key.to_string(),
+                    expected: slot.trap.clone(),
+                    actual: actual.to_string(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 检查选中的格子是 `key`，且（给出 `trap` 时）该格子的陷阱是 `trap`
+pub fn check_selected(
+    slots: &[HotbarSlot],
+    key: &str,
+    trap: Option<&str>,
+) -> Result<(), HotbarError> {
+    if let Some(selected) = slots.iter().find(|s| s.selected) {
+        if selected.key != key {
+            return Err(HotbarError::WrongSelection {
+                key: key.to_string(),
+                selected: selected.key.clone(),
+            });
+        }
+    }
+    let actual = slots
+        .iter()
+        .find(|s| s.key == key)
+        .and_then(|s| s.trap.as_deref());
+    match (trap, actual) {
+        (Some(expected), Some(actual)) if !names_match(actual, expected) => {
+            Err(HotbarError::Mismatch {
+                key: key.to_string(),
+                expected: expected.to_string(),
+                actual: actual.to_string(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 读取快捷栏并检查与装备是否一致
+///
+/// # Returns
+/// 读到的格子；不一致时返回 `HotbarError`
+pub fn verify(loadout: &Loadout) -> Result<Vec<HotbarSlot>> {
+    let slots = read()?;
+    check_loadout(&slots, loadout)?;
+    Ok(slots)
+}
+
+/// 按下热键后确认选中了正确的格子（和陷阱）
+pub fn confirm_selected(key: &str, trap: Option<&str>) -> Result<()> {
+    let slots = read()?;
+    check_selected(&slots, key, trap)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slots() -> Vec<HotbarSlot> {
+        let results = vec![
+            OcrResultItem::at("5", 200, 20),
+            OcrResultItem::at("磁暴塔", 205, 80),
+            OcrResultItem::at("4", 100, 20),
+            OcrResultItem::at("天网", 98, 80),
+            OcrResultItem::at("6", 300, 20),
+            OcrResultItem::at("1500", 300, 100),
+        ];
+        group_slots(&results, 50)
+    }
+
+    #[test]
+    fn test_group_slots() {
+        let summary: Vec<_> = slots().into_iter().map(|s| (s.key, s.trap)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("4".to_string(), Some("天网".to_string())),
+                ("5".to_string(), Some("磁暴塔".to_string())),
+                ("6".to_string(), None),
+            ]
+        );
+
+        assert_eq!(selected_index(&[40.0, 90.0, 42.0]), Some(1));
+        assert_eq!(selected_index(&[40.0, 50.0, 42.0]), None);
+        assert_eq!(selected_index(&[90.0]), None);
+    }
+
+    #[test]
+    fn test_names_match_fragment() {
+        assert!(names_match("自修复磁暴塔", "自修复磁暴塔"));
+        assert!(names_match("[自修复磁暴塔]", "自修复磁暴塔"));
+        assert!(names_match("磁暴", "自修复磁暴塔"));
+        // 单字片段
+        assert!(!names_match("塔", "自修复磁暴塔"));
+        assert!(!names_match("", "天网"));
+        assert!(!names_match("天", "天网"));
+        assert!(!names_match("天网", "天启"));
+    }
+
+    #[test]
+    fn test_check_against_loadout() {
+        let mut slots = slots();
+        let loadout = Loadout::assume_bought(&["天网", "自修复磁暴塔", "天启"]).unwrap();
+        // 6 键识别不到名称，不作判断
+        assert_eq!(check_loadout(&slots, &loadout), Ok(()));
+
+        let mut wrong = Loadout::new(&["天网", "天启", "自修复磁暴塔"]).unwrap();
+        wrong.mark_bought("天网");
+        wrong.mark_bought("天启");
+        assert_eq!(
+            check_loadout(&slots, &wrong),
+            Err(HotbarError::Mismatch {
+                key: "5".into(),
+                expected: "天启".into(),
+                actual: "磁暴塔".into(),
+            })
+        );
+
+        slots[0].selected = true;
+        assert_eq!(check_selected(&slots, "4", Some("天网")), Ok(()));
+        assert!(matches!(
+            check_selected(&slots, "5", None),
+            Err(HotbarError::WrongSelection { .. })
+        ));
+        slots[0].selected = false;
+        assert!(matches!(
+            check_selected(&slots, "5", Some("天启")),
+            Err(HotbarError::Mismatch { .. })
+        ));
+    }
+}
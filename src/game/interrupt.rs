@@ -0,0 +1,452 @@
+//! 弹窗中断处理
+//!
+//! "返回游戏"、"今日不再提醒" 这类弹窗可能在一轮中的任何时刻出现。
+//! 每个 `InterruptHandler` 声明一个检测条件（文字或像素颜色）和恢复动作，
+//! `start` 启动的后台线程在整轮中持续扫描，检测到弹窗时暂停主策略的键鼠输入（见 `pause`），
+//! 执行恢复动作后再恢复主策略。
+//!
+//! 内置处理器见 `builtin_handlers`，exe 同目录的 `interrupts.json` 可以追加或覆盖（同名替换）。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::common::IS_DEBUG;
+use super::strategy_file::Coords;
+use crate::input::{click_at, left_click, move_to, press_key, tap_key};
+use crate::keys::get_vk_code;
+use crate::monitor::watcher::hex_color;
+use crate::ocr::{find_text_contains, ocr_screen, OcrResultItem};
+use crate::pause;
+use crate::screen::{
+    color_within_tolerance, full_screen_region, get_pixel_color, scale_x, scale_y, ScreenPoint,
+};
+use crate::stop_flag::should_stop;
+use crate::timeline;
+
+/// 扫描间隔
+const SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 暂停主策略后、执行恢复动作前的等待（让正在进行的输入结束）
+const SETTLE_DELAY: Duration = Duration::from_millis(300);
+
+/// 检测条件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Detect {
+    /// 全屏 OCR 出现包含 `text` 的文字
+    Text { text: String },
+    /// 像素颜色（1920x1080 基准坐标）在容差内
+    Pixel {
+        x: i32,
+        y: i32,
+        #[serde(with = "hex_color")]
+        color: u32,
+        #[serde(default = "default_tolerance")]
+        tolerance: u8,
+    },
+}
+
+fn default_tolerance() -> u8 {
+    10
+}
+
+/// 恢复动作
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Recovery {
+    /// 点击检测到的位置（文字中心或像素），偏移为基准坐标
+    ClickFound {
+        #[serde(default)]
+        dx: i32,
+        #[serde(default)]
+        dy: i32,
+        #[serde(default = "default_clicks")]
+        clicks: u32,
+    },
+    /// 点击指定坐标
+    Click {
+        x: i32,
+        y: i32,
+        #[serde(default)]
+        coords: Coords,
+    },
+    TapKey {
+        key: String,
+    },
+    PressKey {
+        key: String,
+        secs: f64,
+    },
+    Sleep {
+        secs: f64,
+    },
+}
+
+fn default_clicks() -> u32 {
+    1
+}
+
+/// 弹窗处理器
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InterruptHandler {
+    /// 名称（日志、时间线使用；`interrupts.json` 中同名的处理器覆盖内置的）
+    pub name: String,
+    pub detect: Detect,
+    pub actions: Vec<Recovery>,
+    /// 处理后多少秒内不再检测（弹窗关闭动画期间避免重复处理）
+    #[serde(default = "default_cooldown")]
+    pub cooldown_secs: f64,
+    /// 每轮只处理一次
+    #[serde(default)]
+    pub once_per_round: bool,
+    /// 为 false 时不启用（用于在 `interrupts.json` 中关闭内置处理器）
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_cooldown() -> f64 {
+    2.0
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl InterruptHandler {
+    fn text(name: &str, text: &str, actions: Vec<Recovery>) -> Self {
+        Self {
+            name: name.to_string(),
+            detect: Detect::Text {
+                text: text.to_string(),
+            },
+            actions,
+            cooldown_secs: default_cooldown(),
+            once_per_round: false,
+            enabled: true,
+        }
+    }
+
+    /// 检测弹窗，返回检测到的屏幕坐标
+    ///
+    /// # Arguments
+    /// * `texts` - 本次扫描的全屏 OCR 结果
+    /// * `pixel` - 读取屏幕坐标的颜色
+    fn detect(
+        &self,
+        texts: &[OcrResultItem],
+        pixel: impl Fn(i32, i32) -> Option<u32>,
+    ) -> Option<(i32, i32)> {
+        match &self.detect {
+            Detect::Text { text } => find_text_contains(texts, text).map(|r| r.center()),
+            Detect::Pixel {
+                x,
+                y,
+                color,
+                tolerance,
+            } => {
+                let (x, y) = (scale_x(*x), scale_y(*y));
+                let actual = pixel(x, y)?;
+                color_within_tolerance(actual, *color, *tolerance).then_some((x, y))
+            }
+        }
+    }
+
+    /// 检查按键名称和秒数（负数、NaN 会让处理线程在持有暂停时 panic）
+    fn validate(&self) -> Result<()> {
+        let valid = |secs: f64| secs.is_finite() && secs >= 0.0;
+        if !valid(self.cooldown_secs) {
+            bail!(
+                "处理器 '{}' 冷却秒数无效: {}",
+                self.name,
+                self.cooldown_secs
+            );
+        }
+        for action in &self.actions {
+            if let Recovery::TapKey { key } | Recovery::PressKey { key, .. } = action {
+                vk(key).with_context(|| format!("处理器 '{}'", self.name))?;
+            }
+            if let Recovery::PressKey { secs, .. } | Recovery::Sleep { secs } = action {
+                if !valid(*secs) {
+                    bail!("处理器 '{}' 秒数无效: {}", self.name, secs);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 执行恢复动作，`found` 为检测到的位置
+    fn recover(&self, found: (i32, i32)) -> Result<()> {
+        for action in &self.actions {
+            if should_stop() {
+                return Ok(());
+            }
+            match action {
+                Recovery::ClickFound { dx, dy, clicks } => {
+                    move_to(found.0 + scale_x(*dx), found.1 + scale_y(*dy));
+                    for _ in 0..*clicks {
+                        thread::sleep(Duration::from_millis(200));
+                        left_click();
+                    }
+                }
+                Recovery::Click { x, y, coords } => {
+                    let ScreenPoint { x, y } = coords.to_screen(*x, *y);
+                    click_at(x, y);
+                }
+                Recovery::TapKey { key } => tap_key(vk(key)?),
+                Recovery::PressKey { key, secs } => press_key(vk(key)?, *secs),
+                Recovery::Sleep { secs } => thread::sleep(Duration::from_secs_f64(*secs)),
+            }
+            thread::sleep(Duration::from_millis(200));
+        }
+        Ok(())
+    }
+}
+
+fn vk(key: &str) -> Result<u16> {
+    get_vk_code(key).with_context(|| format!("未知的按键: {}", key))
+}
+
+/// 内置处理器
+pub fn builtin_handlers() -> Vec<InterruptHandler> {
+    vec![
+        // 挂机过久弹出，点击弹窗外关闭
+        InterruptHandler::text(
+            "返回游戏",
+            "返回游戏",
+            vec![Recovery::ClickFound {
+                dx: 50,
+                dy: 50,
+                clicks: 3,
+            }],
+        ),
+        // 开始游戏时的确认框，勾选复选框（每轮只勾一次，重复点击会取消勾选）
+        InterruptHandler {
+            once_per_round: true,
+            ..InterruptHandler::text(
+                "今日不再提醒",
+                "今日不再提醒",
+                vec![Recovery::Click {
+                    x: 898,
+                    y: 609,
+                    coords: Coords::Base,
+                }],
+            )
+        },
+    ]
+}
+
+/// 合并内置处理器和配置文件中的处理器：同名的替换内置的，其余追加；去掉未启用的
+pub fn merge_handlers(custom: Vec<InterruptHandler>) -> Result<Vec<InterruptHandler>> {
+    let mut handlers = builtin_handlers();
+    for handler in custom {
+        handler.validate()?;
+        match handlers.iter_mut().find(|h| h.name == handler.name) {
+            Some(existing) => *existing = handler,
+            None => handlers.push(handler),
+        }
+    }
+    handlers.retain(|h| h.enabled);
+    Ok(handlers)
+}
+
+/// 运行中的后台扫描线程
+struct Runner {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+static ACTIVE: Mutex<Option<Runner>> = Mutex::new(None);
+
+/// 启动后台扫描（已在运行时先停止旧的）
+///
+/// 每轮开始时调用，`once_per_round` 的处理器随之重置
+pub fn start(handlers: Vec<InterruptHandler>) {
+    stop();
+    if handlers.is_empty() {
+        return;
+    }
+
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let handle = {
+        let stop_flag = stop_flag.clone();
+        thread::spawn(move || run(handlers, &stop_flag))
+    };
+    if let Ok(mut active) = ACTIVE.lock() {
+        *active = Some(Runner {
+            stop: stop_flag,
+            handle,
+        });
+    }
+}
+
+/// 停止后台扫描并等待线程退出
+pub fn stop() {
+    let runner = ACTIVE.lock().ok().and_then(|mut a| a.take());
+    if let Some(runner) = runner {
+        runner.stop.store(true, Ordering::SeqCst);
+        let _ = runner.handle.join();
+    }
+}
+
+/// 后台扫描是否在运行
+pub fn is_running() -> bool {
+    ACTIVE.lock().map(|a| a.is_some()).unwrap_or(false)
+}
+
+fn run(handlers: Vec<InterruptHandler>, stop_flag: &AtomicBool) {
+    let needs_text = handlers
+        .iter()
+        .any(|h| matches!(h.detect, Detect::Text { .. }));
+    // 每个处理器下次允许检测的时间，None 表示本轮已处理（once_per_round）
+    let mut ready_at: Vec<Option<Instant>> = vec![Some(Instant::now()); handlers.len()];
+
+    while !stop_flag.load(Ordering::SeqCst) && !should_stop() {
+        let now = Instant::now();
+        let pending = handlers
+            .iter()
+            .zip(&ready_at)
+            .any(|(_, ready)| matches!(ready, Some(t) if *t <= now));
+
+        if pending {
+            let texts = if needs_text {
+                let screen = full_screen_region();
+                ocr_screen(screen, false, IS_DEBUG).unwrap_or_else(|e| {
+                    println!("[interrupt] OCR 失败: {}", e);
+                    Vec::new()
+                })
+            } else {
+                Vec::new()
+            };
+
+            for (handler, ready) in handlers.iter().zip(ready_at.iter_mut()) {
+                if !matches!(ready, Some(t) if *t <= now) {
+                    continue;
+                }
+                let Some(found) = handler.detect(&texts, |x, y| get_pixel_color(x, y).ok()) else {
+                    continue;
+                };
+
+                handle(handler, found);
+                *ready = if handler.once_per_round {
+                    None
+                } else {
+                    Some(Instant::now() + Duration::from_secs_f64(handler.cooldown_secs.max(0.0)))
+                };
+            }
+        }
+
+        thread::sleep(SCAN_INTERVAL);
+    }
+}
+
+/// 暂停主策略，执行恢复动作
+fn handle(handler: &InterruptHandler, found: (i32, i32)) {
+    println!(
+        "[interrupt] 检测到 '{}' @ ({}, {})，暂停主策略",
+        handler.name, found.0, found.1
+    );
+    let _guard = pause::pause();
+    thread::sleep(SETTLE_DELAY);
+
+    let detail = match handler.recover(found) {
+        Ok(()) => format!("{} @ ({}, {})", handler.name, found.0, found.1),
+        Err(e) => {
+            println!("[interrupt] '{}' 处理失败: {}", handler.name, e);
+            format!("{} 失败: {}", handler.name, e)
+        }
+    };
+    timeline::step("interrupt", detail);
+    println!("[interrupt] '{}' 处理完成，恢复主策略", handler.name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let text = InterruptHandler::text("返回游戏", "返回游戏", Vec::new());
+        let texts = vec![
+            OcrResultItem::at("第3波", 10, 5),
+            OcrResultItem::at("点击返回游戏", 110, 205),
+        ];
+        assert_eq!(text.detect(&texts, |_, _| None), Some((110, 205)));
+        assert_eq!(text.detect(&texts[..1], |_, _| None), None);
+
+        let pixel = InterruptHandler {
+            detect: Detect::Pixel {
+                x: 0,
+                y: 0,
+                color: 0x102030,
+                tolerance: 5,
+            },
+            ..text
+        };
+        assert_eq!(pixel.detect(&[], |_, _| Some(0x142030)), Some((0, 0)));
+        assert_eq!(pixel.detect(&[], |_, _| Some(0x202030)), None);
+        // 读取颜色失败视为未检测到
+        assert_eq!(pixel.detect(&[], |_, _| None), None);
+    }
+
+    #[test]
+    fn test_merge_handlers_from_json() {
+        let json = r##"[
+            { "name": "今日不再提醒", "enabled": false,
+              "detect": { "type": "text", "text": "今日不再提醒" }, "actions": [] },
+            { "name": "断线重连",
+              "detect": { "type": "pixel", "x": 960, "y": 540, "color": "#ff8000" },
+              "actions": [
+                { "type": "click", "x": 960, "y": 600 },
+                { "type": "tap_key", "key": "esc" }
+              ] }
+        ]"##;
+        let custom: Vec<InterruptHandler> = serde_json::from_str(json).unwrap();
+        assert_eq!(custom[1].cooldown_secs, 2.0);
+        assert_eq!(
+            custom[1].detect,
+            Detect::Pixel {
+                x: 960,
+                y: 540,
+                color: 0xff8000,
+                tolerance: 10
+            }
+        );
+
+        let names: Vec<_> = merge_handlers(custom)
+            .unwrap()
+            .into_iter()
+            .map(|h| h.name)
+            .collect();
+        assert_eq!(names, vec!["返回游戏", "断线重连"]);
+
+        let bad = vec![InterruptHandler::text(
+            "x",
+            "x",
+            vec![Recovery::TapKey {
+                key: "nokey".into(),
+            }],
+        )];
+        assert!(merge_handlers(bad).is_err());
+
+        let bad = vec![InterruptHandler::text(
+            "x",
+            "x",
+            vec![Recovery::Sleep { secs: -1.0 }],
+        )];
+        assert!(merge_handlers(bad).is_err());
+        let bad = vec![InterruptHandler::text(
+            "x",
+            "x",
+            vec![Recovery::PressKey {
+                key: "g".into(),
+                secs: f64::NAN,
+            }],
+        )];
+        assert!(merge_handlers(bad).is_err());
+    }
+}
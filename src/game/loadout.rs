@@ -0,0 +1,204 @@
+//! 陷阱装备
+//!
+//! 商店购买顺序决定陷阱热键：第一个购买成功的陷阱 → 4 键，第二个 → 5 键……
+//! 购买失败的陷阱不占热键，之后的陷阱依次前移。
+//!
+//! `buy_traps_ordered` 购买后把结果记为当前装备，`place_trap` / `upgrade_trap`
+//! 据此把陷阱名称换成热键，策略不再需要手写 "天网 = 4" 这样的对应关系：
+//!
+//! ```ignore
+//! buy_traps_ordered(&["天网", "自修复磁暴塔"])?;
+//! place_trap(at, "自修复磁暴塔")?; // 按 5 键
+//! ```
+
+use std::fmt;
+use std::sync::Mutex;
+
+use thiserror::Error;
+
+use crate::input::get_vk_code;
+
+/// 陷阱热键，按购买顺序分配
+pub const HOTKEYS: [&str; 4] = ["4", "5", "6", "7"];
+
+/// 陷阱名称无法换成热键
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum LoadoutError {
+    #[error("陷阱 '{0}' 不在装备中")]
+    NotEquipped(String),
+    #[error("陷阱 '{0}' 没有购买成功")]
+    NotBought(String),
+    #[error("最多装备 {max} 个陷阱，实际 {count} 个")]
+    TooMany { count: usize, max: usize },
+}
+
+/// 装备中的一个陷阱
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slot {
+    pub trap: String,
+    /// 是否购买成功
+    pub bought: bool,
+}
+
+/// 陷阱装备（按购买顺序）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Loadout {
+    slots: Vec<Slot>,
+}
+
+impl Loadout {
+    /// 按购买顺序创建，所有陷阱尚未购买
+    pub fn new(traps: &[&str]) -> Result<Self, LoadoutError> {
+        if traps.len() > HOTKEYS.len() {
+            return Err(LoadoutError::TooMany {
+                count: traps.len(),
+                max: HOTKEYS.len(),
+            });
+        }
+        Ok(Self {
+            slots: traps
+                .iter()
+                .map(|trap| Slot {
+                    trap: trap.to_string(),
+                    bought: false,
+                })
+                .collect(),
+        })
+    }
+
+    /// 假定所有陷阱都已购买（中途接管时，陷阱在停止前已经买好）
+    pub fn assume_bought(traps: &[&str]) -> Result<Self, LoadoutError> {
+        let mut loadout = Self::new(traps)?;
+        for slot in &mut loadout.slots {
+            slot.bought = true;
+        }
+        Ok(loadout)
+    }
+
+    pub fn slots(&self) -> &[Slot] {
+        &self.slots
+    }
+
+    /// 标记陷阱购买成功
+    pub fn mark_bought(&mut self, trap: &str) {
+        if let Some(slot) = self.slots.iter_mut().find(|s| s.trap == trap) {
+            slot.bought = true;
+        }
+    }
+
+    /// 陷阱的热键（之前购买失败的陷阱不占热键）
+    pub fn key(&self, trap: &str) -> Result<&'static str, LoadoutError> {
+        let bought = self.slots.iter().filter(|s| s.bought);
+        if let Some(i) = bought.clone().position(|s| s.trap == trap) {
+            return Ok(HOTKEYS[i]);
+        }
+        if self.slots.iter().any(|s| s.trap == trap) {
+            Err(LoadoutError::NotBought(trap.to_string()))
+        } else {
+            Err(LoadoutError::NotEquipped(trap.to_string()))
+        }
+    }
+
+    /// 购买失败的陷阱
+    pub fn failed(&self) -> Vec<&str> {
+        self.slots
+            .iter()
+            .filter(|s| !s.bought)
+            .map(|s| s.trap.as_str())
+            .collect()
+    }
+}
+
+impl fmt::Display for Loadout {
+    /// 如 "天网=4, 天启=未购买, 防空导弹=5"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, slot) in self.slots.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            match self.key(&slot.trap) {
+                Ok(key) => write!(f, "{}={}", slot.trap, key)?,
+                Err(_) => write!(f, "{}=未购买", slot.trap)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 本回合的装备（None 表示还没有购买）
+static CURRENT: Mutex<Option<Loadout>> = Mutex::new(None);
+
+/// 测试中设置全局装备时持有，避免并行的测试互相覆盖
+#[cfg(test)]
+pub(crate) static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+/// 设置本回合的装备（每回合开始时清空）
+pub fn set_current(loadout: Option<Loadout>) {
+    if let Ok(mut current) = CURRENT.lock() {
+        *current = loadout;
+    }
+}
+
+/// 本回合的装备
+pub fn current() -> Option<Loadout> {
+    CURRENT.lock().ok().and_then(|c| c.clone())
+}
+
+/// 陷阱名称换成热键：装备中的陷阱按购买顺序，热键名称（如 "5"）原样返回
+pub fn resolve_key(trap: &str) -> Result<String, LoadoutError> {
+    match current().map(|loadout| loadout.key(trap)) {
+        Some(Ok(key)) => Ok(key.to_string()),
+        Some(Err(LoadoutError::NotEquipped(_))) | None if get_vk_code(trap).is_some() => {
+            Ok(trap.to_string())
+        }
+        Some(Err(e)) => Err(e),
+        None => Err(LoadoutError::NotEquipped(trap.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_follow_successful_purchases() {
+        let mut loadout = Loadout::new(&["天网", "自修复磁暴塔", "天启", "防空导弹"]).unwrap();
+        loadout.mark_bought("天网");
+        loadout.mark_bought("天启");
+        loadout.mark_bought("防空导弹");
+
+        assert_eq!(loadout.key("天网"), Ok("4"));
+        assert_eq!(loadout.key("天启"), Ok("5"));
+        assert_eq!(loadout.key("防空导弹"), Ok("6"));
+        assert_eq!(
+            loadout.key("自修复磁暴塔"),
+            Err(LoadoutError::NotBought("自修复磁暴塔".into()))
+        );
+        assert_eq!(
+            loadout.key("破坏者"),
+            Err(LoadoutError::NotEquipped("破坏者".into()))
+        );
+        assert_eq!(loadout.failed(), vec!["自修复磁暴塔"]);
+        assert_eq!(
+            loadout.to_string(),
+            "天网=4, 自修复磁暴塔=未购买, 天启=5, 防空导弹=6"
+        );
+
+        assert!(Loadout::new(&["a", "b", "c", "d", "e"]).is_err());
+    }
+
+    #[test]
+    fn test_resolve_key() {
+        let _lock = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        set_current(Some(Loadout::assume_bought(&["天网", "天启"]).unwrap()));
+        assert_eq!(resolve_key("天启").unwrap(), "5");
+        // 热键原样返回
+        assert_eq!(resolve_key("7").unwrap(), "7");
+        assert!(matches!(
+            resolve_key("破坏者"),
+            Err(LoadoutError::NotEquipped(_))
+        ));
+        set_current(None);
+        assert_eq!(resolve_key("5").unwrap(), "5");
+    }
+}
@@ -1,40 +1,132 @@
 //! 游戏自动化逻辑模块
 //!
-//! 每个地图/难度是一个独立模块，包含按波次组织的函数。
-//! 通过 `available_maps()` 注册所有可用地图，供 GUI 下拉框使用。
+//! 每个地图/难度是一个实现了 `Strategy` 的策略，包含按波次组织的函数。
+//! 通过 `available_strategies()` 注册所有可用策略，供 GUI 下拉框使用；
+//! `strategies/` 目录下的策略文件（见 `strategy_file`）和脚本（见 `script`）也会加载进列表。
 
 pub mod building_inferno;
 pub mod common;
+pub mod debugger;
+pub mod hotbar;
+pub mod interrupt;
+pub mod loadout;
+pub mod placement;
+pub mod scheduler;
+pub mod script;
+pub mod shop;
+pub mod strategy;
+pub mod strategy_file;
 pub mod training_hard;
+pub mod upgrade;
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 use anyhow::Result;
 
-/// 地图信息（供 GUI 下拉框选择）
-pub struct MapInfo {
-    /// 显示名称
-    pub name: &'static str,
-    /// 难度
-    pub difficulty: &'static str,
-    /// 开始游戏函数
-    pub start_fn: fn() -> Result<()>,
-    /// 执行所有波次函数
-    pub waves_fn: fn() -> Result<()>,
+use strategy::{Metadata, Parameter, Strategy};
+use strategy_file::strategies_dir;
+
+/// 创建策略实例的工厂（每次运行创建一个新实例）
+pub type StrategyFactory = Arc<dyn Fn() -> Box<dyn Strategy> + Send + Sync>;
+
+/// 已注册的策略（供 GUI 下拉框选择）
+#[derive(Clone)]
+pub struct StrategyEntry {
+    pub metadata: Metadata,
+    pub parameters: Vec<Parameter>,
+    /// 策略文件/脚本路径（编译内置的策略为 None）
+    pub source: Option<PathBuf>,
+    factory: StrategyFactory,
+}
+
+impl StrategyEntry {
+    /// 以 `prototype` 为原型注册，每次 `create` 克隆一份
+    pub fn new<S>(prototype: S, source: Option<PathBuf>) -> Self
+    where
+        S: Strategy + Clone + Sync + 'static,
+    {
+        let metadata = prototype.metadata();
+        let parameters = prototype.parameters();
+        let prototype = Arc::new(prototype);
+        Self {
+            metadata,
+            parameters,
+            source,
+            factory: Arc::new(move || Box::new((*prototype).clone())),
+        }
+    }
+
+    /// 创建新的策略实例
+    pub fn create(&self) -> Box<dyn Strategy> {
+        (self.factory)()
+    }
+}
+
+/// 已加载的策略文件和脚本（None 表示尚未加载）
+static FILE_STRATEGIES: Mutex<Option<Vec<StrategyEntry>>> = Mutex::new(None);
+
+/// 获取所有可用策略：编译内置的在前，其后是策略文件、脚本（各自按文件名排序）
+pub fn available_strategies() -> Vec<StrategyEntry> {
+    let mut entries = vec![
+        StrategyEntry::new(training_hard::TrainingHard, None),
+        StrategyEntry::new(building_inferno::BuildingInferno, None),
+    ];
+
+    let loaded = FILE_STRATEGIES.lock().ok().and_then(|m| m.clone());
+    let file_entries = match loaded {
+        Some(file_entries) => file_entries,
+        None => {
+            reload_strategy_files();
+            FILE_STRATEGIES
+                .lock()
+                .ok()
+                .and_then(|m| m.clone())
+                .unwrap_or_default()
+        }
+    };
+    entries.extend(file_entries);
+    entries
+}
+
+/// 重新加载 `strategies/` 目录下的策略文件和脚本
+///
+/// # Returns
+/// 加载失败的文件及原因
+pub fn reload_strategy_files() -> Vec<String> {
+    let dir = strategies_dir();
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+
+    register_loaded(strategy_file::load_dir(&dir), &mut entries, &mut errors);
+    register_loaded(script::load_dir(&dir), &mut entries, &mut errors);
+
+    for entry in &entries {
+        if let Some(path) = &entry.source {
+            println!("[strategies] 加载: {}", path.display());
+        }
+    }
+    for e in &errors {
+        println!("[strategies] 加载失败 {}", e);
+    }
+
+    if let Ok(mut file_entries) = FILE_STRATEGIES.lock() {
+        *file_entries = Some(entries);
+    }
+    errors
 }
 
-/// 获取所有可用地图
-pub fn available_maps() -> Vec<MapInfo> {
-    vec![
-        MapInfo {
-            name: "训练基地",
-            difficulty: "困难",
-            start_fn: training_hard::start_game,
-            waves_fn: training_hard::run_all_waves,
-        },
-        MapInfo {
-            name: "大厦",
-            difficulty: "炼狱",
-            start_fn: building_inferno::start_game,
-            waves_fn: building_inferno::run_all_waves,
-        },
-    ]
+fn register_loaded<S>(
+    loaded: Vec<(PathBuf, Result<S>)>,
+    entries: &mut Vec<StrategyEntry>,
+    errors: &mut Vec<String>,
+) where
+    S: Strategy + Clone + Sync + 'static,
+{
+    for (path, result) in loaded {
+        match result {
+            Ok(strategy) => entries.push(StrategyEntry::new(strategy, Some(path))),
+            Err(e) => errors.push(format!("{}: {:#}", path.display(), e)),
+        }
+    }
 }
@@ -0,0 +1,298 @@
+//! 陷阱放置计划
+//!
+//! 把 "等金币 → 放陷阱" 的流程写成计划：每一项是 (陷阱, 位置, 预计花费, 优先级)，
+//! `PlacementPlan::run` 按优先级依次等待金币足够、放置（`place_trap_verified` 确认），
+//! 可以在某一波到来时暂停，下一波再接着放，不用在每个波次里手写 `wait_gold` 的数字。
+//!
+//! 位置为 `BasePoint`（1920x1080 基准坐标，与 `place_traps` 相同），放置时自动缩放；
+//! `line` / `grid` / `mirror` 生成常用的摆放形状。
+//!
+//! ```ignore
+//! let mut plan = PlacementPlan::new();
+//! let p = BasePoint::new;
+//! plan.add_all("天网", line(p(700, 400), p(1000, 400), 4), 1500, 10);
+//! plan.add_all("自修复磁暴塔", mirror(&grid(p(600, 600), 2, 2, (60, 60)), 960), 2000, 0);
+//! plan.run(Some(3))?; // 放到第 3 波开始为止，剩下的之后再 run
+//! ```
+
+use anyhow::Result;
+
+use super::common::{idle_action, place_trap_verified, Deadline, PlaceOptions, PlaceOutcome};
+use crate::monitor;
+use crate::screen::BasePoint;
+use crate::stop_flag::should_stop;
+use crate::timeline;
+
+/// 计划中的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlacementEntry {
+    /// 陷阱名称或热键
+    pub trap: String,
+    pub position: BasePoint,
+    /// 预计花费（金币）
+    pub cost: i64,
+    /// 优先级，越大越先放；相同时按加入顺序
+    pub priority: i32,
+}
+
+/// 计划项的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryState {
+    Pending,
+    Placed,
+    /// 所有偏移位置都放不下，不再尝试
+    Failed,
+}
+
+/// `run` 返回时的情况
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunResult {
+    /// 全部处理完
+    Done,
+    /// 到了指定波次，剩下的留给下次 `run`
+    WaveReached,
+    /// 收到停止信号（或调试器跳过）
+    Stopped,
+}
+
+/// 陷阱放置计划（记录每一项的进度，可跨波次多次 `run`）
+#[derive(Debug, Clone, Default)]
+pub struct PlacementPlan {
+    entries: Vec<(PlacementEntry, EntryState)>,
+}
+
+impl PlacementPlan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加入一项
+    pub fn add(&mut self, trap: &str, position: BasePoint, cost: i64, priority: i32) -> &mut Self {
+        self.entries.push((
+            PlacementEntry {
+                trap: trap.to_string(),
+                position,
+                cost,
+                priority,
+            },
+            EntryState::Pending,
+        ));
+        self
+    }
+
+    /// 在多个位置放同一陷阱
+    pub fn add_all(
+        &mut self,
+        trap: &str,
+        positions: impl IntoIterator<Item = BasePoint>,
+        cost: i64,
+        priority: i32,
+    ) -> &mut Self {
+        for position in positions {
+            self.add(trap, position, cost, priority);
+        }
+        self
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &(PlacementEntry, EntryState)> {
+        self.entries.iter()
+    }
+
+    /// 下一项（优先级最高、最早加入的待放置项）
+    pub fn next(&self) -> Option<&PlacementEntry> {
+        self.next_index().map(|i| &self.entries[i].0)
+    }
+
+    fn next_index(&self) -> Option<usize> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, state))| *state == EntryState::Pending)
+            .min_by_key(|(i, (entry, _))| (std::cmp::Reverse(entry.priority), *i))
+            .map(|(i, _)| i)
+    }
+
+    /// 剩余待放置项的总花费
+    pub fn remaining_cost(&self) -> i64 {
+        self.entries
+            .iter()
+            .filter(|(_, state)| *state == EntryState::Pending)
+            .map(|(entry, _)| entry.cost)
+            .sum()
+    }
+
+    /// 是否全部处理完（放置成功或失败）
+    pub fn is_done(&self) -> bool {
+        self.next_index().is_none()
+    }
+
+    /// 按优先级依次等待金币足够并放置
+    ///
+    /// `until_wave` 为 Some(n) 时，监控读到第 n 波（本回合）就返回 `WaveReached`，
+    /// 剩下的项留到下次调用。等待金币的超时使用策略的默认超时。
+    pub fn run(&mut self, until_wave: Option<u32>) -> Result<RunResult> {
+        loop {
+            if let Some(result) = self.step(until_wave)? {
+                if result == RunResult::Done {
+                    timeline::step("placement_done", format!("{} 项", self.entries.len()));
+                }
+                return Ok(result);
+            }
+        }
+    }
+
+    /// 等待金币并处理下一项（`run` 的单步，与升级队列交替执行时使用）
+    ///
+    /// # Returns
+    /// None 表示处理了一项、还可以继续；Some 为 `run` 应返回的结果
+    pub fn step(&mut self, until_wave: Option<u32>) -> Result<Option<RunResult>> {
+        let Some(i) = self.next_index() else {
+            return Ok(Some(RunResult::Done));
+        };
+        let entry = self.entries[i].0.clone();
+        if !wait_gold_or_wave(entry.cost, until_wave)? {
+            return Ok(Some(if should_stop() {
+                RunResult::Stopped
+            } else {
+                RunResult::WaveReached
+            }));
+        }
+
+        let opts = PlaceOptions {
+            price: Some(entry.cost),
+            ..PlaceOptions::default()
+        };
+        match place_trap_verified(entry.position, &entry.trap, &opts)? {
+            PlaceOutcome::Placed { .. } => self.entries[i].1 = EntryState::Placed,
+            PlaceOutcome::InvalidSpot => {
+                println!(
+                    "[placement] {} @ {} 放不下，跳过",
+                    entry.trap, entry.position
+                );
+                self.entries[i].1 = EntryState::Failed;
+            }
+            // 读数间金币被花掉（或读数偏高），重新等待
+            PlaceOutcome::NotEnoughGold { .. } => {}
+            PlaceOutcome::Skipped => return Ok(Some(RunResult::Stopped)),
+        }
+        Ok(None)
+    }
+}
+
+/// 等待金币 >= `amount`（本回合读数），或者波次到达 `until_wave`
+///
+/// # Returns
+/// - Ok(true) 金币足够
+/// - Ok(false) 波次已到或收到停止信号
+pub(super) fn wait_gold_or_wave(amount: i64, until_wave: Option<u32>) -> Result<bool> {
+    let round = monitor::round_generation();
+    let deadline = Deadline::new(format!("金币 >= {}", amount), None);
+    let current = |reading: Option<monitor::Reading<i64>>| {
+        reading.filter(|r| r.generation == round).map(|r| r.value)
+    };
+    println!("[placement] 等待金币 >= {}", amount);
+    monitor::set_gold_target(Some(amount));
+    let result = loop {
+        if should_stop() {
+            break Ok(false);
+        }
+        if let Err(e) = deadline.check() {
+            break Err(e.into());
+        }
+        if current(monitor::gold_reading()).is_some_and(|gold| gold >= amount) {
+            break Ok(true);
+        }
+        let wave = monitor::wave_reading().filter(|r| r.generation == round);
+        if let (Some(until), Some(wave)) = (until_wave, wave) {
+            if wave.value >= until {
+                println!("[placement] 已到第 {} 波，剩余的稍后放置", wave.value);
+                break Ok(false);
+            }
+        }
+        idle_action();
+    };
+    monitor::set_gold_target(None);
+    result
+}
+
+// ===== 摆放形状 =====
+
+/// 从 `from` 到 `to`（含两端）等间距的 `count` 个点
+pub fn line(from: BasePoint, to: BasePoint, count: usize) -> Vec<BasePoint> {
+    match count {
+        0 => Vec::new(),
+        1 => vec![from],
+        _ => {
+            let n = (count - 1) as f64;
+            (0..count)
+                .map(|i| {
+                    let t = i as f64 / n;
+                    let x = from.x as f64 + (to.x - from.x) as f64 * t;
+                    let y = from.y as f64 + (to.y - from.y) as f64 * t;
+                    BasePoint::new(x.round() as i32, y.round() as i32)
+                })
+                .collect()
+        }
+    }
+}
+
+/// 从 `origin` 开始 `cols` 列 × `rows` 行，间距 `step`（逐行排列）
+pub fn grid(origin: BasePoint, cols: usize, rows: usize, step: (i32, i32)) -> Vec<BasePoint> {
+    (0..rows as i32)
+        .flat_map(|row| (0..cols as i32).map(move |col| origin.offset(col * step.0, row * step.1)))
+        .collect()
+}
+
+/// 原有的点加上以 `x = axis_x` 为轴左右镜像的点（如 960 为屏幕中线）
+pub fn mirror(points: &[BasePoint], axis_x: i32) -> Vec<BasePoint> {
+    let mirrored = points.iter().map(|p| BasePoint::new(2 * axis_x - p.x, p.y));
+    points.iter().copied().chain(mirrored).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(points: &[(i32, i32)]) -> Vec<BasePoint> {
+        points.iter().map(|&(x, y)| BasePoint::new(x, y)).collect()
+    }
+
+    #[test]
+    fn test_patterns() {
+        let p = BasePoint::new;
+        assert_eq!(
+            line(p(700, 400), p(1000, 400), 4),
+            points(&[(700, 400), (800, 400), (900, 400), (1000, 400)])
+        );
+        assert_eq!(line(p(1, 2), p(3, 4), 1), points(&[(1, 2)]));
+        assert!(line(p(1, 2), p(3, 4), 0).is_empty());
+        assert_eq!(
+            grid(p(100, 200), 2, 2, (50, 60)),
+            points(&[(100, 200), (150, 200), (100, 260), (150, 260)])
+        );
+        assert_eq!(
+            mirror(&points(&[(900, 300), (800, 400)]), 960),
+            points(&[(900, 300), (800, 400), (1020, 300), (1120, 400)])
+        );
+    }
+
+    #[test]
+    fn test_plan_order() {
+        let mut plan = PlacementPlan::new();
+        plan.add("a", BasePoint::new(0, 0), 100, 0)
+            .add_all("b", points(&[(1, 1), (2, 2)]), 200, 5)
+            .add("c", BasePoint::new(3, 3), 300, 0);
+
+        assert_eq!(plan.remaining_cost(), 800);
+        assert_eq!(plan.next().unwrap().position, BasePoint::new(1, 1));
+
+        plan.entries[1].1 = EntryState::Placed;
+        plan.entries[2].1 = EntryState::Failed;
+        assert_eq!(plan.next().unwrap().trap, "a");
+        assert_eq!(plan.remaining_cost(), 400);
+
+        plan.entries[0].1 = EntryState::Placed;
+        plan.entries[3].1 = EntryState::Placed;
+        assert!(plan.is_done());
+    }
+}
@@ -0,0 +1,413 @@
+//! 事件驱动的波次调度
+//!
+//! 代替手写的 `wave_n()` / `wait_wave(n + 1)` 链：注册 "第 N 波开始"、"金币 ≥ X"、
+//! "第 N 波开始 T 秒后"、"出现文字" 等触发器，调度器在监控事件到来时按顺序执行到期的处理器。
+//!
+//! 处理器是串行执行的。某个处理器执行时间过长越过了下一波，或监控直接跳过了某个波次号时，
+//! 已经过去的波次的处理器按 `LatePolicy` 处理：
+//!
+//! | 策略 | 过期的 `Wave` | 过期的 `AfterSecs` |
+//! |------|--------------|-------------------|
+//! | `CatchUp`（默认） | 按波次顺序立即补执行 | 丢弃 |
+//! | `Skip` | 丢弃 | 丢弃 |
+//! | `RunAll` | 按波次顺序立即补执行 | 按波次顺序立即补执行 |
+//!
+//! `Gold` / `Text` 不绑定波次，没有过期的概念，条件满足时执行一次。
+
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use super::common::{random_range, Deadline, IS_DEBUG};
+use crate::input::{tap_key, VK_SPACE};
+use crate::monitor;
+use crate::ocr::ocr_screen;
+use crate::screen::full_screen_region;
+use crate::stop_flag::should_stop;
+use crate::timeline;
+
+/// 没有监控事件时的检查间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 有文字触发器等待时，全屏 OCR 的间隔
+const TEXT_SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 触发条件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Trigger {
+    /// 第 N 波开始
+    Wave { wave: u32 },
+    /// 第 N 波开始 `secs` 秒后（从调度器观察到该波次时计时）
+    AfterSecs { wave: u32, secs: f64 },
+    /// 金币 ≥ `amount`
+    Gold { amount: i64 },
+    /// 全屏 OCR 出现包含 `text` 的文字
+    Text { text: String },
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Wave { wave } => write!(f, "波次 {}", wave),
+            Self::AfterSecs { wave, secs } => write!(f, "波次 {} + {} 秒", wave, secs),
+            Self::Gold { amount } => write!(f, "金币 >= {}", amount),
+            Self::Text { text } => write!(f, "文字 '{}'", text),
+        }
+    }
+}
+
+/// 过期处理器的处理策略（见模块文档）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LatePolicy {
+    #[default]
+    CatchUp,
+    Skip,
+    RunAll,
+}
+
+/// 调度时的游戏状态
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    /// 当前波次（0 = 未知）
+    pub wave: u32,
+    /// 当前波次已经持续的时间（从调度器观察到该波次时算起）
+    pub wave_elapsed: Duration,
+    pub gold: i64,
+    /// 最近一次全屏 OCR 的文字
+    pub texts: Vec<String>,
+}
+
+struct Entry<H> {
+    trigger: Trigger,
+    policy: LatePolicy,
+    handler: H,
+    done: bool,
+}
+
+/// 到期判断结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Due {
+    No,
+    Fire,
+    Skip,
+}
+
+impl<H> Entry<H> {
+    fn due(&self, snap: &Snapshot) -> Due {
+        match &self.trigger {
+            Trigger::Wave { wave } => {
+                if snap.wave < *wave {
+                    Due::No
+                } else if snap.wave == *wave || self.policy != LatePolicy::Skip {
+                    Due::Fire
+                } else {
+                    Due::Skip
+                }
+            }
+            Trigger::AfterSecs { wave, secs } => {
+                if snap.wave < *wave {
+                    Due::No
+                } else if snap.wave == *wave {
+                    if snap.wave_elapsed.as_secs_f64() >= *secs {
+                        Due::Fire
+                    } else {
+                        Due::No
+                    }
+                } else if self.policy == LatePolicy::RunAll {
+                    Due::Fire
+                } else {
+                    Due::Skip
+                }
+            }
+            Trigger::Gold { amount } => {
+                if snap.gold >= *amount {
+                    Due::Fire
+                } else {
+                    Due::No
+                }
+            }
+            Trigger::Text { text } => {
+                if snap.texts.iter().any(|t| t.contains(text.as_str())) {
+                    Due::Fire
+                } else {
+                    Due::No
+                }
+            }
+        }
+    }
+
+    /// 同时到期时的执行顺序：按所属波次，同一波次内 `Wave` → `AfterSecs`（按秒数）→ 其它
+    fn order(&self, snap: &Snapshot) -> (u32, u8, u64) {
+        match &self.trigger {
+            Trigger::Wave { wave } => (*wave, 0, 0),
+            Trigger::AfterSecs { wave, secs } => (*wave, 1, (secs * 1000.0) as u64),
+            Trigger::Gold { .. } | Trigger::Text { .. } => (snap.wave, 2, 0),
+        }
+    }
+}
+
+/// 事件驱动的调度器，`H` 为处理器（由 `run` 的 `dispatch` 执行）
+pub struct Scheduler<H> {
+    entries: Vec<Entry<H>>,
+    policy: LatePolicy,
+}
+
+impl<H> Scheduler<H> {
+    /// `policy` 为未单独指定策略的处理器使用的过期策略
+    pub fn new(policy: LatePolicy) -> Self {
+        Self {
+            entries: Vec::new(),
+            policy,
+        }
+    }
+
+    /// 注册处理器（使用调度器的过期策略）
+    pub fn on(&mut self, trigger: Trigger, handler: H) -> &mut Self {
+        let policy = self.policy;
+        self.on_with(trigger, policy, handler)
+    }
+
+    /// 注册处理器并单独指定过期策略
+    pub fn on_with(&mut self, trigger: Trigger, policy: LatePolicy, handler: H) -> &mut Self {
+        self.entries.push(Entry {
+            trigger,
+            policy,
+            handler,
+            done: false,
+        });
+        self
+    }
+
+    /// 所有处理器都已执行或跳过
+    pub fn is_done(&self) -> bool {
+        self.entries.iter().all(|e| e.done)
+    }
+
+    fn needs_text(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|e| !e.done && matches!(e.trigger, Trigger::Text { .. }))
+    }
+
+    /// 选出下一个要执行的处理器，并把应跳过的处理器标记为完成
+    ///
+    /// # Returns
+    /// (下一个处理器的下标, 本次跳过的触发器)
+    fn next_due(&mut self, snap: &Snapshot) -> (Option<usize>, Vec<Trigger>) {
+        let mut skipped = Vec::new();
+        let mut next: Option<(usize, (u32, u8, u64))> = None;
+
+        for (i, entry) in self.entries.iter_mut().enumerate() {
+            if entry.done {
+                continue;
+            }
+            match entry.due(snap) {
+                Due::No => {}
+                Due::Skip => {
+                    entry.done = true;
+                    skipped.push(entry.trigger.clone());
+                }
+                Due::Fire => {
+                    let order = entry.order(snap);
+                    let earlier = match next {
+                        Some((_, best)) => order < best,
+                        None => true,
+                    };
+                    if earlier {
+                        next = Some((i, order));
+                    }
+                }
+            }
+        }
+        (next.map(|(i, _)| i), skipped)
+    }
+
+    /// 执行调度直到所有处理器完成
+    ///
+    /// 只在本回合内调度，回合切换会返回错误；检测到停止信号时返回 Ok。
+    /// 两次触发之间超过默认超时（`common::default_timeout`）返回 `WaitTimeout`。
+    /// 等待期间每 3-5 秒按一次空格防止挂机检测。
+    pub fn run(&mut self, mut dispatch: impl FnMut(&mut H) -> Result<()>) -> Result<()> {
+        let events = monitor::subscribe();
+        let round = monitor::round_generation();
+        let mut seen_wave = (0, Instant::now());
+        let mut texts = Vec::new();
+        let mut last_scan: Option<Instant> = None;
+        let mut next_idle = Instant::now() + Duration::from_secs(random_range(3, 5));
+        // 两次触发之间最长等待默认超时
+        let mut deadline = Deadline::new("下一个触发器", None);
+
+        loop {
+            if should_stop() {
+                println!("[STOP] scheduler: 检测到停止信号");
+                return Ok(());
+            }
+            if monitor::round_generation() != round {
+                bail!("波次调度期间回合已切换");
+            }
+            if self.is_done() {
+                return Ok(());
+            }
+            deadline.check()?;
+
+            let wave = monitor::wave_reading()
+                .filter(|r| r.generation == round)
+                .map_or(0, |r| r.value);
+            if wave != seen_wave.0 {
+                seen_wave = (wave, Instant::now());
+            }
+
+            if self.needs_text()
+                && !matches!(last_scan, Some(t) if t.elapsed() < TEXT_SCAN_INTERVAL)
+            {
+                texts = scan_texts();
+                last_scan = Some(Instant::now());
+            }
+
+            let snap = Snapshot {
+                wave,
+                wave_elapsed: seen_wave.1.elapsed(),
+                gold: monitor::current_gold(),
+                texts: texts.clone(),
+            };
+            let (due, skipped) = self.next_due(&snap);
+            for trigger in skipped {
+                println!("[scheduler] 跳过过期的 {} (当前波次 {})", trigger, wave);
+                timeline::step("schedule_skip", trigger.to_string());
+            }
+
+            if let Some(i) = due {
+                let entry = &mut self.entries[i];
+                entry.done = true;
+                println!("[scheduler] 触发 {} (当前波次 {})", entry.trigger, wave);
+                timeline::step("schedule", entry.trigger.to_string());
+                dispatch(&mut entry.handler)?;
+                // 处理器执行期间画面已变化，旧的 OCR 结果作废
+                texts.clear();
+                last_scan = None;
+                deadline = Deadline::new("下一个触发器", None);
+                continue;
+            }
+
+            if Instant::now() >= next_idle {
+                tap_key(VK_SPACE);
+                next_idle = Instant::now() + Duration::from_secs(random_range(3, 5));
+            }
+            match &events {
+                Some(rx) => {
+                    let _ = rx.recv_timeout(POLL_INTERVAL);
+                }
+                None => thread::sleep(POLL_INTERVAL),
+            }
+        }
+    }
+}
+
+/// 全屏 OCR 一次，出错时返回空
+fn scan_texts() -> Vec<String> {
+    let screen = full_screen_region();
+    match ocr_screen(screen, false, IS_DEBUG) {
+        Ok(results) => results.into_iter().map(|r| r.text).collect(),
+        Err(e) => {
+            println!("[scheduler] OCR 失败: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snap(wave: u32, secs: u64) -> Snapshot {
+        Snapshot {
+            wave,
+            wave_elapsed: Duration::from_secs(secs),
+            ..Default::default()
+        }
+    }
+
+    /// 依次取出所有到期的处理器
+    fn drain(scheduler: &mut Scheduler<&'static str>, snap: &Snapshot) -> Vec<&'static str> {
+        let mut fired = Vec::new();
+        while let (Some(i), _) = scheduler.next_due(snap) {
+            scheduler.entries[i].done = true;
+            fired.push(scheduler.entries[i].handler);
+        }
+        fired
+    }
+
+    fn waves_with_timer(policy: LatePolicy) -> Scheduler<&'static str> {
+        let mut scheduler = Scheduler::new(policy);
+        scheduler
+            .on(Trigger::Wave { wave: 3 }, "w3")
+            .on(
+                Trigger::AfterSecs {
+                    wave: 2,
+                    secs: 10.0,
+                },
+                "w2+10",
+            )
+            .on(Trigger::Wave { wave: 2 }, "w2")
+            .on(Trigger::Wave { wave: 4 }, "w4");
+        scheduler
+    }
+
+    #[test]
+    fn test_on_time_order() {
+        let mut scheduler = waves_with_timer(LatePolicy::CatchUp);
+        assert!(drain(&mut scheduler, &snap(1, 0)).is_empty());
+        assert_eq!(drain(&mut scheduler, &snap(2, 3)), vec!["w2"]);
+        assert_eq!(drain(&mut scheduler, &snap(2, 10)), vec!["w2+10"]);
+        assert_eq!(drain(&mut scheduler, &snap(3, 0)), vec!["w3"]);
+        assert!(!scheduler.is_done());
+    }
+
+    #[test]
+    fn test_late_policies() {
+        // 监控从 1 直接跳到 4（或第 1 波处理器执行到了第 4 波）
+        let late = snap(4, 0);
+
+        let mut catch_up = waves_with_timer(LatePolicy::CatchUp);
+        assert_eq!(drain(&mut catch_up, &late), vec!["w2", "w3", "w4"]);
+
+        let mut skip = waves_with_timer(LatePolicy::Skip);
+        let (_, skipped) = skip.next_due(&late);
+        assert_eq!(skipped.len(), 3);
+        assert_eq!(drain(&mut skip, &late), vec!["w4"]);
+
+        let mut run_all = waves_with_timer(LatePolicy::RunAll);
+        assert_eq!(drain(&mut run_all, &late), vec!["w2", "w2+10", "w3", "w4"]);
+        assert!(run_all.is_done());
+    }
+
+    #[test]
+    fn test_gold_and_text_triggers() {
+        let mut scheduler = Scheduler::new(LatePolicy::Skip);
+        scheduler
+            .on(Trigger::Gold { amount: 5000 }, "gold")
+            .on(
+                Trigger::Text {
+                    text: "BOSS".into(),
+                },
+                "boss",
+            )
+            .on_with(Trigger::Wave { wave: 2 }, LatePolicy::CatchUp, "w2");
+        assert!(scheduler.needs_text());
+
+        let mut s = snap(3, 0);
+        s.gold = 4999;
+        assert_eq!(drain(&mut scheduler, &s), vec!["w2"]);
+
+        s.gold = 5000;
+        s.texts = vec!["BOSS 来袭".into()];
+        assert_eq!(drain(&mut scheduler, &s), vec!["gold", "boss"]);
+        assert!(scheduler.is_done());
+        assert!(!scheduler.needs_text());
+    }
+}
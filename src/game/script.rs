@@ -0,0 +1,533 @@
+//! 策略脚本（Rhai）
+//!
+//! 声明式策略文件写不了循环、按 OCR 结果分支、Boss 专用操作等逻辑时，
+//! 可以在 `strategies/` 下写 `.rhai` 脚本，无需 Rust 工具链：
+//!
+//! ```rhai
+//! const MAP = "大厦";
+//! const DIFFICULTY = "炼狱";
+//!
+//! // 可选，省略时为 start_game(DIFFICULTY)
+//! fn start() {
+//!     start_game(DIFFICULTY);
+//! }
+//!
+//! fn run_waves() {
+//!     buy_traps(["防空导弹", "自修复磁暴塔", "破坏者", "修理站"]);
+//!     for wave in 2..=10 {
+//!         wait_wave(wave);
+//!         if gold() > 3000 {
+//!             upgrade_trap("自修复磁暴塔");
+//!         }
+//!     }
+//!     wait_for_game_end();
+//! }
+//! ```
+//!
+//! 可选的 `fn on_round_end(outcome)` 在每轮结束时调用（"completed" / "stopped" / "failed"）。
+//! 可选的 `fn resume(wave)` 在对局中途接管时代替 `start()` 调用，此时 `run_waves()` 需要自己
+//! 用 `resumed_at()`（非接管时为 0）跳过已经过去的波次。
+//! GUI 中设置了波次范围时，`run_waves()` 用 `first_wave()` / `last_wave()`（不限时为 0）自己取舍，
+//! 可选的 `fn setup(first)` 补执行 `first` 之前的设置步骤。
+//! 可选的常量 `WAIT_TIMEOUT`（秒）设置等待函数的默认超时，`wait_gold` / `wait_wave` 也可以
+//! 传第二个参数单独指定超时。
+//! 脚本里的坐标（包括 OCR 结果的 `x` / `y`）都是 1920x1080 基准坐标，按当前分辨率缩放；
+//! 需要实际屏幕像素时用带 `_screen` 后缀的版本。
+//! 顶层只写常量：每次调用脚本函数前都会重新执行顶层语句。
+//! 脚本执行过程中检测到停止信号会自动终止，不需要手动检查 `should_stop()`。
+
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context, Result};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FuncArgs, Map, Position, Scope, AST};
+
+use super::common::{
+    buy_traps_ordered, execute_actions, place_trap, place_traps, start_game_with_difficulty,
+    upgrade_trap, valid_secs, wait_for_game_end, wait_gold, wait_gold_with,
+    wait_until_wave_countdown, wait_wave, wait_wave_with, GameAction, WaitOptions, WaitTimeout,
+    IS_DEBUG,
+};
+use super::strategy::{Ctx, Metadata, RoundOutcome, Strategy};
+use super::strategy_file::{list_files, Action};
+use crate::input::get_vk_code;
+use crate::monitor::{self, WatchValue};
+use crate::ocr::{find_text_contains, ocr_screen, OcrResultItem};
+use crate::screen::{
+    dev_x, dev_y, full_screen_region, scale_x, scale_y, BasePoint, Region, ScreenPoint,
+};
+use crate::stop_flag::should_stop;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// 已编译的策略脚本
+#[derive(Clone)]
+pub struct ScriptStrategy {
+    /// 地图名称（脚本常量 `MAP`）
+    pub map: String,
+    /// 难度（脚本常量 `DIFFICULTY`）
+    pub difficulty: String,
+    /// 等待函数的默认超时（脚本常量 `WAIT_TIMEOUT`，秒）
+    pub wait_timeout: Option<Duration>,
+    ast: AST,
+}
+
+impl ScriptStrategy {
+    /// 从文件加载并编译
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("无法读取脚本: {}", path.display()))?;
+        Self::compile(&source)
+    }
+
+    /// 编译脚本并读取 `MAP` / `DIFFICULTY` 常量
+    pub fn compile(source: &str) -> Result<Self> {
+        let ast = Engine::new()
+            .compile(source)
+            .map_err(|e| anyhow!("脚本语法错误: {}", e))?;
+
+        let constant = |name: &str| {
+            ast.iter_literal_variables(true, false)
+                .find(|(n, ..)| *n == name)
+                .and_then(|(_, _, value)| value.into_string().ok())
+                .with_context(|| format!("脚本缺少字符串常量 {}", name))
+        };
+        let map = constant("MAP")?;
+        let difficulty = constant("DIFFICULTY")?;
+        let wait_timeout = match ast
+            .iter_literal_variables(true, false)
+            .find(|(n, ..)| *n == "WAIT_TIMEOUT")
+        {
+            Some((_, _, value)) => Some(timeout_secs(&value).context("WAIT_TIMEOUT 必须是非负数")?),
+            None => None,
+        };
+
+        let script = Self {
+            map,
+            difficulty,
+            wait_timeout,
+            ast,
+        };
+        if !script.has_fn("run_waves", 0) {
+            bail!("脚本缺少 fn run_waves()");
+        }
+        Ok(script)
+    }
+
+    fn has_fn(&self, name: &str, arity: usize) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|f| f.name == name && f.params.len() == arity)
+    }
+
+    fn call(&self, name: &str, ctx: &Ctx, args: impl FuncArgs) -> Result<()> {
+        let engine = build_engine(&self.map, ctx);
+        match engine.call_fn::<Dynamic>(&mut Scope::new(), &self.ast, name, args) {
+            Ok(_) => Ok(()),
+            Err(e) if matches!(*e, EvalAltResult::ErrorTerminated(..)) => {
+                println!("[STOP] {}: 检测到停止信号", self.map);
+                Ok(())
+            }
+            Err(e) => Err(script_error(name, &e)),
+        }
+    }
+}
+
+impl Strategy for ScriptStrategy {
+    fn metadata(&self) -> Metadata {
+        Metadata::new(&self.map, &self.difficulty)
+    }
+
+    /// 执行 `start()`，脚本未定义时按 `DIFFICULTY` 开始游戏
+    fn start(&mut self, ctx: &mut Ctx) -> Result<()> {
+        println!("[{}:{}] 开始游戏...", self.map, self.difficulty);
+        if self.has_fn("start", 0) {
+            self.call("start", ctx, ())
+        } else {
+            start_game_with_difficulty(&self.difficulty)
+        }
+    }
+
+    /// 执行 `resume(wave)`（可选）
+    fn resume(&mut self, ctx: &mut Ctx, wave: u32) -> Result<()> {
+        if self.has_fn("resume", 1) {
+            self.call("resume", ctx, (wave as i64,))?;
+        }
+        Ok(())
+    }
+
+    /// 执行 `setup(first)`（可选）
+    fn setup(&mut self, ctx: &mut Ctx, first: u32) -> Result<()> {
+        if self.has_fn("setup", 1) {
+            self.call("setup", ctx, (first as i64,))?;
+        }
+        Ok(())
+    }
+
+    fn wait_timeout(&self) -> Option<Duration> {
+        self.wait_timeout
+    }
+
+    /// 波次由脚本的 `run_waves()` 自己安排，不单独分发
+    fn on_wave(&mut self, _ctx: &mut Ctx, _wave: u32) -> Result<()> {
+        Ok(())
+    }
+
+    /// 执行 `on_round_end(outcome)`（可选），outcome 为 "completed" / "stopped" / "failed"
+    fn on_round_end(&mut self, ctx: &mut Ctx, outcome: &RoundOutcome) -> Result<()> {
+        if self.has_fn("on_round_end", 1) {
+            self.call("on_round_end", ctx, (outcome.as_str().to_string(),))?;
+        }
+        Ok(())
+    }
+
+    /// 执行 `run_waves()`，波次由脚本自己安排
+    fn run_waves(&mut self, ctx: &mut Ctx) -> Result<()> {
+        self.call("run_waves", ctx, ())
+    }
+}
+
+/// 加载目录下所有 `.rhai` 脚本（按文件名排序）
+pub fn load_dir(dir: &Path) -> Vec<(PathBuf, Result<ScriptStrategy>)> {
+    list_files(dir, &["rhai"])
+        .into_iter()
+        .map(|p| {
+            let result = ScriptStrategy::load(&p);
+            (p, result)
+        })
+        .collect()
+}
+
+/// 创建注册了游戏函数的脚本引擎
+///
+/// 每执行一步都会检查停止信号，检测到时以 `ErrorTerminated` 终止脚本。
+/// `round()` / `resumed_at()` 返回本轮轮次和中途接管时的波次（非接管为 0），
+/// `first_wave()` / `last_wave()` 返回波次范围（不限时 `last_wave()` 为 0）。
+fn build_engine(map: &str, ctx: &Ctx) -> Engine {
+    let mut engine = Engine::new();
+
+    engine.on_progress(|_| should_stop().then_some(Dynamic::UNIT));
+    let tag = format!("[script:{}]", map);
+    engine.on_print(move |s| println!("{} {}", tag, s));
+
+    register_flow(&mut engine);
+    register_input(&mut engine);
+    register_ocr(&mut engine);
+    register_monitor(&mut engine);
+
+    // 本轮上下文
+    let resumed_at = ctx.resumed_at.unwrap_or(0) as i64;
+    let round = ctx.round as i64;
+    let first_wave = ctx.range.first as i64;
+    let last_wave = ctx.range.last.unwrap_or(0) as i64;
+    engine
+        .register_fn("resumed_at", move || resumed_at)
+        .register_fn("round", move || round)
+        .register_fn("first_wave", move || first_wave)
+        .register_fn("last_wave", move || last_wave);
+    engine
+}
+
+/// 游戏流程：开始、买陷阱、放置/升级、等待
+fn register_flow(engine: &mut Engine) {
+    engine
+        .register_fn("start_game", |difficulty: &str| {
+            script_err(start_game_with_difficulty(difficulty))
+        })
+        .register_fn("wait_for_game_end", || script_err(wait_for_game_end()))
+        .register_fn("buy_traps", |traps: Array| {
+            let names = traps
+                .into_iter()
+                .map(|t| t.into_string())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|t| format!("陷阱名称必须是字符串，实际为 {}", t))?;
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            script_err(buy_traps_ordered(&names).map(|_| ()))
+        })
+        .register_fn("place_trap", |x: i64, y: i64, trap: &str| {
+            script_err(place_trap(base(x, y), trap))
+        })
+        .register_fn("place_trap_screen", |x: i64, y: i64, trap: &str| {
+            script_err(place_trap(ScreenPoint::new(x as i32, y as i32), trap))
+        })
+        .register_fn("place_traps", |positions: Array, trap: &str| {
+            let positions = points(positions, BasePoint::new)?;
+            script_err(place_traps(&positions, trap))
+        })
+        .register_fn("place_traps_screen", |positions: Array, trap: &str| {
+            let positions = points(positions, ScreenPoint::new)?;
+            script_err(place_traps(&positions, trap))
+        })
+        .register_fn("upgrade_trap", |trap: &str| script_err(upgrade_trap(trap)))
+        .register_fn("wait_gold", |amount: i64| script_err(wait_gold(amount)))
+        .register_fn("wait_gold", |amount: i64, secs: Dynamic| {
+            script_err(wait_gold_with(amount, timeout_opts(&secs)?))
+        })
+        .register_fn("wait_wave", |wave: i64| script_err(wait_wave(wave as u32)))
+        .register_fn("wait_wave", |wave: i64, secs: Dynamic| {
+            script_err(wait_wave_with(wave as u32, timeout_opts(&secs)?))
+        })
+        .register_fn("wait_countdown", |secs: i64| {
+            script_err(wait_until_wave_countdown(secs as u32))
+        })
+        .register_fn("execute_actions", |actions: Array| {
+            let actions: Vec<Action> = rhai::serde::from_dynamic(&Dynamic::from_array(actions))?;
+            let actions = actions
+                .iter()
+                .map(Action::to_game_action)
+                .collect::<Result<Vec<_>>>();
+            script_err(actions.and_then(|a| execute_actions(&a)))
+        })
+        .register_fn("should_stop", should_stop);
+}
+
+/// 键鼠、等待和坐标换算
+fn register_input(engine: &mut Engine) {
+    engine
+        .register_fn("tap_key", |key: &str| act(GameAction::TapKey(vk(key)?)))
+        .register_fn("press_key", |key: &str, secs: f64| {
+            act(GameAction::PressKey(vk(key)?, secs))
+        })
+        .register_fn("click", |x: i64, y: i64| {
+            let at = base(x, y).to_screen();
+            act(GameAction::ClickAt(at.x, at.y))
+        })
+        .register_fn("click_screen", |x: i64, y: i64| {
+            act(GameAction::ClickAt(x as i32, y as i32))
+        })
+        .register_fn("move_to", |x: i64, y: i64| {
+            let at = base(x, y).to_screen();
+            act(GameAction::MoveTo(at.x, at.y))
+        })
+        .register_fn("move_to_screen", |x: i64, y: i64| {
+            act(GameAction::MoveTo(x as i32, y as i32))
+        })
+        .register_fn("turn", |dx: i64, dy: i64| {
+            act(GameAction::SendRelative(dx as i32, dy as i32))
+        })
+        .register_fn("sleep", |secs: f64| sleep(secs))
+        .register_fn("sleep", |secs: i64| sleep(secs as f64))
+        .register_fn("scale_x", |x: i64| scale_x(x as i32) as i64)
+        .register_fn("scale_y", |y: i64| scale_y(y as i32) as i64)
+        .register_fn("dev_x", |x: i64| dev_x(x as i32) as i64)
+        .register_fn("dev_y", |y: i64| dev_y(y as i32) as i64);
+}
+
+/// OCR 查询
+fn register_ocr(engine: &mut Engine) {
+    engine
+        .register_fn("ocr", |x: i64, y: i64, w: i64, h: i64| {
+            ocr_region(Region::new(x as i32, y as i32, w as i32, h as i32).base_to_screen())
+        })
+        .register_fn("ocr_screen", |x: i64, y: i64, w: i64, h: i64| {
+            ocr_region(Region::new(x as i32, y as i32, w as i32, h as i32))
+        })
+        .register_fn("find_text", |text: &str| -> ScriptResult<Dynamic> {
+            let results = ocr_full_screen()?;
+            Ok(find_text_contains(&results, text)
+                .map(|r| Dynamic::from_map(ocr_item(r)))
+                .unwrap_or(Dynamic::UNIT))
+        })
+        .register_fn("click_text", |text: &str| -> ScriptResult<bool> {
+            let results = ocr_full_screen()?;
+            let Some(found) = find_text_contains(&results, text) else {
+                return Ok(false);
+            };
+            let (cx, cy) = found.center();
+            act(GameAction::ClickAt(cx, cy))?;
+            thread::sleep(Duration::from_millis(500));
+            Ok(true)
+        });
+}
+
+/// 后台监控值
+fn register_monitor(engine: &mut Engine) {
+    engine
+        .register_fn("wave", || monitor::current_wave() as i64)
+        .register_fn("gold", monitor::current_gold)
+        .register_fn("value", |name: &str| {
+            monitor::value(name)
+                .map(watch_value_to_dynamic)
+                .unwrap_or(Dynamic::UNIT)
+        })
+        .register_fn("time_to_next_wave", || {
+            monitor::time_to_next_wave()
+                .map(|d| Dynamic::from_float(d.as_secs_f64()))
+                .unwrap_or(Dynamic::UNIT)
+        });
+}
+
+/// 超时错误保留原类型（`script_error` 还原），其余错误转为文字
+fn script_err<T>(result: Result<T>) -> ScriptResult<T> {
+    result.map_err(|e| match e.downcast::<WaitTimeout>() {
+        Ok(timeout) => EvalAltResult::ErrorRuntime(Dynamic::from(timeout), Position::NONE).into(),
+        Err(e) => format!("{:#}", e).into(),
+    })
+}
+
+/// 脚本错误转为 `anyhow::Error`，等待超时还原为 `WaitTimeout` 供回合循环识别
+fn script_error(name: &str, e: &EvalAltResult) -> anyhow::Error {
+    if let EvalAltResult::ErrorRuntime(value, _) = e.unwrap_inner() {
+        if let Some(timeout) = value.clone().try_cast::<WaitTimeout>() {
+            return timeout.into();
+        }
+    }
+    anyhow!("脚本 {}() 出错: {}", name, e)
+}
+
+/// 脚本中的秒数（整数或小数，非负）
+fn timeout_secs(value: &Dynamic) -> Option<Duration> {
+    let secs = value
+        .as_float()
+        .ok()
+        .or_else(|| value.as_int().ok().map(|v| v as f64))?;
+    (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs_f64(secs))
+}
+
+fn timeout_opts(secs: &Dynamic) -> ScriptResult<WaitOptions> {
+    let timeout = timeout_secs(secs).ok_or_else(|| format!("超时秒数无效: {}", secs))?;
+    Ok(WaitOptions {
+        timeout: Some(timeout),
+        ..WaitOptions::default()
+    })
+}
+
+/// 单个键鼠动作，与 `actions` 一样经过调试器检查点
+fn act(action: GameAction) -> ScriptResult<()> {
+    script_err(execute_actions(&[action])).map(drop)
+}
+
+/// 脚本传入的基准坐标
+fn base(x: i64, y: i64) -> BasePoint {
+    BasePoint::new(x as i32, y as i32)
+}
+
+/// `[[x, y], ...]` 坐标数组
+fn points<P>(positions: Array, point: fn(i32, i32) -> P) -> ScriptResult<Vec<P>> {
+    let positions: Vec<(i32, i32)> = rhai::serde::from_dynamic(&Dynamic::from_array(positions))?;
+    Ok(positions.into_iter().map(|(x, y)| point(x, y)).collect())
+}
+
+fn ocr_region(region: Region) -> ScriptResult<Array> {
+    let results = script_err(ocr_screen(region, false, IS_DEBUG))?;
+    Ok(results
+        .iter()
+        .map(|r| Dynamic::from_map(ocr_item(r)))
+        .collect())
+}
+
+fn vk(key: &str) -> ScriptResult<u16> {
+    get_vk_code(key).ok_or_else(|| format!("未知的按键: {}", key).into())
+}
+
+/// 可被停止信号打断的等待
+fn sleep(secs: f64) {
+    let deadline = Instant::now() + Duration::from_secs_f64(valid_secs(secs));
+    while !should_stop() {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        thread::sleep((deadline - now).min(Duration::from_millis(100)));
+    }
+}
+
+fn ocr_full_screen() -> ScriptResult<Vec<OcrResultItem>> {
+    let screen = full_screen_region();
+    script_err(ocr_screen(screen, false, IS_DEBUG))
+}
+
+/// OCR 结果转为脚本对象 `#{ text, x, y, score }`（x / y 为文字框中心）
+/// OCR 结果，中心点换算成基准坐标，可以直接传给 `click` / `move_to`
+fn ocr_item(item: &OcrResultItem) -> Map {
+    let (cx, cy) = item.center();
+    let center = ScreenPoint::new(cx, cy).to_base();
+    let mut map = Map::new();
+    map.insert("text".into(), item.text.clone().into());
+    map.insert("x".into(), (center.x as i64).into());
+    map.insert("y".into(), (center.y as i64).into());
+    map.insert("score".into(), (item.score as f64).into());
+    map
+}
+
+fn watch_value_to_dynamic(value: WatchValue) -> Dynamic {
+    match value {
+        WatchValue::Int(v) => v.into(),
+        WatchValue::Text(s) => s.into(),
+        WatchValue::Ratio(r) => r.into(),
+        WatchValue::Color(c) => (c as i64).into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = r#"
+const MAP = "大厦";
+const DIFFICULTY = "炼狱";
+
+fn run_waves() {
+    for wave in 2..=3 {
+        wait_wave(wave);
+    }
+}
+"#;
+
+    #[test]
+    fn test_compile_reads_metadata() {
+        let script = ScriptStrategy::compile(EXAMPLE).unwrap();
+        assert_eq!(script.map, "大厦");
+        assert_eq!(script.difficulty, "炼狱");
+        assert!(script.has_fn("run_waves", 0));
+        assert!(!script.has_fn("start", 0));
+
+        assert!(ScriptStrategy::compile("const MAP = \"大厦\"; fn run_waves() {}").is_err());
+        assert!(ScriptStrategy::compile("const MAP = \"a\"; const DIFFICULTY = \"b\";").is_err());
+        assert!(ScriptStrategy::compile("fn run_waves( {").is_err());
+
+        assert_eq!(script.wait_timeout, None);
+        let with_timeout = format!("const WAIT_TIMEOUT = 600;\n{}", EXAMPLE);
+        let script = ScriptStrategy::compile(&with_timeout).unwrap();
+        assert_eq!(script.wait_timeout, Some(Duration::from_secs(600)));
+    }
+
+    #[test]
+    fn test_wait_timeout_survives_script() {
+        let mut engine = Engine::new();
+        engine.register_fn("wait", || -> ScriptResult<()> {
+            script_err(Err(WaitTimeout {
+                what: "波次 >= 3".into(),
+                timeout: Duration::from_secs(5),
+            }
+            .into()))
+        });
+        let ast = engine
+            .compile("fn inner() { wait(); } fn run_waves() { inner(); }")
+            .unwrap();
+        let err = engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &ast, "run_waves", ())
+            .unwrap_err();
+
+        let err = script_error("run_waves", &err);
+        let timeout = err.downcast_ref::<WaitTimeout>().unwrap();
+        assert_eq!(timeout.what, "波次 >= 3");
+    }
+
+    #[test]
+    fn test_script_values_convert() {
+        let engine = Engine::new();
+
+        let actions: Dynamic = engine
+            .eval(r#"[#{ type: "turn", dx: 100, dy: -5 }, #{ type: "sleep", secs: 0.5 }]"#)
+            .unwrap();
+        let actions: Vec<Action> = rhai::serde::from_dynamic(&actions).unwrap();
+        assert_eq!(actions[0], Action::Turn { dx: 100, dy: -5 });
+        assert_eq!(actions[1], Action::Sleep { secs: 0.5 });
+
+        let positions: Dynamic = engine.eval("[[700, 400], [900, 410]]").unwrap();
+        let positions: Vec<(i32, i32)> = rhai::serde::from_dynamic(&positions).unwrap();
+        assert_eq!(positions, vec![(700, 400), (900, 410)]);
+    }
+}
@@ -0,0 +1,400 @@
+//! 局内商店（N 键）
+//!
+//! 全屏 OCR 识别商店界面：页签（"全部" / "地面" / "墙面"）、商品名称和价格。
+//! 商品卡片上名称在上、价格在下，按位置把价格归到正上方最近的名称（`group_items`）。
+//! 当前页面找不到的商品先向下滚动列表，再切换页签查找。
+//!
+//! ```ignore
+//! shop::open()?;
+//! for entry in shop::catalog()? {
+//!     println!("{} {} {:?}", entry.tab, entry.name, entry.price);
+//! }
+//! shop::buy("天网")?;
+//! shop::close();
+//! ```
+
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+
+use super::common::IS_DEBUG;
+use crate::input::{click_at, left_click, mouse_scroll, move_to, tap_key, ScrollDirection, VK_N};
+use crate::ocr::{find_text_contains, ocr_screen, OcrResultItem};
+use crate::screen::{full_screen_region, get_screen_resolution, scale_x, scale_y};
+use crate::stop_flag::should_stop;
+use crate::timeline;
+
+/// 商店页签（打开时默认 "全部"）
+pub const TABS: [&str; 3] = ["全部", "地面", "墙面"];
+
+/// 分类页签（`catalog` 按它们标注商品分类）
+const CATEGORY_TABS: [&str; 2] = ["地面", "墙面"];
+
+/// 已装备标记（购买后显示在商品卡片上）
+const EQUIPPED_MARK: &str = "已装备";
+
+/// 价格与名称的最大水平偏移（1920x1080 基准）
+const CARD_MAX_DX: i32 = 80;
+/// 价格在名称下方的最大距离（1920x1080 基准）
+const CARD_MAX_DY: i32 = 120;
+
+/// 每次翻页滚动的格数
+const SCROLL_STEPS: u32 = 3;
+/// 单个页签最多翻页次数（防止列表识别异常时无限滚动）
+const MAX_SCROLLS: u32 = 10;
+
+/// 商店中的一个商品
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShopItem {
+    pub name: String,
+    /// 价格（识别不到时为 None）
+    pub price: Option<u32>,
+    /// 名称文字的中心（屏幕坐标）
+    pub center: (i32, i32),
+    /// 卡片上有 "已装备" 标记
+    pub equipped: bool,
+}
+
+/// 商品目录中的一项
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogEntry {
+    /// 所在页签（只在 "全部" 中出现的商品为 "全部"）
+    pub tab: String,
+    pub name: String,
+    pub price: Option<u32>,
+}
+
+/// 全屏 OCR
+fn scan() -> Result<Vec<OcrResultItem>> {
+    let screen = full_screen_region();
+    ocr_screen(screen, false, IS_DEBUG)
+}
+
+/// OCR 结果中是否有商店页签
+pub fn is_open_in(results: &[OcrResultItem]) -> bool {
+    CATEGORY_TABS
+        .iter()
+        .any(|tab| find_text_contains(results, tab).is_some())
+}
+
+/// 商店是否已打开
+pub fn is_open() -> Result<bool> {
+    Ok(is_open_in(&scan()?))
+}
+
+/// 打开商店（已打开时不操作），打开失败返回错误
+pub fn open() -> Result<()> {
+    if is_open()? {
+        return Ok(());
+    }
+    tap_key(VK_N);
+    thread::sleep(Duration::from_secs(1));
+    if !is_open()? {
+        bail!("商店未打开（没有识别到 '地面' / '墙面' 页签）");
+    }
+    Ok(())
+}
+
+/// 关闭商店（按 N）
+pub fn close() {
+    tap_key(VK_N);
+    thread::sleep(Duration::from_millis(500));
+}
+
+/// 解析价格文字（如 "1500"、"1,500"），不是价格时返回 None
+fn parse_price(text: &str) -> Option<u32> {
+    let text = text.trim();
+    if text.is_empty() || !text.chars().all(|c| c.is_ascii_digit() || c == ',') {
+        return None;
+    }
+    text.replace(',', "").parse().ok()
+}
+
+/// 把 OCR 结果按卡片布局分组为商品
+///
+/// 名称为非数字文字（页签、"已装备" 除外）；价格为名称正下方 `max_dy` 以内、
+/// 水平偏移 `max_dx` 以内最近的数字。没有价格的文字不算商品（标题、按钮等）。
+pub fn group_items(results: &[OcrResultItem], max_dx: i32, max_dy: i32) -> Vec<ShopItem> {
+    let below = |name: (i32, i32), other: (i32, i32)| {
+        let (dx, dy) = (other.0 - name.0, other.1 - name.1);
+        (dx.abs() <= max_dx && dy > 0 && dy <= max_dy).then_some(dy)
+    };
+
+    let mut items: Vec<ShopItem> = results
+        .iter()
+        .filter(|r| {
+            let text = r.text.trim();
+            parse_price(text).is_none() && !text.contains(EQUIPPED_MARK) && !TABS.contains(&text)
+        })
+        .filter_map(|name| {
+            let center = name.center();
+            let price = results
+                .iter()
+                .filter_map(|r| Some((below(center, r.center())?, parse_price(&r.text)?)))
+                .min_by_key(|(dy, _)| *dy)?
+                .1;
+            let equipped = results
+                .iter()
+                .any(|r| r.text.contains(EQUIPPED_MARK) && below(center, r.center()).is_some());
+            Some(ShopItem {
+                name: name.text.trim().to_string(),
+                price: Some(price),
+                center,
+                equipped,
+            })
+        })
+        .collect();
+    // 按行、列排序（同一行的 y 可能有几个像素的误差）
+    items.sort_by_key(|item| (item.center.1 / (max_dy / 2).max(1), item.center.0));
+    items
+}
+
+/// 当前页面可见的商品
+pub fn visible_items() -> Result<Vec<ShopItem>> {
+    Ok(group_items(
+        &scan()?,
+        scale_x(CARD_MAX_DX),
+        scale_y(CARD_MAX_DY),
+    ))
+}
+
+/// 切换页签
+///
+/// # Returns
+/// 是否找到并点击了页签
+pub fn switch_tab(tab: &str) -> Result<bool> {
+    let results = scan()?;
+    let Some(tab_result) = results.iter().find(|r| r.text.trim() == tab) else {
+        return Ok(false);
+    };
+    println!("[shop] 切换到 '{}' 页面", tab);
+    let (tx, ty) = tab_result.center();
+    click_at(tx, ty);
+    thread::sleep(Duration::from_millis(500));
+    Ok(true)
+}
+
+/// 滚动商品列表（鼠标移到可见商品中间再滚动）
+pub fn scroll(items: &[ShopItem], direction: ScrollDirection, steps: u32) {
+    let (x, y) = if items.is_empty() {
+        let (w, h) = get_screen_resolution();
+        (w as i32 / 2, h as i32 / 2)
+    } else {
+        let n = items.len() as i32;
+        let sx: i32 = items.iter().map(|i| i.center.0).sum();
+        let sy: i32 = items.iter().map(|i| i.center.1).sum();
+        (sx / n, sy / n)
+    };
+    move_to(x, y);
+    thread::sleep(Duration::from_millis(100));
+    mouse_scroll(direction, steps, 0.05);
+    thread::sleep(Duration::from_millis(500));
+}
+
+/// 在当前页签中查找商品，找不到时向下翻页直到列表到底
+///
+/// 返回时列表停留在找到商品的位置；找不到时滚回顶部。
+fn find_on_tab(name: &str) -> Result<Option<ShopItem>> {
+    let mut items = visible_items()?;
+    let mut scrolled = 0;
+    loop {
+        if let Some(item) = items.iter().find(|i| i.name.contains(name)) {
+            return Ok(Some(item.clone()));
+        }
+        if should_stop() || scrolled >= MAX_SCROLLS {
+            break;
+        }
+        scroll(&items, ScrollDirection::Down, SCROLL_STEPS);
+        scrolled += 1;
+        let next = visible_items()?;
+        let names = |items: &[ShopItem]| items.iter().map(|i| i.name.clone()).collect::<Vec<_>>();
+        // 滚动后内容不变：已到底
+        if names(&next) == names(&items) {
+            break;
+        }
+        items = next;
+    }
+    if scrolled > 0 {
+        scroll(&items, ScrollDirection::Up, SCROLL_STEPS * scrolled);
+    }
+    Ok(None)
+}
+
+/// 查找商品：先当前页面（含翻页），再依次切换 "地面"、"墙面"
+pub fn find_item(name: &str) -> Result<Option<ShopItem>> {
+    if let Some(item) = find_on_tab(name)? {
+        return Ok(Some(item));
+    }
+    for tab in CATEGORY_TABS {
+        if should_stop() {
+            break;
+        }
+        if switch_tab(tab)? {
+            if let Some(item) = find_on_tab(name)? {
+                println!("[shop] 在 '{}' 页面找到 '{}'", tab, name);
+                return Ok(Some(item));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// 当前页签的全部商品（翻页到底后滚回顶部）
+fn scan_tab() -> Result<Vec<ShopItem>> {
+    let mut all: Vec<ShopItem> = Vec::new();
+    let mut items = visible_items()?;
+    let mut scrolled = 0;
+    loop {
+        let mut added = false;
+        for item in &items {
+            if !all.iter().any(|i| i.name == item.name) {
+                all.push(item.clone());
+                added = true;
+            }
+        }
+        if !added || should_stop() || scrolled >= MAX_SCROLLS {
+            break;
+        }
+        scroll(&items, ScrollDirection::Down, SCROLL_STEPS);
+        scrolled += 1;
+        items = visible_items()?;
+    }
+    if scrolled > 0 {
+        scroll(&items, ScrollDirection::Up, SCROLL_STEPS * scrolled);
+    }
+    Ok(all)
+}
+
+/// 商品目录：遍历 "地面"、"墙面"（含翻页），再补上只在 "全部" 中出现的商品
+///
+/// 商店需已打开，结束时切回 "全部"。
+pub fn catalog() -> Result<Vec<CatalogEntry>> {
+    let mut entries: Vec<CatalogEntry> = Vec::new();
+    let mut add = |tab: &str, items: Vec<ShopItem>| {
+        for item in items {
+            if !entries.iter().any(|e| e.name == item.name) {
+                entries.push(CatalogEntry {
+                    tab: tab.to_string(),
+                    name: item.name,
+                    price: item.price,
+                });
+            }
+        }
+    };
+    for tab in CATEGORY_TABS {
+        if switch_tab(tab)? {
+            add(tab, scan_tab()?);
+        }
+    }
+    if switch_tab(TABS[0])? {
+        add(TABS[0], scan_tab()?);
+    }
+    println!("[shop] 目录: {} 个商品", entries.len());
+    Ok(entries)
+}
+
+/// 同名文字出现的次数（商品列表 + 装备栏）
+fn count_name(results: &[OcrResultItem], name: &str) -> usize {
+    results.iter().filter(|r| r.text.contains(name)).count()
+}
+
+/// 购买商品并确认进入装备栏
+///
+/// 点击后重新识别：商品卡片出现 "已装备"，或者名称多出现一次（装备栏中）才算购买成功。
+/// 商店需已打开。
+///
+/// # Returns
+/// - Ok(true) 购买成功
+/// - Ok(false) 找不到商品或购买后没有进入装备栏
+pub fn buy(name: &str) -> Result<bool> {
+    let Some(item) = find_item(name)? else {
+        println!("[shop] 未找到 '{}', 跳过", name);
+        timeline::step("buy_trap_missing", name);
+        return Ok(false);
+    };
+    if item.equipped {
+        println!("[shop] '{}' 已装备", name);
+        timeline::step("buy_trap", name);
+        return Ok(true);
+    }
+
+    let before = count_name(&scan()?, name);
+    println!("[shop] 购买 '{}' (价格 {:?})", name, item.price);
+    buy_click(item.center);
+
+    let results = scan()?;
+    let equipped = group_items(&results, scale_x(CARD_MAX_DX), scale_y(CARD_MAX_DY))
+        .iter()
+        .any(|i| i.name.contains(name) && i.equipped);
+    if equipped || count_name(&results, name) > before {
+        timeline::step("buy_trap", name);
+        Ok(true)
+    } else {
+        println!("[shop] '{}' 购买后没有进入装备栏（金币不足？）", name);
+        timeline::step("buy_trap_unverified", name);
+        Ok(false)
+    }
+}
+
+/// 点击购买（名称右下方的购买区域，连点三次）
+fn buy_click((center_x, center_y): (i32, i32)) {
+    let _action = crate::pause::action();
+    move_to(center_x + scale_x(50), center_y + scale_y(50));
+    thread::sleep(Duration::from_millis(300));
+    left_click();
+    thread::sleep(Duration::from_millis(300));
+    left_click();
+    thread::sleep(Duration::from_millis(300));
+    left_click();
+    thread::sleep(Duration::from_millis(500));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_items() {
+        let results = vec![
+            OcrResultItem::at("地面", 100, 50),
+            OcrResultItem::at("墙面", 200, 50),
+            OcrResultItem::at("天网", 300, 200),
+            OcrResultItem::at("1,500", 305, 260),
+            OcrResultItem::at("已装备", 300, 230),
+            OcrResultItem::at("天启", 500, 202),
+            OcrResultItem::at("2000", 498, 262),
+            OcrResultItem::at("破坏者", 300, 400),
+            OcrResultItem::at("800", 300, 460),
+            // 没有价格的文字不算商品
+            OcrResultItem::at("商店", 900, 50),
+            // 价格太远，不属于这个名称
+            OcrResultItem::at("修理站", 700, 200),
+            OcrResultItem::at("900", 700, 500),
+        ];
+        let items = group_items(&results, 80, 120);
+        let summary: Vec<_> = items
+            .iter()
+            .map(|i| (i.name.as_str(), i.price, i.equipped))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("天网", Some(1500), true),
+                ("天启", Some(2000), false),
+                ("破坏者", Some(800), false),
+            ]
+        );
+        assert!(is_open_in(&results));
+        assert!(!is_open_in(&results[2..]));
+    }
+
+    #[test]
+    fn test_parse_price() {
+        assert_eq!(parse_price("1500"), Some(1500));
+        assert_eq!(parse_price(" 1,500 "), Some(1500));
+        assert_eq!(parse_price("天网"), None);
+        assert_eq!(parse_price("x2"), None);
+        assert_eq!(parse_price(""), None);
+    }
+}
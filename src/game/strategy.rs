@@ -0,0 +1,421 @@
+//! 策略接口
+//!
+//! 每张地图的流程实现 `Strategy`：`start` 开始游戏，`on_wave` 处理单个波次，
+//! `on_round_end` 在每轮结束时收尾。策略实例在一次运行（多轮）中保持，可以在字段里记录状态。
+//! 停止后在对局中途重新开始时，回合循环调用 `resume` 代替 `start`，从当前波次接着执行。
+//! 编译内置的模块、策略文件和脚本都实现这个接口，统一注册到 `available_strategies()`。
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use super::common::wait_for_game_end;
+use super::debugger::{self, Checkpoint};
+use super::scheduler::{LatePolicy, Scheduler, Trigger};
+use crate::stop_flag::should_stop;
+
+/// 策略元数据（GUI 下拉框显示）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    /// 地图名称
+    pub name: String,
+    /// 难度
+    pub difficulty: String,
+    /// 简要说明（可为空）
+    pub description: String,
+}
+
+impl Metadata {
+    pub fn new(name: &str, difficulty: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            difficulty: difficulty.to_string(),
+            description: String::new(),
+        }
+    }
+
+    /// 下拉框显示文字，也是参数保存时的键
+    pub fn label(&self) -> String {
+        format!("{} ({})", self.name, self.difficulty)
+    }
+}
+
+/// 参数值
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl ParamValue {
+    /// 按当前值的类型解析字符串（用于读取保存的设置），格式不对时返回 None
+    pub fn parse_as(&self, s: &str) -> Option<Self> {
+        let s = s.trim();
+        match self {
+            Self::Bool(_) => s.parse().ok().map(Self::Bool),
+            Self::Int(_) => s.parse().ok().map(Self::Int),
+            Self::Float(_) => s.parse().ok().map(Self::Float),
+            Self::Text(_) => Some(Self::Text(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for ParamValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(v) => write!(f, "{}", v),
+            Self::Int(v) => write!(f, "{}", v),
+            Self::Float(v) => write!(f, "{}", v),
+            Self::Text(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// 策略参数声明（GUI 中可编辑）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    /// 参数键（`Ctx::bool` 等使用）
+    pub key: String,
+    /// GUI 显示名称
+    pub label: String,
+    /// 默认值，同时决定参数类型
+    pub default: ParamValue,
+}
+
+impl Parameter {
+    pub fn new(key: &str, label: &str, default: ParamValue) -> Self {
+        Self {
+            key: key.to_string(),
+            label: label.to_string(),
+            default,
+        }
+    }
+}
+
+/// 用保存的值覆盖参数默认值，无法解析的值使用默认值
+pub fn resolve_params(
+    params: &[Parameter],
+    saved: &HashMap<String, String>,
+) -> HashMap<String, ParamValue> {
+    params
+        .iter()
+        .map(|p| {
+            let value = saved
+                .get(&p.key)
+                .and_then(|s| p.default.parse_as(s))
+                .unwrap_or_else(|| p.default.clone());
+            (p.key.clone(), value)
+        })
+        .collect()
+}
+
+/// 一轮的结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum RoundOutcome {
+    /// 所有波次执行完成
+    Completed,
+    /// 检测到停止信号
+    Stopped,
+    /// 出错（错误信息）
+    Failed(String),
+}
+
+impl RoundOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Completed => "completed",
+            Self::Stopped => "stopped",
+            Self::Failed(_) => "failed",
+        }
+    }
+}
+
+/// 执行的波次范围（调试用：从第 N 波开始、到第 M 波为止）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaveRange {
+    /// 第一个处理的波次
+    pub first: u32,
+    /// 最后一个处理的波次（None = 不限，处理完后照常等待游戏结束）
+    pub last: Option<u32>,
+}
+
+impl Default for WaveRange {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl WaveRange {
+    /// 全部波次
+    pub fn all() -> Self {
+        Self {
+            first: 1,
+            last: None,
+        }
+    }
+
+    /// 只处理一个波次
+    pub fn single(wave: u32) -> Self {
+        Self {
+            first: wave,
+            last: Some(wave),
+        }
+    }
+
+    pub fn contains(&self, wave: u32) -> bool {
+        wave >= self.first
+            && match self.last {
+                Some(last) => wave <= last,
+                None => true,
+            }
+    }
+
+    /// 是否只处理部分波次
+    pub fn is_partial(&self) -> bool {
+        self.first > 1 || self.last.is_some()
+    }
+}
+
+impl fmt::Display for WaveRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.last {
+            Some(last) if last == self.first => write!(f, "波次 {}", last),
+            Some(last) => write!(f, "波次 {}-{}", self.first, last),
+            None => write!(f, "波次 {}-", self.first),
+        }
+    }
+}
+
+/// 策略运行上下文
+#[derive(Debug, Clone, Default)]
+pub struct Ctx {
+    /// 当前轮次（从 1 开始）
+    pub round: u32,
+    /// 正在处理的波次（0 = 尚未进入波次）
+    pub wave: u32,
+    /// 本轮是中途接管的（接管时所在的波次），之前的波次不再执行
+    pub resumed_at: Option<u32>,
+    /// 执行的波次范围
+    pub range: WaveRange,
+    params: HashMap<String, ParamValue>,
+}
+
+impl Ctx {
+    pub fn new(params: HashMap<String, ParamValue>) -> Self {
+        Self {
+            round: 0,
+            wave: 0,
+            resumed_at: None,
+            range: WaveRange::all(),
+            params,
+        }
+    }
+
+    pub fn param(&self, key: &str) -> Option<&ParamValue> {
+        self.params.get(key)
+    }
+
+    pub fn bool(&self, key: &str) -> Option<bool> {
+        match self.param(key)? {
+            ParamValue::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn int(&self, key: &str) -> Option<i64> {
+        match self.param(key)? {
+            ParamValue::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn float(&self, key: &str) -> Option<f64> {
+        match self.param(key)? {
+            ParamValue::Float(v) => Some(*v),
+            ParamValue::Int(v) => Some(*v as f64),
+            _ => None,
+        }
+    }
+
+    pub fn text(&self, key: &str) -> Option<&str> {
+        match self.param(key)? {
+            ParamValue::Text(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// 地图策略
+pub trait Strategy: Send {
+    /// 地图名称、难度等
+    fn metadata(&self) -> Metadata;
+
+    /// 可在 GUI 中调整的参数
+    fn parameters(&self) -> Vec<Parameter> {
+        Vec::new()
+    }
+
+    /// 从大厅开始游戏，返回时应已进入第 1 波
+    fn start(&mut self, ctx: &mut Ctx) -> Result<()>;
+
+    /// 中途接管：游戏已在第 `wave` 波时代替 `start` 调用（`ctx.resumed_at` 已设置）
+    ///
+    /// 用于重放之前波次中可重复执行的设置步骤，默认什么都不做
+    fn resume(&mut self, _ctx: &mut Ctx, _wave: u32) -> Result<()> {
+        Ok(())
+    }
+
+    /// 设置步骤：从第 `first` 波开始执行（`ctx.range`）且勾选了 "先执行设置步骤" 时，
+    /// 在 `start` 之后调用，补执行 `first` 之前波次中的准备工作（如购买陷阱）。默认什么都不做
+    fn setup(&mut self, _ctx: &mut Ctx, _first: u32) -> Result<()> {
+        Ok(())
+    }
+
+    /// 需要处理的波次，默认的 `run_waves` 在每个波次开始时调用 `on_wave`
+    fn waves(&self) -> Vec<u32> {
+        Vec::new()
+    }
+
+    /// 处理一个波次
+    fn on_wave(&mut self, ctx: &mut Ctx, wave: u32) -> Result<()>;
+
+    /// 波次以外的触发器（金币、波内计时、文字），触发时以名称调用 `on_trigger`
+    fn triggers(&self) -> Vec<(String, Trigger)> {
+        Vec::new()
+    }
+
+    /// 处理 `triggers` 中的触发器
+    fn on_trigger(&mut self, _ctx: &mut Ctx, _name: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// 处理器执行过久越过下一波、或监控跳过了某个波次时的处理方式
+    fn late_policy(&self) -> LatePolicy {
+        LatePolicy::CatchUp
+    }
+
+    /// 等待函数（`wait_wave`、`wait_gold`、`wait_for_game_end` 等）的默认超时，None = 不限时
+    ///
+    /// 超时返回 `WaitTimeout` 错误，回合循环结束本轮并尝试回到大厅
+    fn wait_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// 每轮结束时调用（无论成功、停止还是出错）
+    fn on_round_end(&mut self, _ctx: &mut Ctx, _outcome: &RoundOutcome) -> Result<()> {
+        Ok(())
+    }
+
+    /// 所有波次处理完后调用，默认等待游戏结束
+    fn finish(&mut self, _ctx: &mut Ctx) -> Result<()> {
+        wait_for_game_end()
+    }
+
+    /// 执行本轮所有波次
+    ///
+    /// 默认：用 `Scheduler` 按监控事件分发 `waves` 和 `triggers`，全部处理完后 `finish`。
+    /// 只分发 `ctx.range` 内、中途接管波次之后的波次和波内计时触发器；
+    /// 范围有上限时处理完最后一波直接返回，不等待游戏结束。
+    /// 每波开始前经过步骤调试器的波次检查点，调试器选择 "跳过" 时不执行这一波。
+    fn run_waves(&mut self, ctx: &mut Ctx) -> Result<()> {
+        let resumed_at = ctx.resumed_at.unwrap_or(0);
+        let range = ctx.range;
+        let wanted = |wave: u32| wave >= resumed_at && range.contains(wave);
+
+        let mut scheduler = Scheduler::new(self.late_policy());
+        for wave in self.waves().into_iter().filter(|w| wanted(*w)) {
+            scheduler.on(Trigger::Wave { wave }, Event::Wave(wave));
+        }
+        for (name, trigger) in self.triggers() {
+            match trigger {
+                Trigger::Wave { wave } | Trigger::AfterSecs { wave, .. } if !wanted(wave) => {}
+                trigger => {
+                    scheduler.on(trigger, Event::Trigger(name));
+                }
+            }
+        }
+
+        scheduler.run(|event| match event {
+            Event::Wave(wave) => {
+                ctx.wave = *wave;
+                if !debugger::checkpoint(Checkpoint::wave(*wave)) {
+                    return Ok(());
+                }
+                self.on_wave(ctx, *wave)
+            }
+            Event::Trigger(name) => self.on_trigger(ctx, name),
+        })?;
+
+        if !should_stop() && range.last.is_none() {
+            self.finish(ctx)?;
+        }
+        Ok(())
+    }
+}
+
+/// 默认 `run_waves` 的调度事件
+enum Event {
+    Wave(u32),
+    Trigger(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_params() {
+        let params = vec![
+            Parameter::new("skip", "跳过", ParamValue::Bool(true)),
+            Parameter::new("gold", "金币", ParamValue::Int(2500)),
+            Parameter::new("ratio", "比例", ParamValue::Float(0.5)),
+        ];
+        let saved = HashMap::from([
+            ("skip".to_string(), "false".to_string()),
+            ("gold".to_string(), "abc".to_string()),
+            ("other".to_string(), "1".to_string()),
+        ]);
+
+        let ctx = Ctx::new(resolve_params(&params, &saved));
+        assert_eq!(ctx.bool("skip"), Some(false));
+        // 无法解析时用默认值
+        assert_eq!(ctx.int("gold"), Some(2500));
+        assert_eq!(ctx.float("ratio"), Some(0.5));
+        // 类型不匹配或未声明
+        assert_eq!(ctx.text("gold"), None);
+        assert!(ctx.param("other").is_none());
+    }
+
+    #[test]
+    fn test_wave_range() {
+        let all = WaveRange::all();
+        assert!(all.contains(1) && all.contains(99));
+        assert!(!all.is_partial());
+
+        let range = WaveRange {
+            first: 3,
+            last: Some(5),
+        };
+        assert!(!range.contains(2) && range.contains(3) && range.contains(5));
+        assert!(!range.contains(6));
+        assert_eq!(range.to_string(), "波次 3-5");
+        assert_eq!(WaveRange::single(4).to_string(), "波次 4");
+        assert!(WaveRange::single(1).is_partial());
+    }
+
+    #[test]
+    fn test_param_value_round_trip() {
+        for value in [
+            ParamValue::Bool(true),
+            ParamValue::Int(-3),
+            ParamValue::Float(1.25),
+            ParamValue::Text("天网".into()),
+        ] {
+            assert_eq!(value.parse_as(&value.to_string()), Some(value.clone()));
+        }
+    }
+}
@@ -0,0 +1,755 @@
+//! 策略文件（TOML / JSON）
+//!
+//! exe 同目录 `strategies/` 下的每个 `.toml` / `.json` 文件描述一张地图的完整流程，
+//! 启动时加载进地图下拉框，新地图无需重新编译：
+//!
+//! ```toml
+//! map = "训练基地"
+//! difficulty = "困难"
+//! loadout = ["防空导弹", "自修复磁暴塔", "破坏者", "修理站"]
+//!
+//! # 省略 start 时默认为 start_game（按 difficulty 选难度开始）
+//! start = [{ action = "start_game" }]
+//!
+//! [[waves]]
+//! wave = 1
+//! steps = [
+//!     { action = "buy_traps" },
+//!     { action = "wait_gold", amount = 2500 },
+//!     { action = "place_trap", x = 800, y = 400, trap = "自修复磁暴塔" },
+//! ]
+//!
+//! [[waves]]
+//! wave = 2
+//! steps = [{ action = "upgrade_trap", trap = "自修复磁暴塔" }]
+//! ```
+//!
+//! 陷阱步骤的 `trap` 是装备中的陷阱名称，热键按购买结果换算（见 `Loadout`）；
+//! 也可以直接写热键（`trap = "5"`，旧写法 `key = "5"` 同样可用）。
+//!
+//! 波次中的步骤可以加 `idempotent = true`，表示可以重复执行：对局中途停止后重新开始时，
+//! 之前波次中标记的步骤会先重放一遍（其余跳过），再从当前波次继续。
+//! 加 `setup = true` 表示设置步骤：在 GUI 中从第 N 波开始调试时，可以先补执行之前波次的设置步骤。
+//!
+//! 每个波次的步骤在监控检测到该波次时执行（见 `scheduler`），全部处理完后 `wait_for_game_end()`。
+//! `[[triggers]]` 可以在金币、波内计时、文字出现时执行步骤：
+//!
+//! ```toml
+//! late_policy = "skip"   # 过期波次的处理方式，默认 catch_up
+//! wait_timeout_secs = 900   # 等待步骤的默认超时，省略时不限时
+//!
+//! [[triggers]]
+//! when = { type = "gold", amount = 8000 }
+//! steps = [{ action = "upgrade_trap", trap = "自修复磁暴塔" }]
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::common::{
+    buy_traps_ordered, execute_actions, place_trap, place_traps, start_game_with_difficulty,
+    upgrade_trap, wait_for_game_end, wait_gold_with, wait_wave_with, Deadline, GameAction,
+    WaitOptions, IS_DEBUG,
+};
+use super::scheduler::{LatePolicy, Trigger};
+use super::strategy::{Ctx, Metadata, Strategy};
+use crate::input::get_vk_code;
+use crate::ocr::{find_text_contains, ocr_screen};
+use crate::screen::{full_screen_region, BasePoint, DevPoint, ScreenPoint};
+use crate::stop_flag::should_stop;
+
+/// 策略文件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrategyFile {
+    /// 地图名称（下拉框显示）
+    pub map: String,
+    /// 难度（`start_game` 步骤使用）
+    pub difficulty: String,
+    /// 陷阱装备顺序（`buy_traps` 步骤使用，顺序决定 4/5/6/7 热键）
+    #[serde(default)]
+    pub loadout: Vec<String>,
+    /// 开始流程，省略时为 `start_game`
+    #[serde(default)]
+    pub start: Vec<Step>,
+    /// 各波次的步骤
+    #[serde(default)]
+    pub waves: Vec<WavePlan>,
+    /// 波次以外的触发器
+    #[serde(default)]
+    pub triggers: Vec<TriggerPlan>,
+    /// 过期波次的处理方式
+    #[serde(default)]
+    pub late_policy: LatePolicy,
+    /// 全部波次结束后是否等待游戏结束（默认 true）
+    #[serde(default = "default_true")]
+    pub wait_for_game_end: bool,
+    /// 等待步骤的默认超时（秒），省略时不限时
+    #[serde(default)]
+    pub wait_timeout_secs: Option<f64>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 一个波次的步骤
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WavePlan {
+    pub wave: u32,
+    #[serde(default)]
+    pub steps: Vec<WaveStep>,
+}
+
+/// 波次中的一个步骤（`Step` 加上执行标记）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WaveStep {
+    #[serde(flatten)]
+    pub step: Step,
+    /// 可重复执行：中途接管时，之前波次中标记的步骤会重放，其余跳过
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub idempotent: bool,
+    /// 设置步骤（如购买陷阱）：从中间波次开始调试时可以先补执行
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub setup: bool,
+}
+
+impl From<Step> for WaveStep {
+    fn from(step: Step) -> Self {
+        Self {
+            step,
+            idempotent: false,
+            setup: false,
+        }
+    }
+}
+
+fn is_false(v: &bool) -> bool {
+    !*v
+}
+
+/// 一个触发器的步骤
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TriggerPlan {
+    pub when: Trigger,
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+/// 坐标类型（见 strategy-guide 的 "坐标系统与分辨率适配"）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Coords {
+    /// 1920x1080 基准坐标（`scale_x` / `scale_y`）
+    #[default]
+    Base,
+    /// 开发分辨率坐标（`dev_x` / `dev_y`）
+    Dev,
+    /// 实际屏幕坐标，不缩放
+    Screen,
+}
+
+impl Coords {
+    pub(super) fn to_screen(self, x: i32, y: i32) -> ScreenPoint {
+        match self {
+            Self::Base => BasePoint::new(x, y).to_screen(),
+            Self::Dev => DevPoint::new(x, y).to_screen(),
+            Self::Screen => ScreenPoint::new(x, y),
+        }
+    }
+}
+
+/// 策略步骤
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Step {
+    /// 选择难度并开始游戏（省略 difficulty 时用文件的 difficulty）
+    StartGame {
+        #[serde(default)]
+        difficulty: Option<String>,
+    },
+    /// 购买陷阱（省略 traps 时用文件的 loadout）
+    BuyTraps {
+        #[serde(default)]
+        traps: Option<Vec<String>>,
+    },
+    /// 等待金币（`timeout_secs` 省略时用文件的 `wait_timeout_secs`）
+    WaitGold {
+        amount: i64,
+        #[serde(default)]
+        timeout_secs: Option<f64>,
+    },
+    /// 等待波次
+    WaitWave {
+        wave: u32,
+        #[serde(default)]
+        timeout_secs: Option<f64>,
+    },
+    /// 等待指定秒数
+    Sleep { secs: f64 },
+    /// 放置陷阱（`trap` 为陷阱名称或热键）
+    PlaceTrap {
+        x: i32,
+        y: i32,
+        #[serde(alias = "key")]
+        trap: String,
+        #[serde(default)]
+        coords: Coords,
+    },
+    /// 在多个位置放置同一陷阱
+    PlaceTraps {
+        positions: Vec<(i32, i32)>,
+        #[serde(alias = "key")]
+        trap: String,
+        #[serde(default)]
+        coords: Coords,
+    },
+    /// 升级陷阱（长按热键）
+    UpgradeTrap {
+        #[serde(alias = "key")]
+        trap: String,
+    },
+    /// 点击按键
+    TapKey { key: String },
+    /// 按住按键指定秒数
+    PressKey { key: String, secs: f64 },
+    /// 点击坐标
+    Click {
+        x: i32,
+        y: i32,
+        #[serde(default)]
+        coords: Coords,
+    },
+    /// 全屏 OCR 查找文字并点击其中心，找不到时报错
+    ClickText { text: String },
+    /// 等待文字出现（每秒全屏 OCR 一次）
+    WaitText {
+        text: String,
+        #[serde(default)]
+        timeout_secs: Option<f64>,
+    },
+    /// 动作序列（`execute_actions`）
+    Actions { actions: Vec<Action> },
+}
+
+/// 动作序列中的单个动作（对应 `GameAction`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    PressKey {
+        key: String,
+        secs: f64,
+    },
+    TapKey {
+        key: String,
+    },
+    /// 相对移动鼠标（视角转动）
+    Turn {
+        dx: i32,
+        dy: i32,
+    },
+    Sleep {
+        secs: f64,
+    },
+    Click,
+    MoveTo {
+        x: i32,
+        y: i32,
+        #[serde(default)]
+        coords: Coords,
+    },
+}
+
+impl StrategyFile {
+    /// 从文件加载（按扩展名选择 TOML 或 JSON）并校验
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("无法读取策略文件: {}", path.display()))?;
+        let strategy: Self = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(&content).context("TOML 格式错误")?,
+            Some("json") => serde_json::from_str(&content).context("JSON 格式错误")?,
+            _ => bail!("不支持的策略文件类型: {}", path.display()),
+        };
+        strategy.validate()?;
+        Ok(strategy)
+    }
+
+    /// 校验按键名称、陷阱名称和波次顺序
+    pub fn validate(&self) -> Result<()> {
+        let mut last_wave = 0;
+        for plan in &self.waves {
+            if plan.wave <= last_wave {
+                bail!(
+                    "波次必须从 1 开始递增: {} 出现在 {} 之后",
+                    plan.wave,
+                    last_wave
+                );
+            }
+            last_wave = plan.wave;
+        }
+
+        let all_steps = self
+            .start
+            .iter()
+            .chain(self.waves.iter().flat_map(|w| w.steps.iter().map(|s| &s.step)))
+            .chain(self.triggers.iter().flat_map(|t| t.steps.iter()));
+        let all_steps: Vec<&Step> = all_steps.collect();
+        // 陷阱步骤可以用 loadout 或任一 buy_traps 步骤中的陷阱名称
+        let mut traps: Vec<&str> = self.loadout.iter().map(String::as_str).collect();
+        for step in &all_steps {
+            if let Step::BuyTraps { traps: Some(names) } = step {
+                traps.extend(names.iter().map(String::as_str));
+            }
+        }
+        let mut timeouts = vec![self.wait_timeout_secs];
+        for step in all_steps {
+            for key in step.keys() {
+                if get_vk_code(key).is_none() {
+                    bail!("未知的按键: '{}'", key);
+                }
+            }
+            if let Some(trap) = step.trap() {
+                if get_vk_code(trap).is_none() && !traps.contains(&trap) {
+                    bail!("陷阱 '{}' 不在装备中，也不是按键", trap);
+                }
+            }
+            if let Step::WaitGold { timeout_secs, .. }
+            | Step::WaitWave { timeout_secs, .. }
+            | Step::WaitText { timeout_secs, .. } = step
+            {
+                timeouts.push(*timeout_secs);
+            }
+            for secs in step.durations() {
+                if !(secs.is_finite() && secs >= 0.0) {
+                    bail!("秒数无效: {}", secs);
+                }
+            }
+        }
+        for secs in timeouts.into_iter().flatten() {
+            if !(secs.is_finite() && secs >= 0.0) {
+                bail!("超时秒数无效: {}", secs);
+            }
+        }
+        Ok(())
+    }
+
+    fn run_steps<'a>(&self, steps: impl IntoIterator<Item = &'a Step>) -> Result<()> {
+        for step in steps {
+            if should_stop() {
+                println!("[STOP] {}: 检测到停止信号", self.map);
+                return Ok(());
+            }
+            self.run_step(step)?;
+        }
+        Ok(())
+    }
+
+    fn run_step(&self, step: &Step) -> Result<()> {
+        match step {
+            Step::StartGame { difficulty } => {
+                start_game_with_difficulty(difficulty.as_deref().unwrap_or(&self.difficulty))?
+            }
+            Step::BuyTraps { traps } => {
+                let traps = traps.as_ref().unwrap_or(&self.loadout);
+                let names: Vec<&str> = traps.iter().map(String::as_str).collect();
+                buy_traps_ordered(&names)?;
+            }
+            Step::WaitGold {
+                amount,
+                timeout_secs,
+            } => wait_gold_with(*amount, wait_options(*timeout_secs))?,
+            Step::WaitWave { wave, timeout_secs } => {
+                wait_wave_with(*wave, wait_options(*timeout_secs))?
+            }
+            Step::Sleep { secs } => thread::sleep(Duration::from_secs_f64(*secs)),
+            Step::PlaceTrap { x, y, trap, coords } => {
+                place_trap(coords.to_screen(*x, *y), trap)?;
+            }
+            Step::PlaceTraps {
+                positions,
+                trap,
+                coords,
+            } => {
+                let positions: Vec<ScreenPoint> =
+                    positions.iter().map(|&(x, y)| coords.to_screen(x, y)).collect();
+                place_traps(&positions, trap)?
+            }
+            Step::UpgradeTrap { trap } => upgrade_trap(trap)?,
+            Step::TapKey { key } => {
+                execute_actions(&[GameAction::TapKey(vk(key)?)])?;
+            }
+            Step::PressKey { key, secs } => {
+                execute_actions(&[GameAction::PressKey(vk(key)?, *secs)])?;
+            }
+            Step::Click { x, y, coords } => {
+                let ScreenPoint { x, y } = coords.to_screen(*x, *y);
+                execute_actions(&[GameAction::ClickAt(x, y)])?;
+            }
+            Step::ClickText { text } => {
+                let screen = full_screen_region();
+                let results = ocr_screen(screen, false, IS_DEBUG)?;
+                let found = find_text_contains(&results, text)
+                    .with_context(|| format!("未找到文字 '{}'", text))?;
+                let (cx, cy) = found.center();
+                println!("[{}] 点击 '{}' @ ({},{})", self.map, text, cx, cy);
+                execute_actions(&[GameAction::ClickAt(cx, cy)])?;
+                thread::sleep(Duration::from_millis(500));
+            }
+            Step::WaitText { text, timeout_secs } => {
+                println!("[{}] 等待 '{}'...", self.map, text);
+                let screen = full_screen_region();
+                let deadline = Deadline::new(
+                    format!("文字 '{}'", text),
+                    timeout_secs.map(Duration::from_secs_f64),
+                );
+                while !should_stop() {
+                    deadline.check()?;
+                    let results = ocr_screen(screen, false, IS_DEBUG)?;
+                    if find_text_contains(&results, text).is_some() {
+                        break;
+                    }
+                    thread::sleep(Duration::from_secs(1));
+                }
+            }
+            Step::Actions { actions } => {
+                let actions = actions
+                    .iter()
+                    .map(Action::to_game_action)
+                    .collect::<Result<Vec<_>>>()?;
+                execute_actions(&actions)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Strategy for StrategyFile {
+    fn metadata(&self) -> Metadata {
+        Metadata::new(&self.map, &self.difficulty)
+    }
+
+    fn start(&mut self, _ctx: &mut Ctx) -> Result<()> {
+        println!("[{}:{}] 开始游戏...", self.map, self.difficulty);
+        if self.start.is_empty() {
+            return self.run_step(&Step::StartGame { difficulty: None });
+        }
+        self.run_steps(&self.start)
+    }
+
+    /// 重放 `wave` 之前各波次中标记 `idempotent` 的步骤
+    fn resume(&mut self, _ctx: &mut Ctx, wave: u32) -> Result<()> {
+        for plan in self.waves.iter().filter(|w| w.wave < wave) {
+            let (replay, skip): (Vec<_>, Vec<_>) = plan.steps.iter().partition(|s| s.idempotent);
+            println!(
+                "[{}:{}] 接管：波次 {} 重放 {} 步，跳过 {} 步",
+                self.map,
+                self.difficulty,
+                plan.wave,
+                replay.len(),
+                skip.len()
+            );
+            self.run_steps(replay.into_iter().map(|s| &s.step))?;
+        }
+        Ok(())
+    }
+
+    /// 补执行 `first` 之前各波次中标记 `setup` 的步骤
+    fn setup(&mut self, _ctx: &mut Ctx, first: u32) -> Result<()> {
+        let steps = self
+            .waves
+            .iter()
+            .filter(|w| w.wave < first)
+            .flat_map(|w| w.steps.iter())
+            .filter(|s| s.setup)
+            .map(|s| &s.step);
+        self.run_steps(steps)
+    }
+
+    fn waves(&self) -> Vec<u32> {
+        self.waves.iter().map(|w| w.wave).collect()
+    }
+
+    fn on_wave(&mut self, _ctx: &mut Ctx, wave: u32) -> Result<()> {
+        let Some(plan) = self.waves.iter().find(|w| w.wave == wave) else {
+            return Ok(());
+        };
+        println!("[{}:{}] === 波次 {} ===", self.map, self.difficulty, wave);
+        self.run_steps(plan.steps.iter().map(|s| &s.step))
+    }
+
+    fn triggers(&self) -> Vec<(String, Trigger)> {
+        self.triggers
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (i.to_string(), t.when.clone()))
+            .collect()
+    }
+
+    fn on_trigger(&mut self, _ctx: &mut Ctx, name: &str) -> Result<()> {
+        let Some(plan) = name.parse::<usize>().ok().and_then(|i| self.triggers.get(i)) else {
+            return Ok(());
+        };
+        println!("[{}:{}] === 触发 {} ===", self.map, self.difficulty, plan.when);
+        self.run_steps(&plan.steps)
+    }
+
+    fn late_policy(&self) -> LatePolicy {
+        self.late_policy
+    }
+
+    fn wait_timeout(&self) -> Option<Duration> {
+        self.wait_timeout_secs.map(Duration::from_secs_f64)
+    }
+
+    fn finish(&mut self, _ctx: &mut Ctx) -> Result<()> {
+        if self.wait_for_game_end {
+            wait_for_game_end()?;
+        }
+        Ok(())
+    }
+}
+
+impl Step {
+    /// 步骤中引用的按键名称（用于加载时校验）
+    fn keys(&self) -> Vec<&str> {
+        match self {
+            Step::TapKey { key }
+            | Step::PressKey { key, .. } => vec![key.as_str()],
+            Step::Actions { actions } => actions
+                .iter()
+                .filter_map(|a| match a {
+                    Action::PressKey { key, .. } | Action::TapKey { key } => Some(key.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// 步骤中的等待/按住秒数（用于加载时校验）
+    fn durations(&self) -> Vec<f64> {
+        match self {
+            Step::Sleep { secs } | Step::PressKey { secs, .. } => vec![*secs],
+            Step::Actions { actions } => actions
+                .iter()
+                .filter_map(|a| match a {
+                    Action::Sleep { secs } | Action::PressKey { secs, .. } => Some(*secs),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// 陷阱步骤的陷阱名称（或热键）
+    fn trap(&self) -> Option<&str> {
+        match self {
+            Step::PlaceTrap { trap, .. }
+            | Step::PlaceTraps { trap, .. }
+            | Step::UpgradeTrap { trap } => Some(trap),
+            _ => None,
+        }
+    }
+}
+
+impl Action {
+    pub(super) fn to_game_action(&self) -> Result<GameAction> {
+        Ok(match self {
+            Action::PressKey { key, secs } => GameAction::PressKey(vk(key)?, *secs),
+            Action::TapKey { key } => GameAction::TapKey(vk(key)?),
+            Action::Turn { dx, dy } => GameAction::SendRelative(*dx, *dy),
+            Action::Sleep { secs } => GameAction::Sleep(*secs),
+            Action::Click => GameAction::Click,
+            Action::MoveTo { x, y, coords } => {
+                let ScreenPoint { x, y } = coords.to_screen(*x, *y);
+                GameAction::MoveTo(x, y)
+            }
+        })
+    }
+}
+
+/// 步骤的超时（None 时由等待函数使用文件的默认超时）
+fn wait_options(timeout_secs: Option<f64>) -> WaitOptions {
+    WaitOptions {
+        timeout: timeout_secs.map(Duration::from_secs_f64),
+        ..WaitOptions::default()
+    }
+}
+
+fn vk(key: &str) -> Result<u16> {
+    get_vk_code(key).with_context(|| format!("未知的按键: {}", key))
+}
+
+/// 策略文件目录（exe 同目录的 strategies/）
+pub fn strategies_dir() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(|p| p.to_path_buf()))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("strategies")
+}
+
+/// 加载目录下所有 `.toml` / `.json` 策略文件（按文件名排序）
+///
+/// # Returns
+/// 每个文件的路径和加载结果，目录不存在时返回空列表
+pub fn load_dir(dir: &Path) -> Vec<(PathBuf, Result<StrategyFile>)> {
+    list_files(dir, &["toml", "json"])
+        .into_iter()
+        .map(|p| {
+            let result = StrategyFile::load(&p);
+            (p, result)
+        })
+        .collect()
+}
+
+/// 列出目录下指定扩展名的文件（按文件名排序），目录不存在时返回空列表
+pub(super) fn list_files(dir: &Path, extensions: &[&str]) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| extensions.contains(&e))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = r#"
+map = "训练基地"
+difficulty = "困难"
+loadout = ["防空导弹", "自修复磁暴塔"]
+
+[[waves]]
+wave = 1
+steps = [
+    { action = "buy_traps", setup = true, idempotent = true },
+    { action = "wait_gold", amount = 2500, timeout_secs = 300 },
+    { action = "place_trap", x = 800, y = 400, key = "5" },
+]
+
+[[waves]]
+wave = 3
+steps = [
+    { action = "actions", actions = [{ type = "turn", dx = 100, dy = 0 }, { type = "tap_key", key = "g" }] },
+    { action = "place_traps", positions = [[1600, 800], [1700, 800]], trap = "防空导弹", coords = "dev" },
+]
+
+[[triggers]]
+when = { type = "after_secs", wave = 3, secs = 20 }
+steps = [{ action = "upgrade_trap", trap = "自修复磁暴塔" }]
+"#;
+
+    #[test]
+    fn test_parse_toml() {
+        let strategy: StrategyFile = toml::from_str(EXAMPLE).unwrap();
+        strategy.validate().unwrap();
+
+        assert!(strategy.start.is_empty());
+        assert!(strategy.wait_for_game_end);
+        assert_eq!(strategy.late_policy, LatePolicy::CatchUp);
+        assert_eq!(
+            strategy.triggers[0].when,
+            Trigger::AfterSecs {
+                wave: 3,
+                secs: 20.0
+            }
+        );
+        assert_eq!(strategy.waves.len(), 2);
+        assert_eq!(strategy.waves[0].steps[0].step, Step::BuyTraps { traps: None });
+        assert!(strategy.waves[0].steps[0].setup);
+        assert!(strategy.waves[0].steps[0].idempotent);
+        assert!(!strategy.waves[0].steps[1].setup);
+        assert_eq!(
+            strategy.waves[0].steps[1].step,
+            Step::WaitGold {
+                amount: 2500,
+                timeout_secs: Some(300.0)
+            }
+        );
+        assert_eq!(strategy.wait_timeout(), None);
+        assert_eq!(
+            strategy.waves[0].steps[2].step,
+            Step::PlaceTrap {
+                x: 800,
+                y: 400,
+                trap: "5".into(),
+                coords: Coords::Base
+            }
+        );
+        assert_eq!(
+            strategy.waves[1].steps[1].step,
+            Step::PlaceTraps {
+                positions: vec![(1600, 800), (1700, 800)],
+                trap: "防空导弹".into(),
+                coords: Coords::Dev
+            }
+        );
+    }
+
+    #[test]
+    fn test_toml_and_json_agree() {
+        let strategy: StrategyFile = toml::from_str(EXAMPLE).unwrap();
+        let json = serde_json::to_string(&strategy).unwrap();
+        let back: StrategyFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(strategy, back);
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_input() {
+        let mut strategy: StrategyFile = toml::from_str(EXAMPLE).unwrap();
+        strategy.waves[1].steps.push(
+            Step::TapKey {
+                key: "不存在".into(),
+            }
+            .into(),
+        );
+        assert!(strategy.validate().is_err());
+
+        let mut strategy: StrategyFile = toml::from_str(EXAMPLE).unwrap();
+        strategy.waves[1].steps.push(
+            Step::UpgradeTrap {
+                trap: "天网".into(),
+            }
+            .into(),
+        );
+        assert!(strategy.validate().is_err());
+
+        let mut strategy: StrategyFile = toml::from_str(EXAMPLE).unwrap();
+        strategy.waves.swap(0, 1);
+        assert!(strategy.validate().is_err());
+
+        let mut strategy: StrategyFile = toml::from_str(EXAMPLE).unwrap();
+        strategy.wait_timeout_secs = Some(-1.0);
+        assert!(strategy.validate().is_err());
+
+        let mut strategy: StrategyFile = toml::from_str(EXAMPLE).unwrap();
+        strategy.waves[1].steps.push(Step::Sleep { secs: -1.0 }.into());
+        assert!(strategy.validate().is_err());
+
+        let mut strategy: StrategyFile = toml::from_str(EXAMPLE).unwrap();
+        strategy.waves[1].steps.push(
+            Step::Actions {
+                actions: vec![Action::PressKey {
+                    key: "g".into(),
+                    secs: f64::NAN,
+                }],
+            }
+            .into(),
+        );
+        assert!(strategy.validate().is_err());
+    }
+}
@@ -1,15 +1,57 @@
 //! 训练基地 - 困难难度
 //!
 //! 每个波次一个函数，可单独调试。
-//! 调试时注释掉不需要的波次即可。
+//! 调试时在 GUI 中设置波次范围（或单波模式）即可，勾选 "先执行设置步骤" 会先购买陷阱。
 
 use anyhow::Result;
+use std::time::Duration;
 
-use super::common::{
-    buy_traps, place_trap, start_game_with_difficulty, wait_for_game_end, wait_gold, wait_wave,
-};
+use super::common::{buy_traps, place_trap, start_game_with_difficulty, wait_gold};
+use super::strategy::{Ctx, Metadata, Strategy};
 use crate::stop_flag::should_stop;
 
+/// 等待函数的默认超时
+const WAIT_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// 训练基地 - 困难
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TrainingHard;
+
+impl Strategy for TrainingHard {
+    fn metadata(&self) -> Metadata {
+        Metadata::new("训练基地", "困难")
+    }
+
+    fn start(&mut self, _ctx: &mut Ctx) -> Result<()> {
+        start_game()
+    }
+
+    fn waves(&self) -> Vec<u32> {
+        vec![1, 2, 3]
+    }
+
+    fn setup(&mut self, _ctx: &mut Ctx, first: u32) -> Result<()> {
+        // 波次 1 的购买陷阱
+        if first > 1 {
+            buy_traps()?;
+        }
+        Ok(())
+    }
+
+    fn wait_timeout(&self) -> Option<Duration> {
+        Some(WAIT_TIMEOUT)
+    }
+
+    fn on_wave(&mut self, _ctx: &mut Ctx, wave: u32) -> Result<()> {
+        match wave {
+            1 => wave_1(),
+            2 => wave_2(),
+            3 => wave_3_boss(),
+            _ => Ok(()),
+        }
+    }
+}
+
 /// 开始游戏
 pub fn start_game() -> Result<()> {
     start_game_with_difficulty("困难")
@@ -26,7 +68,7 @@ pub fn wave_1() -> Result<()> {
 
     // TODO: 根据实际游戏调整坐标和金币阈值
     // wait_gold(2500)?;
-    // place_trap(800, 400, "5")?;
+    // place_trap(BasePoint::new(800, 400), "5")?;
 
     Ok(())
 }
@@ -40,7 +82,7 @@ pub fn wave_2() -> Result<()> {
 
     // TODO: 根据实际游戏调整
     // wait_gold(5000)?;
-    // place_trap(600, 300, "6")?;
+    // place_trap(BasePoint::new(600, 300), "6")?;
 
     Ok(())
 }
@@ -54,7 +96,7 @@ pub fn wave_3_boss() -> Result<()> {
 
     // Boss 波次可以写任意复杂逻辑
     // wait_gold(15000)?;
-    // place_trap(400, 300, "7")?;
+    // place_trap(BasePoint::new(400, 300), "7")?;
     // for _ in 0..3 {
     //     move_to(960, 540);
     //     left_click();
@@ -63,19 +105,3 @@ pub fn wave_3_boss() -> Result<()> {
 
     Ok(())
 }
-
-/// 执行所有波次
-///
-/// 调试时可以注释掉前面的波次，只跑某一段。
-pub fn run_all_waves() -> Result<()> {
-    wave_1()?;
-
-    wait_wave(2)?;
-    wave_2()?;
-
-    wait_wave(3)?;
-    wave_3_boss()?;
-
-    wait_for_game_end()?;
-    Ok(())
-}
@@ -0,0 +1,416 @@
+//! 陷阱升级队列
+//!
+//! `upgrade_trap` 只是长按热键 3 秒，不知道升级花多少钱、有没有升上去。
+//! `UpgradePlanner` 记录每个陷阱（快捷栏格子）当前的等级和各级升级价格，
+//! 把升级请求排成队列：按优先级依次等待金币足够再升级，用金币减少确认升级成功。
+//! 请求可以取消；`run_with` 与 `PlacementPlan` 按优先级交替执行。
+//!
+//! ```ignore
+//! let mut costs = UpgradeCosts::new();
+//! costs.set("天网", &[1000, 2000, 3000]); // 1→2 级 1000，2→3 级 2000……
+//! let mut upgrades = UpgradePlanner::new(costs);
+//! upgrades.queue("天网", 3, 5);            // 升到 3 级，优先级 5
+//! upgrades.run_with(&mut plan, Some(4))?;  // 与放置计划按优先级交替
+//! ```
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+
+use super::common::{
+    gold_settle, judge_spend, round_gold, upgrade_trap_pressed, wait_gold_drop, GoldSpend,
+};
+use super::loadout;
+use super::placement::{wait_gold_or_wave, PlacementPlan, RunResult};
+use crate::monitor;
+use crate::stop_flag::should_stop;
+use crate::timeline;
+
+/// 各陷阱的升级价格
+#[derive(Debug, Clone, Default)]
+pub struct UpgradeCosts {
+    /// 陷阱 → 第 i 项为从 i+1 级升到 i+2 级的价格
+    costs: HashMap<String, Vec<i64>>,
+}
+
+impl UpgradeCosts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置陷阱从 1 级开始每一级的升级价格
+    pub fn set(&mut self, trap: &str, costs: &[i64]) -> &mut Self {
+        self.costs.insert(trap.to_string(), costs.to_vec());
+        self
+    }
+
+    /// 从 `level` 级升到下一级的价格（已满级或没有设置时为 None）
+    pub fn cost(&self, trap: &str, level: u32) -> Option<i64> {
+        let index = level.checked_sub(1)? as usize;
+        self.costs_of(trap)?.get(index).copied()
+    }
+
+    /// 最高等级（没有设置时为 None）
+    pub fn max_level(&self, trap: &str) -> Option<u32> {
+        self.costs_of(trap).map(|costs| costs.len() as u32 + 1)
+    }
+
+    /// 陷阱的价格表：先按原样查找，再按热键匹配（名称设置的价格也能用热键查到，反之亦然）
+    fn costs_of(&self, trap: &str) -> Option<&Vec<i64>> {
+        self.costs.get(trap).or_else(|| {
+            let key = slot_key(trap);
+            self.costs
+                .iter()
+                .find(|(name, _)| slot_key(name) == key)
+                .map(|(_, costs)| costs)
+        })
+    }
+}
+
+/// 一次升级的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpgradeOutcome {
+    /// 升级成功，`level` 为升级后的等级，`cost` 为金币减少量
+    Upgraded { level: u32, cost: i64 },
+    /// 金币不足
+    NotEnoughGold { gold: i64, price: i64 },
+    /// 按下热键后金币没有减少（没有放置该陷阱、已满级等）
+    NoChange,
+    /// 收到停止信号或调试器跳过，没有升级
+    Skipped,
+}
+
+/// 升级前后的金币判断 `level` 级的升级结果（判断规则见 `judge_spend`）
+fn judge_upgrade(before: i64, after: Option<i64>, price: i64, level: u32) -> UpgradeOutcome {
+    match judge_spend(before, after, Some(price)) {
+        GoldSpend::Spent(cost) => UpgradeOutcome::Upgraded {
+            level: level + 1,
+            cost,
+        },
+        GoldSpend::NotEnoughGold { gold, price } => UpgradeOutcome::NotEnoughGold { gold, price },
+        GoldSpend::NoChange => UpgradeOutcome::NoChange,
+    }
+}
+
+/// 等级记录的键：陷阱名称换成热键，装备还未确定时用原名称
+fn slot_key(trap: &str) -> String {
+    loadout::resolve_key(trap).unwrap_or_else(|_| trap.to_string())
+}
+
+/// 队列中的升级请求
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpgradeRequest {
+    /// 取消时使用
+    pub id: u64,
+    /// 陷阱名称或热键（使用时按当前装备换成热键，名称和热键指同一格子的请求视为同一陷阱）
+    pub trap: String,
+    /// 目标等级
+    pub to_level: u32,
+    /// 优先级，越大越先升级；相同时按加入顺序
+    pub priority: i32,
+}
+
+/// 陷阱升级队列（记录各陷阱等级，可跨波次多次 `run`）
+#[derive(Debug, Clone)]
+pub struct UpgradePlanner {
+    costs: UpgradeCosts,
+    /// 陷阱热键 → 当前等级（没有记录时为 1 级），名称和热键指同一格子时共用记录
+    levels: HashMap<String, u32>,
+    queue: Vec<UpgradeRequest>,
+    next_id: u64,
+    /// 按下热键后等待金币读数变化的时间，None 时按金币监控的检测间隔和中值窗口计算
+    pub settle: Option<Duration>,
+}
+
+impl UpgradePlanner {
+    pub fn new(costs: UpgradeCosts) -> Self {
+        Self {
+            costs,
+            levels: HashMap::new(),
+            queue: Vec::new(),
+            next_id: 0,
+            settle: None,
+        }
+    }
+
+    /// 陷阱当前等级
+    pub fn level(&self, trap: &str) -> u32 {
+        self.levels.get(&slot_key(trap)).copied().unwrap_or(1)
+    }
+
+    /// 设置陷阱当前等级（中途接管时，陷阱在停止前已经升过级）
+    pub fn set_level(&mut self, trap: &str, level: u32) {
+        self.levels.insert(slot_key(trap), level);
+    }
+
+    /// 请求把陷阱升到 `to_level` 级
+    ///
+    /// # Returns
+    /// 请求 id（用于 `cancel`）
+    pub fn queue(&mut self, trap: &str, to_level: u32, priority: i32) -> u64 {
+        self.next_id += 1;
+        self.queue.push(UpgradeRequest {
+            id: self.next_id,
+            trap: trap.to_string(),
+            to_level,
+            priority,
+        });
+        self.next_id
+    }
+
+    /// 取消请求，返回是否找到
+    pub fn cancel(&mut self, id: u64) -> bool {
+        let len = self.queue.len();
+        self.queue.retain(|r| r.id != id);
+        self.queue.len() != len
+    }
+
+    /// 取消某个陷阱的所有请求，返回取消的数量
+    pub fn cancel_trap(&mut self, trap: &str) -> usize {
+        let key = slot_key(trap);
+        let len = self.queue.len();
+        self.queue.retain(|r| slot_key(&r.trap) != key);
+        len - self.queue.len()
+    }
+
+    /// 队列中的请求（按加入顺序）
+    pub fn pending(&self) -> &[UpgradeRequest] {
+        &self.queue
+    }
+
+    /// 下一个请求（优先级最高、最早加入、还没达到目标等级）
+    pub fn next(&self) -> Option<&UpgradeRequest> {
+        self.next_index().map(|i| &self.queue[i])
+    }
+
+    fn next_index(&self) -> Option<usize> {
+        self.queue
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| self.level(&r.trap) < r.to_level)
+            .min_by_key(|(i, r)| (std::cmp::Reverse(r.priority), *i))
+            .map(|(i, _)| i)
+    }
+
+    /// 队列中所有请求还需要的金币（同一陷阱的多个请求按最高目标等级计算）
+    pub fn remaining_cost(&self) -> i64 {
+        // 热键 → (价格表使用的名称, 最高目标等级)
+        let mut targets: HashMap<String, (&str, u32)> = HashMap::new();
+        for r in &self.queue {
+            let target = targets.entry(slot_key(&r.trap)).or_insert((&r.trap, 0));
+            target.1 = target.1.max(r.to_level);
+        }
+        targets
+            .into_values()
+            .flat_map(|(trap, to)| (self.level(trap)..to).map(move |level| (trap, level)))
+            .filter_map(|(trap, level)| self.costs.cost(trap, level))
+            .sum()
+    }
+
+    /// 升级一次并用金币变化确认，成功时等级 +1
+    ///
+    /// 需要金币监控正在运行，没有本回合的金币读数或没有该等级的价格时返回错误
+    pub fn upgrade_once(&mut self, trap: &str) -> Result<UpgradeOutcome> {
+        let level = self.level(trap);
+        let price = self
+            .costs
+            .cost(trap, level)
+            .context(format!("没有 {} 从 {} 级升级的价格", trap, level))?;
+        let round = monitor::round_generation();
+        let before = round_gold(round).context("没有本回合的金币读数，无法确认升级")?;
+        if before.value < price {
+            return Ok(UpgradeOutcome::NotEnoughGold {
+                gold: before.value,
+                price,
+            });
+        }
+
+        if !upgrade_trap_pressed(trap)? {
+            return Ok(UpgradeOutcome::Skipped);
+        }
+        let settle = gold_settle(self.settle);
+        let after = wait_gold_drop(round, before.value, Instant::now(), settle);
+
+        let outcome = judge_upgrade(before.value, after, price, level);
+        if let UpgradeOutcome::Upgraded { level, cost } = outcome {
+            println!("[upgrade] {} 升到 {} 级，花费 {}", trap, level, cost);
+            timeline::step("upgrade_ok", format!("{} lv{} cost={}", trap, level, cost));
+            self.set_level(trap, level);
+        }
+        Ok(outcome)
+    }
+
+    /// 按优先级依次等待金币足够并升级
+    ///
+    /// `until_wave` 的含义与 `PlacementPlan::run` 相同
+    pub fn run(&mut self, until_wave: Option<u32>) -> Result<RunResult> {
+        loop {
+            if let Some(result) = self.step(until_wave)? {
+                return Ok(result);
+            }
+        }
+    }
+
+    /// 等待金币并升级一次（`run` 的单步）
+    ///
+    /// # Returns
+    /// None 表示还可以继续；Some 为 `run` 应返回的结果
+    pub fn step(&mut self, until_wave: Option<u32>) -> Result<Option<RunResult>> {
+        let Some(i) = self.next_index() else {
+            let done = self.queue.len();
+            self.queue.clear();
+            if done > 0 {
+                timeline::step("upgrade_done", format!("{} 项", done));
+            }
+            return Ok(Some(RunResult::Done));
+        };
+        let request = self.queue[i].clone();
+        let Some(price) = self.costs.cost(&request.trap, self.level(&request.trap)) else {
+            println!(
+                "[upgrade] {} 没有 {} 级的升级价格，取消",
+                request.trap,
+                self.level(&request.trap)
+            );
+            self.queue.remove(i);
+            return Ok(None);
+        };
+
+        if !wait_gold_or_wave(price, until_wave)? {
+            return Ok(Some(if should_stop() {
+                RunResult::Stopped
+            } else {
+                RunResult::WaveReached
+            }));
+        }
+        match self.upgrade_once(&request.trap)? {
+            UpgradeOutcome::Upgraded { .. } => {}
+            UpgradeOutcome::NoChange => {
+                println!("[upgrade] {} 金币没有减少，取消升级", request.trap);
+                timeline::step("upgrade_failed", &request.trap);
+                self.queue.remove(i);
+            }
+            // 读数间金币被花掉（或读数偏高），重新等待
+            UpgradeOutcome::NotEnoughGold { .. } => {}
+            UpgradeOutcome::Skipped => return Ok(Some(RunResult::Stopped)),
+        }
+        Ok(None)
+    }
+
+    /// 与放置计划一起执行：每次处理优先级更高的一项（相同时先放置），直到两边都完成
+    pub fn run_with(
+        &mut self,
+        plan: &mut PlacementPlan,
+        until_wave: Option<u32>,
+    ) -> Result<RunResult> {
+        loop {
+            let place = plan.next().map(|e| e.priority);
+            let upgrade = self.next().map(|r| r.priority);
+            let result = match (place, upgrade) {
+                (None, None) => {
+                    // 记录两边的完成情况
+                    plan.run(until_wave)?;
+                    return self.run(until_wave);
+                }
+                (Some(p), Some(u)) if u > p => self.step(until_wave)?,
+                (Some(_), _) => plan.step(until_wave)?,
+                (None, Some(_)) => self.step(until_wave)?,
+            };
+            match result {
+                None | Some(RunResult::Done) => {}
+                Some(result) => return Ok(result),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn planner() -> UpgradePlanner {
+        let mut costs = UpgradeCosts::new();
+        costs.set("天网", &[1000, 2000]).set("天启", &[1500]);
+        UpgradePlanner::new(costs)
+    }
+
+    #[test]
+    fn test_costs_and_judge() {
+        let planner = planner();
+        assert_eq!(planner.costs.cost("天网", 1), Some(1000));
+        assert_eq!(planner.costs.cost("天网", 2), Some(2000));
+        assert_eq!(planner.costs.cost("天网", 3), None);
+        assert_eq!(planner.costs.cost("天网", 0), None);
+        assert_eq!(planner.costs.max_level("天启"), Some(2));
+
+        assert_eq!(
+            judge_upgrade(3000, Some(2000), 1000, 1),
+            UpgradeOutcome::Upgraded {
+                level: 2,
+                cost: 1000
+            }
+        );
+        // 期间有收入，减少量不到价格也算
+        assert_eq!(
+            judge_upgrade(3000, Some(2400), 1000, 1),
+            UpgradeOutcome::Upgraded {
+                level: 2,
+                cost: 600
+            }
+        );
+        assert_eq!(judge_upgrade(3000, None, 1000, 1), UpgradeOutcome::NoChange);
+        assert_eq!(
+            judge_upgrade(800, Some(820), 1000, 1),
+            UpgradeOutcome::NotEnoughGold {
+                gold: 800,
+                price: 1000
+            }
+        );
+    }
+
+    #[test]
+    fn test_queue_order_and_cancel() {
+        // 等级按热键记录，与设置装备的测试互斥
+        let _lock = loadout::TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut planner = planner();
+        let a = planner.queue("天网", 3, 0);
+        let b = planner.queue("天启", 2, 5);
+        planner.queue("天网", 2, 0);
+
+        assert_eq!(planner.next().unwrap().id, b);
+        assert_eq!(planner.remaining_cost(), 1000 + 2000 + 1500);
+
+        planner.set_level("天启", 2);
+        assert_eq!(planner.next().unwrap().id, a);
+        assert_eq!(planner.remaining_cost(), 3000);
+
+        assert!(planner.cancel(a));
+        assert!(!planner.cancel(a));
+        assert_eq!(planner.next().unwrap().to_level, 2);
+        assert_eq!(planner.cancel_trap("天网"), 1);
+        assert!(planner.next().is_none());
+    }
+
+    #[test]
+    fn test_key_and_name_share_costs_and_levels() {
+        let _lock = loadout::TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        loadout::set_current(Some(
+            loadout::Loadout::assume_bought(&["天网", "天启"]).unwrap(),
+        ));
+
+        let mut planner = planner();
+        // 价格按名称设置，请求用热键
+        assert_eq!(planner.costs.cost("4", 1), Some(1000));
+        assert_eq!(planner.costs.max_level("5"), Some(2));
+        let id = planner.queue("4", 3, 0);
+        assert_eq!(planner.remaining_cost(), 1000 + 2000);
+
+        planner.set_level("天网", 2);
+        assert_eq!(planner.level("4"), 2);
+        assert_eq!(planner.remaining_cost(), 2000);
+        assert_eq!(planner.next().map(|r| r.id), Some(id));
+        assert_eq!(planner.cancel_trap("天网"), 1);
+
+        loadout::set_current(None);
+    }
+}
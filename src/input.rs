@@ -4,6 +4,9 @@
 //! - SendInput: Windows 原生 API（默认）
 //! - Logitech: 罗技驱动层输入（需要 LGS v9.02.65）
 //!
+//! 后台弹窗处理器运行时会暂停其它线程的输入（见 `pause` 模块），
+//! 鼠标和按下键的函数在此期间阻塞；抬起键不受影响。
+//!
 //! # 使用方法
 //!
 //! ```rust
@@ -24,6 +27,7 @@ use std::time::Duration;
 
 use crate::keys;
 use crate::logitech;
+use crate::pause;
 
 // ===== 后端类型 =====
 
@@ -86,6 +90,7 @@ pub fn destroy() {
 
 /// 相对移动鼠标
 pub fn send_relative(dx: i32, dy: i32) {
+    pause::wait_if_paused();
     match current_backend() {
         InputBackend::SendInput => keys::send_relative(dx, dy),
         InputBackend::Logitech => {
@@ -96,6 +101,7 @@ pub fn send_relative(dx: i32, dy: i32) {
 
 /// 移动鼠标到绝对坐标
 pub fn move_to(x: i32, y: i32) {
+    pause::wait_if_paused();
     match current_backend() {
         InputBackend::SendInput => keys::move_to(x, y),
         InputBackend::Logitech => {
@@ -106,6 +112,7 @@ pub fn move_to(x: i32, y: i32) {
 
 /// 鼠标左键点击
 pub fn left_click() {
+    pause::wait_if_paused();
     match current_backend() {
         InputBackend::SendInput => keys::left_click_legacy(),
         InputBackend::Logitech => {
@@ -117,6 +124,8 @@ pub fn left_click() {
 /// 移动并点击
 /// 增加足够的延迟让游戏引擎注册新位置
 pub fn click_at(x: i32, y: i32) {
+    // 移动和点击之间不允许弹窗处理器插入
+    let _action = pause::action();
     move_to(x, y);
     // 等待游戏引擎更新鼠标位置（UE4 通常需要 1-2 帧）
     thread::sleep(Duration::from_millis(100));
@@ -125,6 +134,7 @@ pub fn click_at(x: i32, y: i32) {
 
 /// 鼠标右键点击
 pub fn right_click() {
+    pause::wait_if_paused();
     match current_backend() {
         InputBackend::SendInput => {
             // keys.rs 没有 right_click，使用 mouse_event
@@ -146,6 +156,7 @@ pub use keys::ScrollDirection;
 
 /// 鼠标滚轮滚动
 pub fn mouse_scroll(direction: ScrollDirection, count: u32, interval_secs: f64) {
+    pause::wait_if_paused();
     match current_backend() {
         InputBackend::SendInput => keys::mouse_scroll(direction, count, interval_secs),
         InputBackend::Logitech => {
@@ -193,6 +204,7 @@ pub fn move_down(value: i32) {
 
 /// 按下键
 pub fn key_down(vk: u16) {
+    pause::wait_if_paused();
     match current_backend() {
         InputBackend::SendInput => keys::key_down(vk),
         InputBackend::Logitech => {
@@ -8,5 +8,7 @@ pub mod keys;
 pub mod logitech;
 pub mod monitor;
 pub mod ocr;
+pub mod pause;
 pub mod screen;
 pub mod stop_flag;
+pub mod timeline;
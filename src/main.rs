@@ -4,7 +4,8 @@
 //! GUI 主程序，包含：
 //! - 地图/难度选择
 //! - 启动/停止控制
-//! - 实时波次/金币显示
+//! - 实时波次/金币显示、监控项运行状态
+//! - 策略步骤调试（断点、单步、点击位置预览）
 //! - 日志面板
 //! - OCR 区域配置（持久化到 settings.ini）
 
@@ -14,8 +15,10 @@ mod keys;
 mod logitech;
 mod monitor;
 mod ocr;
+mod pause;
 mod screen;
 mod stop_flag;
+mod timeline;
 
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
@@ -24,12 +27,18 @@ use std::thread;
 
 use eframe::egui;
 
-use crate::game::available_maps;
-use crate::game::common::buy_traps;
+use crate::game::strategy::{resolve_params, Ctx, ParamValue, RoundOutcome, WaveRange};
+use crate::game::{available_strategies, reload_strategy_files, StrategyEntry};
+use crate::game::common::{
+    buy_traps, detect_in_round, set_default_timeout, wait_for_game_end, WaitTimeout,
+};
+use crate::game::debugger::{self, Breakpoint};
+use crate::game::interrupt::{self, InterruptHandler};
+use crate::game::loadout;
 use crate::input::click_at;
-use crate::monitor::MonitorConfig;
+use crate::monitor::{FilterConfig, MonitorConfig, Plausibility, WatcherConfig};
 use crate::ocr::{ocr_screen, OcrResultItem};
-use crate::screen::{get_scale_factors, get_screen_resolution};
+use crate::screen::{get_scale_factors, get_screen_resolution, Region};
 use crate::stop_flag::{request_stop, reset_stop, should_stop};
 
 /// 热键事件信号：0=无, 1=F1(启动), 2=F2(停止)
@@ -40,13 +49,37 @@ static GAME_RUNNING: AtomicBool = AtomicBool::new(false);
 
 // ===== Settings INI =====
 
-/// 获取 settings.ini 路径（exe 同目录）
-fn settings_path() -> std::path::PathBuf {
+/// exe 所在目录
+fn exe_dir() -> std::path::PathBuf {
     std::env::current_exe()
         .ok()
         .and_then(|p| p.parent().map(|p| p.to_path_buf()))
         .unwrap_or_else(|| std::path::PathBuf::from("."))
-        .join("settings.ini")
+}
+
+/// 获取 settings.ini 路径（exe 同目录）
+fn settings_path() -> std::path::PathBuf {
+    exe_dir().join("settings.ini")
+}
+
+/// 从 watchers.json（exe 同目录）读取额外监控项，文件不存在时返回空列表
+fn load_watchers() -> Result<Vec<WatcherConfig>, String> {
+    let path = exe_dir().join("watchers.json");
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return Ok(Vec::new());
+    };
+    serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// 读取 exe 同目录的 interrupts.json（弹窗处理器），与内置处理器合并
+fn load_interrupts() -> Result<Vec<InterruptHandler>, String> {
+    let path = exe_dir().join("interrupts.json");
+    let custom = match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map_err(|e| format!("{}: {}", path.display(), e))?,
+        Err(_) => Vec::new(),
+    };
+    interrupt::merge_handlers(custom).map_err(|e| format!("{}: {:#}", path.display(), e))
 }
 
 /// 从 settings.ini 读取所有 key=value
@@ -77,11 +110,24 @@ fn save_settings(map: &HashMap<String, String>) {
         "selected_map",
         "wave_region",
         "gold_region",
+        "countdown_region",
         "wave_interval",
         "gold_interval",
         "gold_use_color_filter",
         "gold_color_hex",
         "gold_color_tolerance",
+        "wave_consensus_n",
+        "wave_consensus_m",
+        "gold_median_window",
+        "gold_max_income",
+        "gold_max_spend",
+        "monitor_stale_secs",
+        "timeline_csv",
+        "wave_first",
+        "wave_last",
+        "single_wave",
+        "run_setup",
+        "debug_breakpoints",
         "ocr_region",
     ];
 
@@ -160,9 +206,9 @@ fn percent_to_pixel(s: &str) -> String {
     )
 }
 
-/// 解析坐标字符串 "x1,y1,x2,y2" → (x, y, w, h)
+/// 解析坐标字符串 "x1,y1,x2,y2" → Region
 /// 也兼容旧格式 "x,y,w,h"（当宽高合理时）
-fn parse_region_coords(s: &str) -> Option<(i32, i32, i32, i32)> {
+fn parse_region_coords(s: &str) -> Option<Region> {
     let parts: Vec<&str> = s.split(',').collect();
     if parts.len() < 4 {
         return None;
@@ -178,9 +224,9 @@ fn parse_region_coords(s: &str) -> Option<(i32, i32, i32, i32)> {
 
     // 判断是 x1,y1,x2,y2（两点）还是 x,y,w,h（旧格式）
     if x2 > x1 && y2 > y1 {
-        Some((x1, y1, x2 - x1, y2 - y1))
+        Some(Region::new(x1, y1, x2 - x1, y2 - y1))
     } else {
-        Some((x1, y1, x2, y2))
+        Some(Region::new(x1, y1, x2, y2))
     }
 }
 
@@ -243,6 +289,8 @@ struct MainApp {
     // 监控区域配置（x1,y1,x2,y2 格式）
     wave_region: String,
     gold_region: String,
+    /// 波次倒计时区域，留空则不监控
+    countdown_region: String,
     wave_interval: u64,
     gold_interval: u64,
 
@@ -251,6 +299,34 @@ struct MainApp {
     gold_color_hex: String,
     gold_color_tolerance: f64,
 
+    // 读数过滤
+    wave_consensus_n: usize,
+    wave_consensus_m: usize,
+    gold_median_window: usize,
+    gold_max_income: i64,
+    gold_max_spend: i64,
+
+    // 监控状态：超过多少秒没有成功读数显示警告色
+    monitor_stale_secs: u64,
+
+    // 时间线：每轮结束时是否额外写入按波次汇总的 CSV
+    timeline_csv: bool,
+
+    // 波次范围（调试用）：从 wave_first 到 wave_last（0 = 不限），single_wave 时只跑 wave_first
+    wave_first: u32,
+    wave_last: u32,
+    single_wave: bool,
+    // 从中间波次开始时先执行设置步骤（如购买陷阱）
+    run_setup: bool,
+
+    // 步骤调试：断点列表（逗号分隔，数字为波次，其余为步骤类型）
+    debug_breakpoints: String,
+    // 暂停截图（暂停 id, 纹理）
+    debug_preview: Option<(u64, egui::TextureHandle)>,
+
+    // 策略参数（键为 param.<策略>.<参数>，值为字符串形式）
+    strategy_params: HashMap<String, String>,
+
     // OCR 识别工具
     ocr_region: String,
     ocr_results: Vec<OcrResultItem>,
@@ -282,6 +358,10 @@ impl MainApp {
                 .get("gold_region")
                 .map(|v| percent_to_pixel(v))
                 .unwrap_or_else(|| "96,112,336,156".to_string()),
+            countdown_region: s
+                .get("countdown_region")
+                .map(|v| percent_to_pixel(v))
+                .unwrap_or_default(),
             wave_interval: s
                 .get("wave_interval")
                 .and_then(|v| v.parse().ok())
@@ -304,6 +384,59 @@ impl MainApp {
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(35.0),
 
+            wave_consensus_n: s
+                .get("wave_consensus_n")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2),
+            wave_consensus_m: s
+                .get("wave_consensus_m")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3),
+            gold_median_window: s
+                .get("gold_median_window")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3),
+            gold_max_income: s
+                .get("gold_max_income")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5000),
+            gold_max_spend: s
+                .get("gold_max_spend")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(100000),
+
+            monitor_stale_secs: s
+                .get("monitor_stale_secs")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5),
+            timeline_csv: s
+                .get("timeline_csv")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            wave_first: s
+                .get("wave_first")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1),
+            wave_last: s
+                .get("wave_last")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            single_wave: s
+                .get("single_wave")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            run_setup: s
+                .get("run_setup")
+                .map(|v| v == "true")
+                .unwrap_or(true),
+            debug_breakpoints: s.get("debug_breakpoints").cloned().unwrap_or_default(),
+            debug_preview: None,
+            strategy_params: s
+                .iter()
+                .filter(|(k, _)| k.starts_with("param."))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+
             ocr_region: s
                 .get("ocr_region")
                 .map(|v| percent_to_pixel(v))
@@ -325,6 +458,10 @@ impl MainApp {
         // 坐标以百分比存储，跨分辨率可移植
         map.insert("wave_region".to_string(), pixel_to_percent(&self.wave_region));
         map.insert("gold_region".to_string(), pixel_to_percent(&self.gold_region));
+        map.insert(
+            "countdown_region".to_string(),
+            pixel_to_percent(&self.countdown_region),
+        );
         map.insert("wave_interval".to_string(), self.wave_interval.to_string());
         map.insert("gold_interval".to_string(), self.gold_interval.to_string());
         map.insert(
@@ -336,11 +473,70 @@ impl MainApp {
             "gold_color_tolerance".to_string(),
             self.gold_color_tolerance.to_string(),
         );
+        map.insert("wave_consensus_n".to_string(), self.wave_consensus_n.to_string());
+        map.insert("wave_consensus_m".to_string(), self.wave_consensus_m.to_string());
+        map.insert(
+            "gold_median_window".to_string(),
+            self.gold_median_window.to_string(),
+        );
+        map.insert("gold_max_income".to_string(), self.gold_max_income.to_string());
+        map.insert("gold_max_spend".to_string(), self.gold_max_spend.to_string());
+        map.insert(
+            "monitor_stale_secs".to_string(),
+            self.monitor_stale_secs.to_string(),
+        );
+        map.insert("timeline_csv".to_string(), self.timeline_csv.to_string());
+        map.insert("wave_first".to_string(), self.wave_first.to_string());
+        map.insert("wave_last".to_string(), self.wave_last.to_string());
+        map.insert("single_wave".to_string(), self.single_wave.to_string());
+        map.insert("run_setup".to_string(), self.run_setup.to_string());
+        map.insert("debug_breakpoints".to_string(), self.debug_breakpoints.clone());
         map.insert("ocr_region".to_string(), pixel_to_percent(&self.ocr_region));
+        map.extend(self.strategy_params.clone());
         save_settings(&map);
         self.settings_dirty = false;
     }
 
+    /// 监控项运行状态表格，超过 `stale_secs` 没有成功读数的行显示为警告色
+    fn monitor_stats_grid(ui: &mut egui::Ui, stale_secs: u64) {
+        let stats = monitor::stats();
+        if stats.is_empty() {
+            ui.label("监控未启动");
+            return;
+        }
+
+        let threshold = std::time::Duration::from_secs(stale_secs);
+        egui::Grid::new("monitor_stats")
+            .striped(true)
+            .spacing([12.0, 2.0])
+            .show(ui, |ui| {
+                for header in ["监控项", "上次成功", "读取/秒", "平均耗时", "失败率", "原始文字"] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+
+                for st in &stats {
+                    let color = if st.is_stale(threshold) {
+                        egui::Color32::from_rgb(230, 160, 60)
+                    } else {
+                        ui.visuals().text_color()
+                    };
+                    let since = match st.since_success() {
+                        Some(d) => format!("{:.1}s 前", d.as_secs_f32()),
+                        None => "从未".to_string(),
+                    };
+
+                    ui.colored_label(color, &st.name);
+                    ui.colored_label(color, since);
+                    ui.label(format!("{:.1}", st.reads_per_sec));
+                    ui.label(format!("{}ms", st.avg_latency.as_millis()));
+                    ui.label(format!("{:.0}%", st.failure_rate * 100.0));
+                    ui.label(&st.last_raw);
+                    ui.end_row();
+                }
+            });
+    }
+
     /// 解析 hex 颜色 "d9e1e3" → (0xd9, 0xe1, 0xe3)
     fn parse_hex_color(s: &str) -> Option<(u8, u8, u8)> {
         let s = s.trim().trim_start_matches('#');
@@ -356,12 +552,33 @@ impl MainApp {
     /// 获取当前监控配置（GUI 输入的坐标即实际屏幕坐标，直接使用）
     fn get_monitor_config(&self) -> MonitorConfig {
         let wave_region =
-            parse_region_coords(&self.wave_region).unwrap_or((3686, 1476, 300, 102));
+            parse_region_coords(&self.wave_region).unwrap_or(Region::new(3686, 1476, 300, 102));
         let gold_region =
-            parse_region_coords(&self.gold_region).unwrap_or((96, 112, 240, 44));
+            parse_region_coords(&self.gold_region).unwrap_or(Region::new(96, 112, 240, 44));
+        let countdown_region = parse_region_coords(&self.countdown_region);
         let gold_text_color =
             Self::parse_hex_color(&self.gold_color_hex).unwrap_or((0xd9, 0xe1, 0xe3));
 
+        let wave_filter = FilterConfig {
+            consensus_n: self.wave_consensus_n,
+            consensus_m: self.wave_consensus_m.max(self.wave_consensus_n),
+            ..FilterConfig::wave()
+        };
+        let gold_filter = FilterConfig {
+            median_window: self.gold_median_window,
+            plausibility: Plausibility::GoldDelta {
+                max_income_per_sec: self.gold_max_income,
+                max_burst: self.gold_max_income * 4,
+                max_spend: self.gold_max_spend,
+            },
+            ..FilterConfig::gold()
+        };
+
+        let watchers = load_watchers().unwrap_or_else(|e| {
+            self.log_msg(&format!("监控项配置读取失败: {}", e));
+            Vec::new()
+        });
+
         MonitorConfig {
             wave_region,
             gold_region,
@@ -370,6 +587,11 @@ impl MainApp {
             gold_text_color,
             gold_color_tolerance: self.gold_color_tolerance,
             gold_use_color_filter: self.gold_use_color_filter,
+            wave_filter,
+            gold_filter,
+            countdown_region,
+            countdown_interval_ms: self.wave_interval,
+            watchers,
         }
     }
 
@@ -393,9 +615,25 @@ impl MainApp {
             }
         }
 
+        self.reload_strategies();
+        debugger::set_breakpoints(Breakpoint::parse_list(&self.debug_breakpoints));
+
         self.initialized = true;
     }
 
+    /// 重新加载 strategies/ 目录下的策略文件和脚本
+    fn reload_strategies(&self) {
+        let errors = reload_strategy_files();
+        let count = available_strategies()
+            .iter()
+            .filter(|e| e.source.is_some())
+            .count();
+        self.log_msg(&format!("已加载 {} 个策略文件", count));
+        for e in errors {
+            self.log_msg(&format!("策略文件加载失败: {}", e));
+        }
+    }
+
     fn log_msg(&self, msg: &str) {
         if let Ok(mut log) = self.log.lock() {
             let now = chrono_now();
@@ -409,17 +647,24 @@ impl MainApp {
             return;
         }
 
-        let maps = available_maps();
-        if self.selected_map >= maps.len() {
+        let entries = available_strategies();
+        let Some(entry) = entries.get(self.selected_map) else {
             self.log_msg("未选择有效地图");
             return;
-        }
+        };
 
-        let map = &maps[self.selected_map];
-        let start_fn = map.start_fn;
-        let waves_fn = map.waves_fn;
-        let map_name = map.name;
+        let mut strategy = entry.create();
+        let params = resolve_params(&entry.parameters, &self.saved_params(entry));
+        let map_name = entry.metadata.name.clone();
         let log = self.log.clone();
+        let timeline_csv = self.timeline_csv;
+        let range = self.wave_range();
+        let run_setup = self.run_setup;
+
+        let interrupts = load_interrupts().unwrap_or_else(|e| {
+            self.log_msg(&format!("弹窗处理配置读取失败: {}，使用内置处理器", e));
+            interrupt::builtin_handlers()
+        });
 
         let config = self.get_monitor_config();
         reset_stop();
@@ -430,14 +675,61 @@ impl MainApp {
 
         thread::spawn(move || {
             log_to(&log, &format!("开始游戏: {}", map_name));
+            if range.is_partial() {
+                log_to(&log, &format!("调试: 只执行 {}，运行一轮", range));
+            }
 
+            let mut ctx = Ctx::new(params);
+            ctx.range = range;
             let mut round = 0;
             const MAX_ROUNDS: i32 = 100;
+            // 连续这么多轮等待超时则停止运行（游戏可能已卡死或掉线）
+            const MAX_TIMEOUTS: u32 = 3;
+            // 检测是否已在对局中时，等待监控读到波次的时间
+            const IN_ROUND_PROBE: std::time::Duration = std::time::Duration::from_secs(5);
+            let mut timeouts = 0;
+            set_default_timeout(strategy.wait_timeout());
 
             while round < MAX_ROUNDS && !should_stop() {
                 log_to(&log, &format!("=== 第 {} 轮 ===", round + 1));
-
-                if let Err(e) = start_fn() {
+                ctx.round = round as u32 + 1;
+                ctx.wave = 0;
+                if let Err(e) = timeline::begin_round(&map_name, ctx.round, timeline_csv) {
+                    log_to(&log, &format!("时间线创建失败: {}", e));
+                }
+                // 第一轮可能是中途接管，不能要求波次从 1 开始，否则检测时读不到当前波次
+                if round == 0 {
+                    monitor::begin_round_resumable();
+                } else {
+                    monitor::begin_round();
+                }
+                // 陷阱每回合重新购买，热键对应关系随之重新确定
+                loadout::set_current(None);
+                interrupt::start(interrupts.clone());
+
+                // 第一轮先检测是否已在对局中（中途停止后重新开始），是则从当前波次接管
+                ctx.resumed_at = None;
+                if round == 0 {
+                    match detect_in_round(IN_ROUND_PROBE) {
+                        Ok(wave) => ctx.resumed_at = wave,
+                        Err(e) => log_to(&log, &format!("对局检测失败: {}", e)),
+                    }
+                }
+                let start_result = match ctx.resumed_at {
+                    Some(wave) => {
+                        log_to(&log, &format!("已在第 {} 波，从当前波次继续", wave));
+                        strategy.resume(&mut ctx, wave)
+                    }
+                    None => strategy.start(&mut ctx).and_then(|()| {
+                        let first = ctx.range.first;
+                        if !run_setup || first <= 1 || should_stop() {
+                            return Ok(());
+                        }
+                        log_to(&log, &format!("执行第 {} 波之前的设置步骤", first));
+                        strategy.setup(&mut ctx, first)
+                    }),
+                };
+                if let Err(e) = &start_result {
                     log_to(&log, &format!("开始游戏失败: {}", e));
                     if should_stop() {
                         break;
@@ -448,27 +740,254 @@ impl MainApp {
                     break;
                 }
 
-                if let Err(e) = waves_fn() {
-                    log_to(&log, &format!("波次执行失败: {}", e));
-                    if should_stop() {
-                        break;
+                // 开始阶段超时时不执行波次，直接按超时恢复
+                let waves_result = match start_result {
+                    Err(e) if e.is::<WaitTimeout>() => Err(e),
+                    _ => strategy.run_waves(&mut ctx),
+                };
+
+                let mut give_up = false;
+                match waves_result.as_ref().map_err(|e| e.downcast_ref::<WaitTimeout>()) {
+                    Err(Some(timeout)) => {
+                        timeouts += 1;
+                        timeline::step("timeout", timeout.to_string());
+                        if timeouts >= MAX_TIMEOUTS {
+                            log_to(
+                                &log,
+                                &format!("{}，连续 {} 轮超时，停止运行", timeout, timeouts),
+                            );
+                            give_up = true;
+                        } else {
+                            log_to(&log, &format!("{}，等待游戏结束后进入下一轮", timeout));
+                            if let Err(e) = wait_for_game_end() {
+                                log_to(&log, &format!("超时恢复失败: {}", e));
+                            }
+                        }
                     }
+                    _ => timeouts = 0,
                 }
 
-                if should_stop() {
+                monitor::end_round();
+                interrupt::stop();
+                timeline::end_round();
+
+                let outcome = match &waves_result {
+                    _ if should_stop() => RoundOutcome::Stopped,
+                    Ok(()) => RoundOutcome::Completed,
+                    Err(e) => RoundOutcome::Failed(e.to_string()),
+                };
+                if let Err(e) = waves_result {
+                    log_to(&log, &format!("波次执行失败: {}", e));
+                }
+                if let Err(e) = strategy.on_round_end(&mut ctx, &outcome) {
+                    log_to(&log, &format!("轮次收尾失败: {}", e));
+                }
+
+                if should_stop() || give_up {
                     break;
                 }
 
                 round += 1;
                 log_to(&log, &format!("第 {} 轮完成", round));
+                if range.is_partial() {
+                    break;
+                }
             }
 
+            interrupt::stop();
+            timeline::end_round();
             monitor::stop_monitors();
+            set_default_timeout(None);
             GAME_RUNNING.store(false, Ordering::SeqCst);
             log_to(&log, &format!("游戏结束，共完成 {} 轮", round));
         });
     }
 
+    /// GUI 中设置的波次范围
+    fn wave_range(&self) -> WaveRange {
+        let first = self.wave_first.max(1);
+        if self.single_wave {
+            return WaveRange::single(first);
+        }
+        WaveRange {
+            first,
+            last: (self.wave_last > 0).then(|| self.wave_last.max(first)),
+        }
+    }
+
+    /// 波次范围设置（调试用，对所有策略生效）
+    fn wave_range_ui(&mut self, ui: &mut egui::Ui) {
+        let old = (self.wave_first, self.wave_last, self.single_wave, self.run_setup);
+        ui.horizontal(|ui| {
+            ui.label("波次:");
+            ui.checkbox(&mut self.single_wave, "单波");
+            if self.single_wave {
+                ui.add(egui::DragValue::new(&mut self.wave_first).range(1..=99));
+            } else {
+                ui.label("从");
+                ui.add(egui::DragValue::new(&mut self.wave_first).range(1..=99));
+                ui.label("到");
+                ui.add(egui::DragValue::new(&mut self.wave_last).range(0..=99))
+                    .on_hover_text("0 = 不限（处理完后照常等待游戏结束）");
+            }
+            ui.checkbox(&mut self.run_setup, "先执行设置步骤")
+                .on_hover_text("从中间波次开始时，先补执行之前波次的设置步骤（如购买陷阱）");
+            if self.wave_range().is_partial() {
+                ui.colored_label(egui::Color32::YELLOW, "调试：只运行一轮");
+            }
+        });
+        if (self.wave_first, self.wave_last, self.single_wave, self.run_setup) != old {
+            self.settings_dirty = true;
+        }
+    }
+
+    /// 步骤调试：开关、断点、当前步骤、暂停时的截图预览和单步/继续/跳过
+    fn debugger_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let mut enabled = debugger::is_enabled();
+            if ui.checkbox(&mut enabled, "启用").changed() {
+                debugger::set_enabled(enabled);
+            }
+            let mut stepping = debugger::is_stepping();
+            if ui
+                .add_enabled(enabled, egui::Checkbox::new(&mut stepping, "单步"))
+                .on_hover_text("每个步骤前都暂停")
+                .changed()
+            {
+                debugger::set_stepping(stepping);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("断点:");
+            let resp = ui.add(
+                egui::TextEdit::singleline(&mut self.debug_breakpoints)
+                    .hint_text("3, place_trap")
+                    .desired_width(200.0),
+            );
+            resp.on_hover_text(
+                "逗号分隔：数字为波次开始，其余为步骤类型\n\
+                 （buy_traps, place_trap, upgrade_trap, press_key, tap_key, \
+                 send_relative, sleep, click, move_to）",
+            );
+            if ui.small_button("应用").clicked() {
+                debugger::set_breakpoints(Breakpoint::parse_list(&self.debug_breakpoints));
+                self.settings_dirty = true;
+            }
+        });
+
+        let Some(paused) = debugger::paused() else {
+            self.debug_preview = None;
+            match debugger::current() {
+                Some(current) => ui.label(format!("当前步骤: {}", current)),
+                None => ui.label("当前步骤: -"),
+            };
+            return;
+        };
+
+        let text = format!("暂停于: {}", paused.checkpoint);
+        ui.colored_label(egui::Color32::YELLOW, text);
+        ui.horizontal(|ui| {
+            if ui.button("单步").clicked() {
+                debugger::send(debugger::Command::Step);
+            }
+            if ui.button("继续").clicked() {
+                debugger::send(debugger::Command::Continue);
+            }
+            if ui.button("跳过").on_hover_text("不执行这一步").clicked() {
+                debugger::send(debugger::Command::Skip);
+            }
+        });
+
+        let Some(screenshot) = &paused.screenshot else {
+            ui.label("（截图失败）");
+            return;
+        };
+        if self.debug_preview.as_ref().map(|(id, _)| *id) != Some(paused.id) {
+            let size = [screenshot.width() as usize, screenshot.height() as usize];
+            let pixels = screenshot
+                .pixels()
+                .map(|p| egui::Color32::from_rgb(p[0], p[1], p[2]))
+                .collect();
+            let color_image = egui::ColorImage { size, pixels };
+            let texture = ui.ctx().load_texture(
+                "debug_preview",
+                color_image,
+                egui::TextureOptions::default(),
+            );
+            self.debug_preview = Some((paused.id, texture));
+        }
+        let Some((_, texture)) = &self.debug_preview else {
+            return;
+        };
+
+        let max_width = ui.available_width().min(640.0);
+        let scale = max_width / screenshot.width() as f32;
+        let display_size = egui::vec2(max_width, screenshot.height() as f32 * scale);
+        let rect = ui.image((texture.id(), display_size)).rect;
+        // 标出将要点击的位置
+        if let Some((x, y)) = paused.checkpoint.target {
+            let center = rect.min + egui::vec2(x as f32 * scale, y as f32 * scale);
+            let stroke = egui::Stroke::new(2.0, egui::Color32::RED);
+            let (h, v) = (egui::vec2(14.0, 0.0), egui::vec2(0.0, 14.0));
+            let painter = ui.painter();
+            painter.circle_stroke(center, 8.0, stroke);
+            painter.line_segment([center - h, center + h], stroke);
+            painter.line_segment([center - v, center + v], stroke);
+        }
+    }
+
+    /// 当前策略的参数编辑（策略没有参数时不显示）
+    fn strategy_params_ui(&mut self, ui: &mut egui::Ui) {
+        let entries = available_strategies();
+        let Some(entry) = entries.get(self.selected_map) else {
+            return;
+        };
+        if entry.parameters.is_empty() {
+            return;
+        }
+
+        let values = resolve_params(&entry.parameters, &self.saved_params(entry));
+        let label = entry.metadata.label();
+        ui.collapsing("策略参数", |ui| {
+            egui::Grid::new("strategy_params")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    for param in &entry.parameters {
+                        let Some(mut value) = values.get(&param.key).cloned() else {
+                            continue;
+                        };
+                        ui.label(&param.label);
+                        let changed = match &mut value {
+                            ParamValue::Bool(v) => ui.checkbox(v, "").changed(),
+                            ParamValue::Int(v) => ui.add(egui::DragValue::new(v)).changed(),
+                            ParamValue::Float(v) => {
+                                ui.add(egui::DragValue::new(v).speed(0.1)).changed()
+                            }
+                            ParamValue::Text(v) => ui.text_edit_singleline(v).changed(),
+                        };
+                        ui.end_row();
+
+                        if changed {
+                            self.strategy_params.insert(
+                                format!("param.{}.{}", label, param.key),
+                                value.to_string(),
+                            );
+                            self.settings_dirty = true;
+                        }
+                    }
+                });
+        });
+    }
+
+    /// 某个策略已保存的参数（参数键 → 字符串值）
+    fn saved_params(&self, entry: &StrategyEntry) -> HashMap<String, String> {
+        let prefix = format!("param.{}.", entry.metadata.label());
+        self.strategy_params
+            .iter()
+            .filter_map(|(k, v)| Some((k.strip_prefix(&prefix)?.to_string(), v.clone())))
+            .collect()
+    }
+
     fn stop_game(&self) {
         request_stop();
         monitor::stop_monitors();
@@ -496,7 +1015,7 @@ impl MainApp {
         thread::spawn(move || {
             log_to(&log, "执行购买陷阱...");
             match buy_traps() {
-                Ok(_) => log_to(&log, "购买陷阱完成"),
+                Ok(loadout) => log_to(&log, &format!("购买陷阱完成: {}", loadout)),
                 Err(e) => log_to(&log, &format!("购买陷阱失败: {}", e)),
             }
         });
@@ -506,7 +1025,7 @@ impl MainApp {
         self.ocr_results.clear();
         self.ocr_error.clear();
 
-        let (x, y, w, h) = match parse_region_coords(&self.ocr_region) {
+        let region = match parse_region_coords(&self.ocr_region) {
             Some(r) => r,
             None => {
                 self.ocr_error = "区域格式错误，需要 x1,y1,x2,y2".to_string();
@@ -514,12 +1033,12 @@ impl MainApp {
             }
         };
 
-        if w <= 0 || h <= 0 {
+        if region.is_empty() {
             self.ocr_error = "区域无效".to_string();
             return;
         }
 
-        match ocr_screen(x, y, w, h, false, false) {
+        match ocr_screen(region, false, false) {
             Ok(results) => {
                 self.log_msg(&format!("OCR 识别到 {} 个文字区域", results.len()));
                 for r in &results {
@@ -625,31 +1144,41 @@ impl eframe::App for MainApp {
             // ===== 控制区域 =====
             ui.horizontal(|ui| {
                 ui.label("地图:");
-                let maps = available_maps();
-                let current_name = if self.selected_map < maps.len() {
-                    format!(
-                        "{} ({})",
-                        maps[self.selected_map].name, maps[self.selected_map].difficulty
-                    )
-                } else {
-                    "无".to_string()
-                };
+                let entries = available_strategies();
+                let current_name = entries
+                    .get(self.selected_map)
+                    .map(|e| e.metadata.label())
+                    .unwrap_or_else(|| "无".to_string());
                 let old_map = self.selected_map;
                 egui::ComboBox::from_id_salt("map_select")
                     .selected_text(&current_name)
                     .show_ui(ui, |ui| {
-                        for (i, map) in maps.iter().enumerate() {
-                            let label = format!("{} ({})", map.name, map.difficulty);
-                            ui.selectable_value(&mut self.selected_map, i, label);
+                        for (i, entry) in entries.iter().enumerate() {
+                            let response = ui.selectable_value(
+                                &mut self.selected_map,
+                                i,
+                                entry.metadata.label(),
+                            );
+                            if let Some(path) = &entry.source {
+                                response.on_hover_text(path.display().to_string());
+                            }
                         }
                     });
                 if self.selected_map != old_map {
                     self.settings_dirty = true;
                 }
 
+                let is_running = GAME_RUNNING.load(Ordering::SeqCst);
+                if ui
+                    .add_enabled(!is_running, egui::Button::new("刷新策略"))
+                    .on_hover_text("重新加载 strategies/ 目录下的策略文件和脚本")
+                    .clicked()
+                {
+                    self.reload_strategies();
+                }
+
                 ui.add_space(20.0);
 
-                let is_running = GAME_RUNNING.load(Ordering::SeqCst);
                 if is_running {
                     if ui.button("停止 (F2)").clicked() {
                         self.stop_game();
@@ -668,6 +1197,9 @@ impl eframe::App for MainApp {
                 }
             });
 
+            self.wave_range_ui(ui);
+            self.strategy_params_ui(ui);
+
             ui.separator();
 
             // ===== 实时状态 + 监控控制 =====
@@ -695,6 +1227,35 @@ impl eframe::App for MainApp {
                 ));
                 ui.add_space(10.0);
 
+                if let Some(next) = monitor::time_to_next_wave() {
+                    ui.label(format!("下一波: {:.0} 秒", next.as_secs_f64()));
+                    ui.add_space(10.0);
+                }
+
+                // 收入速率和等待目标的预计时间
+                if let Some(rate) = monitor::income_rate() {
+                    ui.label(format!("收入: {}/秒", format_gold(rate.round() as i64)));
+                    ui.add_space(10.0);
+                }
+                if let Some(target) = monitor::gold_target() {
+                    let eta = match monitor::gold_eta(target) {
+                        Some(eta) => format!("约 {:.0} 秒", eta.as_secs_f64()),
+                        None => "未知".to_string(),
+                    };
+                    ui.label(format!("目标: {} ({})", format_gold(target), eta));
+                    ui.add_space(10.0);
+                }
+
+                // 额外监控项
+                for (name, value) in monitor::values() {
+                    if monitor::is_builtin(&name) {
+                        continue;
+                    }
+                    let text = value.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string());
+                    ui.label(format!("{}: {}", name, text));
+                    ui.add_space(10.0);
+                }
+
                 if monitor_running {
                     ui.colored_label(egui::Color32::from_rgb(100, 200, 100), "监控中");
                     if ui.small_button("停止监控").clicked() {
@@ -708,6 +1269,26 @@ impl eframe::App for MainApp {
                 }
             });
 
+            // ===== 监控状态 =====
+            egui::CollapsingHeader::new("监控状态")
+                .default_open(false)
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("超时警告(秒):");
+                        let old_stale = self.monitor_stale_secs;
+                        ui.add(egui::DragValue::new(&mut self.monitor_stale_secs).range(1..=120));
+                        if self.monitor_stale_secs != old_stale {
+                            self.settings_dirty = true;
+                        }
+                    });
+                    Self::monitor_stats_grid(ui, self.monitor_stale_secs);
+                    let old_csv = self.timeline_csv;
+                    ui.checkbox(&mut self.timeline_csv, "时间线同时输出 CSV 汇总 (sessions/)");
+                    if self.timeline_csv != old_csv {
+                        self.settings_dirty = true;
+                    }
+                });
+
             ui.separator();
 
             // ===== 监控区域配置 =====
@@ -726,6 +1307,12 @@ impl eframe::App for MainApp {
                         &mut self.gold_region,
                         &mut self.settings_dirty,
                     );
+                    Self::region_input(
+                        ui,
+                        "倒计时区域 (留空不监控):",
+                        &mut self.countdown_region,
+                        &mut self.settings_dirty,
+                    );
                     ui.horizontal(|ui| {
                         ui.label("波次间隔(ms):");
                         let old_wi = self.wave_interval;
@@ -745,6 +1332,46 @@ impl eframe::App for MainApp {
                             self.settings_dirty = true;
                         }
                     });
+                    ui.horizontal(|ui| {
+                        ui.label("波次共识 N/M:");
+                        let old = (self.wave_consensus_n, self.wave_consensus_m);
+                        ui.add(egui::DragValue::new(&mut self.wave_consensus_n).range(1..=5));
+                        ui.label("/");
+                        ui.add(egui::DragValue::new(&mut self.wave_consensus_m).range(1..=9));
+                        if (self.wave_consensus_n, self.wave_consensus_m) != old {
+                            self.settings_dirty = true;
+                        }
+                        ui.add_space(10.0);
+                        ui.label("金币中值窗口:");
+                        let old_mw = self.gold_median_window;
+                        ui.add(egui::DragValue::new(&mut self.gold_median_window).range(1..=9));
+                        if self.gold_median_window != old_mw {
+                            self.settings_dirty = true;
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("金币收入上限(/秒):");
+                        let old_inc = self.gold_max_income;
+                        ui.add(
+                            egui::DragValue::new(&mut self.gold_max_income)
+                                .range(100..=1_000_000)
+                                .speed(100.0),
+                        );
+                        if self.gold_max_income != old_inc {
+                            self.settings_dirty = true;
+                        }
+                        ui.add_space(10.0);
+                        ui.label("单次花费上限:");
+                        let old_sp = self.gold_max_spend;
+                        ui.add(
+                            egui::DragValue::new(&mut self.gold_max_spend)
+                                .range(1000..=10_000_000)
+                                .speed(1000.0),
+                        );
+                        if self.gold_max_spend != old_sp {
+                            self.settings_dirty = true;
+                        }
+                    });
                     ui.separator();
                     ui.horizontal(|ui| {
                         let old_cf = self.gold_use_color_filter;
@@ -791,6 +1418,15 @@ impl eframe::App for MainApp {
 
             ui.separator();
 
+            // ===== 步骤调试 =====
+            egui::CollapsingHeader::new("步骤调试")
+                .default_open(false)
+                .show(ui, |ui| {
+                    self.debugger_ui(ui);
+                });
+
+            ui.separator();
+
             // ===== 快捷操作 =====
             ui.horizontal(|ui| {
                 if ui.button("购买陷阱").clicked() {
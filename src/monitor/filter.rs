@@ -0,0 +1,431 @@
+//! 读数过滤
+//!
+//! 对 OCR 读出的原始数值做时间维度的过滤和合理性检查：
+//! - N-of-M 共识：最近 M 次合理读数中至少 N 次相同才接受
+//! - 合理性检查：波次只能 +1（新一轮回到 1 须持续读到），金币变化幅度受收入/花费上限约束
+//! - 中值平滑：对已接受的值取最近若干次的中值
+//!
+//! 每个被接受的值都带一个置信度，供 `wait_wave`/`wait_gold` 设置门槛。
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+/// 带置信度的读数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading<T> {
+    /// 过滤后的值
+    pub value: T,
+    /// 置信度 (0.0-1.0)：共识比例 × 支持样本的平均 OCR 置信度
+    pub confidence: f32,
+    /// 接受该值的时间
+    pub at: Instant,
+    /// 所属回合代数（见 `monitor::begin_round`），旧回合的读数不能满足新回合的等待
+    pub generation: u64,
+}
+
+/// 合理性检查规则
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Plausibility {
+    /// 不检查
+    None,
+    /// 波次：只能保持或 +1；回到 1（新一轮）须持续 `reanchor_after` 次，
+    /// 防止 "12" 漏读成 "1" 的短暂误读被当作新一轮（`reanchor_after` 为 0 时直接接受）
+    WaveStep,
+    /// 金币：增长 ≤ `max_burst` + `max_income_per_sec` × 距上次接受的秒数，下降 ≤ `max_spend`
+    GoldDelta {
+        max_income_per_sec: i64,
+        max_burst: i64,
+        max_spend: i64,
+    },
+}
+
+/// 过滤配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilterConfig {
+    /// 共识所需相同读数次数 N（1 = 关闭共识）
+    pub consensus_n: usize,
+    /// 共识窗口大小 M
+    pub consensus_m: usize,
+    /// 中值平滑窗口（<= 1 关闭）
+    pub median_window: usize,
+    /// 合理性检查规则
+    pub plausibility: Plausibility,
+    /// 连续被合理性检查拒绝多少次后，以被拒读数的共识值重新定基（防止锁死在错误值上）
+    pub reanchor_after: u32,
+}
+
+impl FilterConfig {
+    /// 不做任何过滤（原始行为）
+    pub fn passthrough() -> Self {
+        Self {
+            consensus_n: 1,
+            consensus_m: 1,
+            median_window: 1,
+            plausibility: Plausibility::None,
+            reanchor_after: 0,
+        }
+    }
+
+    /// 波次默认过滤：3 次中 2 次一致，只能 +1，连续 6 次读到其他值（如回到 1）才重新定基
+    pub fn wave() -> Self {
+        Self {
+            consensus_n: 2,
+            consensus_m: 3,
+            median_window: 1,
+            plausibility: Plausibility::WaveStep,
+            reanchor_after: 6,
+        }
+    }
+
+    /// 金币默认过滤：3 次中值平滑，限制单次变化幅度
+    pub fn gold() -> Self {
+        Self {
+            consensus_n: 1,
+            consensus_m: 1,
+            median_window: 3,
+            plausibility: Plausibility::GoldDelta {
+                max_income_per_sec: 5_000,
+                max_burst: 20_000,
+                max_spend: 100_000,
+            },
+            reanchor_after: 10,
+        }
+    }
+}
+
+/// 单次原始读数
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    value: i64,
+    score: f32,
+}
+
+/// 读数过滤器（每个监控项一个，状态随读数累积）
+#[derive(Debug)]
+pub struct ReadingFilter {
+    config: FilterConfig,
+    /// 通过合理性检查的最近 M 次读数
+    samples: VecDeque<Sample>,
+    /// 被合理性检查拒绝的最近 M 次读数（用于重新定基）
+    rejected: VecDeque<Sample>,
+    /// 连续被拒绝次数
+    rejected_streak: u32,
+    /// 已接受的值（中值平滑窗口）
+    smoothed: VecDeque<i64>,
+    /// 当前接受的读数
+    accepted: Option<Reading<i64>>,
+    /// 当前回合代数，接受的读数会带上它
+    generation: u64,
+    /// 重置后第一个读数必须等于此值（如新一轮的波次 1），None 表示不限制
+    expected_start: Option<i64>,
+}
+
+impl ReadingFilter {
+    pub fn new(config: FilterConfig) -> Self {
+        Self {
+            config,
+            samples: VecDeque::new(),
+            rejected: VecDeque::new(),
+            rejected_streak: 0,
+            smoothed: VecDeque::new(),
+            accepted: None,
+            generation: 0,
+            expected_start: None,
+        }
+    }
+
+    /// 过滤配置
+    pub fn config(&self) -> &FilterConfig {
+        &self.config
+    }
+
+    /// 当前接受的读数
+    pub fn accepted(&self) -> Option<Reading<i64>> {
+        self.accepted
+    }
+
+    /// 清空所有状态（新一轮/重置监控时调用）
+    pub fn reset(&mut self) {
+        self.samples.clear();
+        self.rejected.clear();
+        self.rejected_streak = 0;
+        self.smoothed.clear();
+        self.accepted = None;
+        self.expected_start = None;
+    }
+
+    /// 当前回合代数
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// 切换回合代数（当前接受的读数一并改为新代数）
+    pub fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+        if let Some(reading) = &mut self.accepted {
+            reading.generation = generation;
+        }
+    }
+
+    /// 要求重置后的第一个读数等于 `value`，屏蔽上一轮残留在屏幕上的旧值
+    ///
+    /// 持续读到其他值时仍会按 `reanchor_after` 重新定基。
+    pub fn expect_start(&mut self, value: i64) {
+        self.expected_start = Some(value);
+    }
+
+    /// 输入一次原始读数
+    ///
+    /// # Returns
+    /// 被接受时返回新的读数（值可能与上次相同，置信度和时间会刷新），否则 None
+    pub fn push(&mut self, value: i64, score: f32, now: Instant) -> Option<Reading<i64>> {
+        let m = self.config.consensus_m.max(1);
+        let sample = Sample { value, score };
+
+        if !self.is_plausible(value, now) {
+            self.rejected_streak += 1;
+            push_bounded(&mut self.rejected, sample, m);
+
+            let reanchor = self.config.reanchor_after;
+            if reanchor > 0 && self.rejected_streak >= reanchor {
+                if let Some((value, confidence)) =
+                    consensus(&self.rejected, self.config.consensus_n, m)
+                {
+                    // 错误值被持续读到说明是真实变化（漏读波次、新一轮金币清零等），降低置信度后接受
+                    self.samples = std::mem::take(&mut self.rejected);
+                    self.rejected_streak = 0;
+                    self.smoothed.clear();
+                    return Some(self.accept(value, confidence * 0.5, now));
+                }
+            }
+            return None;
+        }
+
+        self.rejected_streak = 0;
+        self.rejected.clear();
+        push_bounded(&mut self.samples, sample, m);
+
+        let (value, confidence) = consensus(&self.samples, self.config.consensus_n, m)?;
+        Some(self.accept(value, confidence, now))
+    }
+
+    fn accept(&mut self, value: i64, confidence: f32, now: Instant) -> Reading<i64> {
+        push_bounded(&mut self.smoothed, value, self.config.median_window.max(1));
+        let reading = Reading {
+            value: median(&self.smoothed),
+            confidence,
+            at: now,
+            generation: self.generation,
+        };
+        self.accepted = Some(reading);
+        reading
+    }
+
+    /// 相对当前接受值的合理性检查（还没有接受值时一律合理）
+    fn is_plausible(&self, value: i64, now: Instant) -> bool {
+        let Some(prev) = self.accepted else {
+            return match self.expected_start {
+                Some(start) => value == start,
+                None => true,
+            };
+        };
+        match self.config.plausibility {
+            Plausibility::None => true,
+            Plausibility::WaveStep => {
+                value == prev.value
+                    || value == prev.value + 1
+                    || (value == 1 && self.config.reanchor_after == 0)
+            }
+            Plausibility::GoldDelta {
+                max_income_per_sec,
+                max_burst,
+                max_spend,
+            } => {
+                let delta = value - prev.value;
+                if delta >= 0 {
+                    let secs = now.saturating_duration_since(prev.at).as_secs_f64();
+                    delta as f64 <= max_burst as f64 + max_income_per_sec as f64 * secs
+                } else {
+                    -delta <= max_spend
+                }
+            }
+        }
+    }
+}
+
+fn push_bounded<T>(queue: &mut VecDeque<T>, item: T, cap: usize) {
+    queue.push_back(item);
+    while queue.len() > cap {
+        queue.pop_front();
+    }
+}
+
+/// 共识：出现次数最多的值（次数相同取最新），次数 ≥ n 时返回 (值, 置信度)
+fn consensus(samples: &VecDeque<Sample>, n: usize, m: usize) -> Option<(i64, f32)> {
+    let latest = samples.back()?;
+    let mut best = (latest.value, 0usize, 0.0f32);
+
+    for candidate in samples.iter().rev() {
+        let (count, score_sum) = samples
+            .iter()
+            .filter(|s| s.value == candidate.value)
+            .fold((0usize, 0.0f32), |(c, sum), s| (c + 1, sum + s.score));
+        if count > best.1 {
+            best = (candidate.value, count, score_sum);
+        }
+    }
+
+    let (value, count, score_sum) = best;
+    if count < n.max(1) {
+        return None;
+    }
+    let ratio = count as f32 / m as f32;
+    let avg_score = score_sum / count as f32;
+    Some((value, (ratio * avg_score).clamp(0.0, 1.0)))
+}
+
+/// 中值（偶数个取较小的中间值）
+fn median(values: &VecDeque<i64>) -> i64 {
+    let mut sorted: Vec<i64> = values.iter().copied().collect();
+    sorted.sort_unstable();
+    sorted[(sorted.len() - 1) / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_wave_consensus_and_step() {
+        let mut filter = ReadingFilter::new(FilterConfig::wave());
+        let t = Instant::now();
+
+        assert_eq!(filter.push(2, 1.0, t), None);
+        assert_eq!(filter.push(2, 1.0, t).map(|r| r.value), Some(2));
+
+        // "02" 误读为 "20"：不是 +1，被拒绝
+        assert_eq!(filter.push(20, 1.0, t), None);
+        assert_eq!(filter.accepted().map(|r| r.value), Some(2));
+
+        // 单次新值还不够共识，保持旧值
+        assert_eq!(filter.push(3, 1.0, t).map(|r| r.value), Some(2));
+        assert_eq!(filter.push(3, 1.0, t).map(|r| r.value), Some(3));
+
+        // 新一轮回到 1：持续读到才接受
+        for _ in 1..FilterConfig::wave().reanchor_after {
+            assert_eq!(filter.push(1, 1.0, t), None);
+        }
+        assert_eq!(filter.push(1, 1.0, t).map(|r| r.value), Some(1));
+    }
+
+    #[test]
+    fn test_wave_short_drop_to_one_rejected() {
+        let mut filter = ReadingFilter::new(FilterConfig::wave());
+        let t = Instant::now();
+        filter.push(12, 1.0, t);
+        filter.push(12, 1.0, t);
+
+        // "12" 漏读成 "1" 的短暂误读，中间夹着正确读数
+        for value in [1, 1, 1, 12, 1, 1, 12, 1, 1, 1] {
+            if let Some(reading) = filter.push(value, 1.0, t) {
+                assert_eq!(reading.value, 12);
+            }
+        }
+        assert_eq!(filter.accepted().map(|r| r.value), Some(12));
+    }
+
+    #[test]
+    fn test_wave_reanchor_after_skip() {
+        let mut filter = ReadingFilter::new(FilterConfig::wave());
+        let t = Instant::now();
+        filter.push(3, 1.0, t);
+        filter.push(3, 1.0, t);
+
+        // 漏读了波次 4，持续读到 5 后重新定基
+        let mut accepted = None;
+        for _ in 0..6 {
+            accepted = filter.push(5, 1.0, t);
+        }
+        let reading = accepted.expect("应重新定基");
+        assert_eq!(reading.value, 5);
+        assert!(reading.confidence < 1.0);
+    }
+
+    #[test]
+    fn test_gold_delta_and_median() {
+        let mut filter = ReadingFilter::new(FilterConfig::gold());
+        let t = Instant::now();
+
+        filter.push(3600, 1.0, t);
+        filter.push(3600, 1.0, t);
+        // 逗号丢失读成 "36"：单次离群值被中值平滑掉
+        assert_eq!(filter.push(36, 1.0, t).map(|r| r.value), Some(3600));
+
+        // 短时间内增长远超收入上限：拒绝
+        assert_eq!(filter.push(3_600_000, 1.0, t), None);
+
+        // 合理增长被接受
+        let later = t + Duration::from_secs(2);
+        filter.push(4800, 1.0, later);
+        assert_eq!(filter.push(4800, 1.0, later).map(|r| r.value), Some(4800));
+    }
+
+    #[test]
+    fn test_confidence_reflects_consensus_and_score() {
+        let mut filter = ReadingFilter::new(FilterConfig::wave());
+        let t = Instant::now();
+        filter.push(4, 0.8, t);
+        let reading = filter.push(4, 0.8, t).unwrap();
+        assert!((reading.confidence - 0.8 * 2.0 / 3.0).abs() < 1e-4);
+
+        let reading = filter.push(4, 0.8, t).unwrap();
+        assert!((reading.confidence - 0.8).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_expect_start_blocks_stale_value() {
+        let mut filter = ReadingFilter::new(FilterConfig::wave());
+        let t = Instant::now();
+        filter.set_generation(3);
+        filter.expect_start(1);
+
+        // 上一轮残留的波次 9 不被接受
+        assert_eq!(filter.push(9, 1.0, t), None);
+        assert_eq!(filter.push(9, 1.0, t), None);
+
+        filter.push(1, 1.0, t);
+        let reading = filter.push(1, 1.0, t).unwrap();
+        assert_eq!((reading.value, reading.generation), (1, 3));
+    }
+
+    #[test]
+    fn test_resume_without_expect_start() {
+        let t = Instant::now();
+
+        // 中途接管：不要求从 1 开始，当前波次按普通共识接受
+        let mut filter = ReadingFilter::new(FilterConfig::wave());
+        assert_eq!(filter.push(7, 1.0, t), None);
+        assert_eq!(filter.push(7, 1.0, t).map(|r| r.value), Some(7));
+
+        // 要求从 1 开始时，同样的读数要等到重新定基才被接受
+        let mut filter = ReadingFilter::new(FilterConfig::wave());
+        filter.expect_start(1);
+        let reanchor = FilterConfig::wave().reanchor_after;
+        for _ in 1..reanchor {
+            assert_eq!(filter.push(7, 1.0, t), None);
+        }
+        assert_eq!(filter.push(7, 1.0, t).map(|r| r.value), Some(7));
+    }
+
+    #[test]
+    fn test_passthrough() {
+        let mut filter = ReadingFilter::new(FilterConfig::passthrough());
+        let t = Instant::now();
+        assert_eq!(filter.push(20, 1.0, t).map(|r| r.value), Some(20));
+        assert_eq!(filter.push(2, 1.0, t).map(|r| r.value), Some(2));
+    }
+}
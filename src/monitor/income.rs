@@ -0,0 +1,137 @@
+//! 金币收入估算
+//!
+//! 记录过滤后的金币时间序列，按波次估算每秒收入：
+//! 只累计金币上涨的部分，购买/放置陷阱造成的下降不计入，也不会拉低速率。
+//! 当前波次的数据不足时退回上一波的速率。
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// 最多保留多少个金币样本
+const MAX_SAMPLES: usize = 2000;
+
+/// 当前波次数据跨度不足此值时使用上一波的速率
+const MIN_SPAN: Duration = Duration::from_secs(3);
+
+/// 金币样本
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GoldSample {
+    pub at: Instant,
+    pub gold: i64,
+    /// 记录时的波次（0 = 未知）
+    pub wave: u32,
+}
+
+/// 金币时间序列和收入估算
+#[derive(Debug, Default)]
+pub(super) struct IncomeTracker {
+    samples: VecDeque<GoldSample>,
+}
+
+impl IncomeTracker {
+    /// 记录一次金币值
+    pub fn record(&mut self, sample: GoldSample) {
+        if self.samples.len() >= MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// 清空序列（新回合）
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// 指定波次的每秒收入（该波第一个样本到 `now`），数据跨度不足 `MIN_SPAN` 时返回 None
+    pub fn wave_rate(&self, wave: u32, now: Instant) -> Option<f64> {
+        let mut iter = self.samples.iter().filter(|s| s.wave == wave);
+        let first = iter.next()?;
+
+        let mut prev = first.gold;
+        let mut income = 0;
+        for s in iter {
+            income += (s.gold - prev).max(0);
+            prev = s.gold;
+        }
+
+        let span = now.checked_duration_since(first.at)?;
+        if span < MIN_SPAN {
+            return None;
+        }
+        Some(income as f64 / span.as_secs_f64())
+    }
+
+    /// 当前每秒收入：优先用最新样本所在波次，数据不足时用之前最近一个有数据的波次
+    pub fn rate(&self, now: Instant) -> Option<f64> {
+        let current = self.samples.back()?.wave;
+        if let Some(rate) = self.wave_rate(current, now) {
+            return Some(rate);
+        }
+
+        // 上一波以其最后一个样本为终点计算
+        let previous = self.samples.iter().rev().find(|s| s.wave != current)?;
+        self.wave_rate(previous.wave, previous.at)
+    }
+
+    /// 预计多久后金币达到 `amount`：已达到返回 0，没有收入数据返回 None
+    pub fn eta(&self, amount: i64, now: Instant) -> Option<Duration> {
+        let gold = self.samples.back()?.gold;
+        if gold >= amount {
+            return Some(Duration::ZERO);
+        }
+        let rate = self.rate(now)?;
+        if rate <= 0.0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64((amount - gold) as f64 / rate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(t0: Instant, secs: u64, gold: i64, wave: u32) -> GoldSample {
+        GoldSample {
+            at: t0 + Duration::from_secs(secs),
+            gold,
+            wave,
+        }
+    }
+
+    #[test]
+    fn test_rate_ignores_spending() {
+        let t0 = Instant::now();
+        let mut tracker = IncomeTracker::default();
+        tracker.record(sample(t0, 0, 1000, 1));
+        tracker.record(sample(t0, 2, 1200, 1));
+        // 买陷阱花掉 1000
+        tracker.record(sample(t0, 3, 200, 1));
+        tracker.record(sample(t0, 5, 500, 1));
+
+        // 上涨共 200 + 300 = 500，跨度 5 秒
+        let rate = tracker.rate(t0 + Duration::from_secs(5)).unwrap();
+        assert!((rate - 100.0).abs() < 1e-9);
+
+        let eta = tracker.eta(1500, t0 + Duration::from_secs(5)).unwrap();
+        assert_eq!(eta, Duration::from_secs(10));
+        assert_eq!(tracker.eta(400, t0), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_rate_falls_back_to_previous_wave() {
+        let t0 = Instant::now();
+        let mut tracker = IncomeTracker::default();
+        tracker.record(sample(t0, 0, 0, 1));
+        tracker.record(sample(t0, 10, 2000, 1));
+        tracker.record(sample(t0, 11, 2100, 2));
+
+        // 第 2 波只有 1 秒数据，用第 1 波的 200/秒
+        let rate = tracker.rate(t0 + Duration::from_secs(12)).unwrap();
+        assert!((rate - 200.0).abs() < 1e-9);
+
+        tracker.clear();
+        assert!(tracker.rate(t0).is_none());
+        assert!(tracker.eta(100, t0).is_none());
+    }
+}
@@ -0,0 +1,1028 @@
+//! 后台监控模块
+//!
+//! 提供屏幕区域的持续监控。每个监控项（`WatcherConfig`）有自己的名称、区域、预处理、
+//! 解析方式和间隔，在独立线程中读取；波次（"wave"）和金币（"gold"）是内置监控项，
+//! 其余通过 `MonitorConfig::watchers` 添加，策略用 `monitor::value(name)` 查询。
+//! 整数读数先经过 `filter` 子模块的共识/合理性/平滑过滤，再作为带置信度的 `Reading` 对外提供。
+//!
+//! 监控按回合划分代数（generation）：`begin_round`/`end_round` 或检测到波次回到 1 时代数加一，
+//! 旧代数的读数会被清空，因此上一轮残留的波次/金币不会满足下一轮的等待。
+//! `Monitor` 句柄持有后台线程和共享状态，停止时会等待线程真正退出，
+//! 状态变化通过 `subscribe()` 返回的通道以 `MonitorEvent` 推送。
+//!
+//! 模块级函数（`start_monitors`、`current_wave` 等）操作全局唯一的活动实例，
+//! 供策略代码和 GUI 直接调用。
+
+pub mod filter;
+pub mod income;
+pub mod stats;
+pub mod watcher;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::screen::Region;
+use crate::stop_flag::should_stop;
+
+use filter::ReadingFilter;
+pub use filter::{FilterConfig, Plausibility, Reading};
+use income::{GoldSample, IncomeTracker};
+use stats::HealthTracker;
+pub use stats::WatcherStats;
+use watcher::{Reader, COUNTDOWN, GOLD, WAVE};
+pub use watcher::{is_builtin, Parser, Pipeline, WatchValue, WatcherConfig};
+
+// ===== 配置 =====
+
+/// 倒计时读数超过其剩余时间多久后视为失效
+const COUNTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+/// 监控配置
+#[derive(Debug, Clone)]
+pub struct MonitorConfig {
+    /// 波次 OCR 区域（实际屏幕坐标）
+    pub wave_region: Region,
+    /// 金币 OCR 区域（实际屏幕坐标）
+    pub gold_region: Region,
+    /// 波次检测间隔 (毫秒)
+    pub wave_interval_ms: u64,
+    /// 金币检测间隔 (毫秒)
+    pub gold_interval_ms: u64,
+    /// 金币文字颜色 (R, G, B)，用于颜色过滤
+    pub gold_text_color: (u8, u8, u8),
+    /// 金币颜色容差（RGB 欧氏距离）
+    pub gold_color_tolerance: f64,
+    /// 是否使用颜色过滤（false 则用 Otsu 二值化）
+    pub gold_use_color_filter: bool,
+    /// 波次读数过滤
+    pub wave_filter: FilterConfig,
+    /// 金币读数过滤
+    pub gold_filter: FilterConfig,
+    /// 波次倒计时 OCR 区域，None 则不监控倒计时
+    pub countdown_region: Option<Region>,
+    /// 倒计时检测间隔 (毫秒)
+    pub countdown_interval_ms: u64,
+    /// 额外的监控项（基地血量、Boss 名称等），名称不能与内置的 "wave"/"gold"/"countdown" 重复
+    pub watchers: Vec<WatcherConfig>,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            wave_region: Region::new(1841, 733, 172, 52),
+            gold_region: Region::new(48, 56, 120, 22),
+            wave_interval_ms: 500,
+            gold_interval_ms: 300,
+            gold_text_color: (0xd9, 0xe1, 0xe3), // #d9e1e3
+            gold_color_tolerance: 35.0,
+            gold_use_color_filter: true,
+            wave_filter: FilterConfig::wave(),
+            gold_filter: FilterConfig::gold(),
+            countdown_region: None,
+            countdown_interval_ms: 500,
+            watchers: Vec::new(),
+        }
+    }
+}
+
+impl MonitorConfig {
+    /// 全部监控项：内置的波次、金币、倒计时 + 额外配置的监控项（重名的会被跳过）
+    pub fn all_watchers(&self) -> Vec<WatcherConfig> {
+        let gold_pipeline = if self.gold_use_color_filter {
+            Pipeline::ColorFilter {
+                scale: 3,
+                color: self.gold_text_color,
+                tolerance: self.gold_color_tolerance,
+            }
+        } else {
+            Pipeline::Small { scale: 3 }
+        };
+
+        let mut watchers = vec![
+            WatcherConfig {
+                name: WAVE.to_string(),
+                region: self.wave_region,
+                pipeline: Pipeline::Small { scale: 3 },
+                parser: Parser::Integer,
+                interval_ms: self.wave_interval_ms,
+                filter: Some(self.wave_filter.clone()),
+            },
+            WatcherConfig {
+                name: GOLD.to_string(),
+                region: self.gold_region,
+                pipeline: gold_pipeline,
+                parser: Parser::Integer,
+                interval_ms: self.gold_interval_ms,
+                filter: Some(self.gold_filter.clone()),
+            },
+        ];
+
+        if let Some(region) = self.countdown_region {
+            watchers.push(WatcherConfig {
+                name: COUNTDOWN.to_string(),
+                region,
+                pipeline: Pipeline::Small { scale: 3 },
+                parser: Parser::Countdown,
+                interval_ms: self.countdown_interval_ms,
+                filter: None,
+            });
+        }
+
+        for extra in &self.watchers {
+            if watchers.iter().any(|w| w.name == extra.name) {
+                println!("[Monitor] 监控项名称重复，已跳过: {}", extra.name);
+                continue;
+            }
+            watchers.push(extra.clone());
+        }
+        watchers
+    }
+}
+
+// ===== 事件 =====
+
+/// 监控事件（通过 `subscribe()` 获得的通道推送）
+#[derive(Debug, Clone)]
+pub enum MonitorEvent {
+    /// 波次变化
+    WaveChanged { old: u32, new: u32, at: Instant },
+    /// 金币变化
+    GoldChanged { old: i64, new: i64, at: Instant },
+    /// 新回合开始（`auto` 为 true 表示由波次回到 1 自动检测）
+    RoundBegan {
+        generation: u64,
+        auto: bool,
+        at: Instant,
+    },
+    /// 回合结束
+    RoundEnded { generation: u64, at: Instant },
+    /// 额外监控项的值变化
+    ValueChanged {
+        watcher: String,
+        old: Option<WatchValue>,
+        new: WatchValue,
+        at: Instant,
+    },
+    /// 读取失败（OCR 出错或无法解析）
+    ReadFailed {
+        watcher: String,
+        error: String,
+        at: Instant,
+    },
+}
+
+// ===== 监控实例 =====
+
+/// 单个监控项的状态
+struct Slot {
+    /// 整数读数的过滤器；非整数读数只用它记录回合代数
+    filter: ReadingFilter,
+    /// 当前值
+    latest: Option<Reading<WatchValue>>,
+    /// 检测间隔
+    interval: Duration,
+    /// 运行状态统计（不随回合重置）
+    health: HealthTracker,
+}
+
+/// 监控线程共享的状态
+struct Shared {
+    /// 各监控项的状态（启动后集合不变）
+    slots: HashMap<String, Mutex<Slot>>,
+    /// 金币时间序列（收入估算）
+    income: Mutex<IncomeTracker>,
+    /// 当前回合代数
+    generation: AtomicU64,
+    /// 是否处于回合中
+    in_round: AtomicBool,
+    /// 监控是否在运行
+    running: AtomicBool,
+    /// 事件订阅者，发送失败（接收端已丢弃）的会被移除
+    subscribers: Mutex<Vec<Sender<MonitorEvent>>>,
+    /// 停止信号，配合 Condvar 让线程在休眠中也能立即醒来
+    shutdown: Mutex<bool>,
+    wakeup: Condvar,
+}
+
+impl Shared {
+    fn new(config: &MonitorConfig) -> Self {
+        Self {
+            slots: config
+                .all_watchers()
+                .into_iter()
+                .map(|w| {
+                    let filter = w.filter.unwrap_or_else(FilterConfig::passthrough);
+                    let slot = Slot {
+                        filter: ReadingFilter::new(filter),
+                        latest: None,
+                        interval: Duration::from_millis(w.interval_ms),
+                        health: HealthTracker::default(),
+                    };
+                    (w.name, Mutex::new(slot))
+                })
+                .collect(),
+            income: Mutex::new(IncomeTracker::default()),
+            generation: AtomicU64::new(0),
+            in_round: AtomicBool::new(false),
+            running: AtomicBool::new(true),
+            subscribers: Mutex::new(Vec::new()),
+            shutdown: Mutex::new(false),
+            wakeup: Condvar::new(),
+        }
+    }
+
+    /// 线程是否应继续运行
+    fn alive(&self) -> bool {
+        self.running.load(Ordering::Relaxed) && !should_stop()
+    }
+
+    /// 休眠指定时间，期间收到停止信号会提前返回
+    fn sleep(&self, dur: Duration) {
+        if let Ok(guard) = self.shutdown.lock() {
+            let _ = self
+                .wakeup
+                .wait_timeout_while(guard, dur, |stopped| !*stopped);
+        }
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// 进入新的回合代数：清空所有读数；波次读数在 `keep_wave` 时保留（自动检测到回到 1 的情况），
+    /// 否则清空，`wave_start` 为 Some 时要求新一轮从该波次开始
+    fn advance_generation(&self, keep_wave: bool, wave_start: Option<i64>) -> u64 {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        for (name, slot) in &self.slots {
+            let Ok(mut slot) = slot.lock() else {
+                continue;
+            };
+            if name == WAVE && keep_wave {
+                slot.filter.set_generation(generation);
+                if let Some(latest) = &mut slot.latest {
+                    latest.generation = generation;
+                }
+                continue;
+            }
+            slot.filter.reset();
+            if let (WAVE, Some(start)) = (name.as_str(), wave_start) {
+                slot.filter.expect_start(start);
+            }
+            slot.filter.set_generation(generation);
+            slot.latest = None;
+        }
+        if let Ok(mut income) = self.income.lock() {
+            income.clear();
+        }
+        generation
+    }
+
+    /// 一次变化反映到过滤后读数所需的时间：中值窗口填满新读数，再加一次检测的余量
+    fn settle_time(&self, name: &str) -> Option<Duration> {
+        let slot = self.slots.get(name)?.lock().ok()?;
+        let samples = slot.filter.config().median_window.max(1) as u32 + 1;
+        Some(slot.interval * samples)
+    }
+
+    /// 读取监控项当前值
+    fn reading(&self, name: &str) -> Option<Reading<WatchValue>> {
+        self.slots.get(name)?.lock().ok()?.latest.clone()
+    }
+
+    /// 读取监控项当前的整数读数
+    fn int_reading(&self, name: &str) -> Option<Reading<i64>> {
+        let reading = self.reading(name)?;
+        Some(Reading {
+            value: reading.value.as_int()?,
+            confidence: reading.confidence,
+            at: reading.at,
+            generation: reading.generation,
+        })
+    }
+
+    /// 把一次原始读数送入监控项，接受的值发生变化时返回 (旧值, 新值)
+    ///
+    /// 整数读数经过过滤器，其他类型直接接受。
+    /// `generation` 是截图前读取的回合代数，截图期间回合已切换的读数直接丢弃。
+    fn apply(
+        &self,
+        name: &str,
+        generation: u64,
+        value: WatchValue,
+        score: f32,
+    ) -> Option<(Option<WatchValue>, WatchValue)> {
+        let mut slot = self.slots.get(name)?.lock().ok()?;
+        if slot.filter.generation() != generation {
+            return None;
+        }
+        let old = slot.latest.as_ref().map(|r| r.value.clone());
+
+        let reading = match value {
+            WatchValue::Int(v) => {
+                let r = slot.filter.push(v, score, Instant::now())?;
+                Reading {
+                    value: WatchValue::Int(r.value),
+                    confidence: r.confidence,
+                    at: r.at,
+                    generation: r.generation,
+                }
+            }
+            value => Reading {
+                value,
+                confidence: score,
+                at: Instant::now(),
+                generation,
+            },
+        };
+        let new = reading.value.clone();
+        slot.latest = Some(reading);
+        (old.as_ref() != Some(&new)).then_some((old, new))
+    }
+
+    /// 距下一波开始的时间（见 `Monitor::time_to_next_wave`）
+    fn time_to_next_wave(&self) -> Option<Duration> {
+        let reading = self.int_reading(COUNTDOWN)?;
+        let remaining = Duration::from_secs(reading.value.max(0) as u64);
+        let elapsed = reading.at.elapsed();
+        // 倒计时消失后最后一次读数会一直保留，超过容差即视为失效
+        if elapsed > remaining + COUNTDOWN_GRACE {
+            return None;
+        }
+        Some(remaining.saturating_sub(elapsed))
+    }
+
+    /// 记录金币时间序列（附带当前波次）
+    fn record_gold(&self, gold: i64, at: Instant) {
+        let wave = self.int_reading(WAVE).map_or(0, |r| r.value as u32);
+        if let Ok(mut income) = self.income.lock() {
+            income.record(GoldSample { at, gold, wave });
+        }
+    }
+
+    /// 记录一次读取的耗时和结果
+    fn record(&self, name: &str, started: Instant, ok: bool, raw: String) {
+        if let Some(Ok(mut slot)) = self.slots.get(name).map(|s| s.lock()) {
+            slot.health.record(started, started.elapsed(), ok, raw);
+        }
+    }
+
+    /// 全部监控项的运行状态（按名称排序）
+    fn stats(&self) -> Vec<WatcherStats> {
+        let mut stats: Vec<_> = self
+            .slots
+            .iter()
+            .filter_map(|(name, slot)| Some(slot.lock().ok()?.health.snapshot(name)))
+            .collect();
+        stats.sort_by(|a, b| a.name.cmp(&b.name));
+        stats
+    }
+
+    fn emit(&self, event: MonitorEvent) {
+        if let Ok(mut subs) = self.subscribers.lock() {
+            subs.retain(|tx| tx.send(event.clone()).is_ok());
+        }
+    }
+
+    fn read_failed(&self, watcher: &str, error: String) {
+        self.emit(MonitorEvent::ReadFailed {
+            watcher: watcher.to_string(),
+            error,
+            at: Instant::now(),
+        });
+    }
+}
+
+/// 后台监控句柄
+///
+/// 每个监控项一个线程，`shutdown()`（或 drop）会通知线程退出并等待结束，
+/// 因此先停止再启动不会出现新旧线程同时运行的情况。
+pub struct Monitor {
+    shared: Arc<Shared>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Monitor {
+    /// 启动后台监控线程（每个监控项一个）
+    pub fn start(config: MonitorConfig) -> Self {
+        println!("[Monitor] 启动后台监控");
+        let shared = Arc::new(Shared::new(&config));
+
+        let mut threads = Vec::new();
+        for watcher in config.all_watchers() {
+            let reader = match Reader::new(watcher.clone()) {
+                Ok(reader) => reader,
+                Err(e) => {
+                    println!("[Monitor] 监控项 '{}' 配置无效，已跳过: {}", watcher.name, e);
+                    continue;
+                }
+            };
+            let shared = shared.clone();
+            threads.push(thread::spawn(move || watcher_loop(&shared, &reader, watcher)));
+        }
+
+        Self { shared, threads }
+    }
+
+    /// 读取当前波次读数（过滤后，带置信度）
+    pub fn wave_reading(&self) -> Option<Reading<u32>> {
+        let reading = self.shared.int_reading(WAVE)?;
+        Some(Reading {
+            value: reading.value as u32,
+            confidence: reading.confidence,
+            at: reading.at,
+            generation: reading.generation,
+        })
+    }
+
+    /// 读取当前金币读数（过滤后，带置信度）
+    pub fn gold_reading(&self) -> Option<Reading<i64>> {
+        self.shared.int_reading(GOLD)
+    }
+
+    /// 金币变化反映到读数所需的时间（检测间隔 × 中值窗口，另加一次检测）
+    pub fn gold_settle_time(&self) -> Option<Duration> {
+        self.shared.settle_time(GOLD)
+    }
+
+    /// 读取指定监控项的当前读数
+    pub fn reading(&self, name: &str) -> Option<Reading<WatchValue>> {
+        self.shared.reading(name)
+    }
+
+    /// 全部监控项的当前值（按名称排序，还没有读数的为 None）
+    pub fn values(&self) -> Vec<(String, Option<WatchValue>)> {
+        let mut values: Vec<_> = self
+            .shared
+            .slots
+            .keys()
+            .map(|name| (name.clone(), self.shared.reading(name).map(|r| r.value)))
+            .collect();
+        values.sort_by(|a, b| a.0.cmp(&b.0));
+        values
+    }
+
+    /// 读取当前波次
+    pub fn current_wave(&self) -> u32 {
+        self.wave_reading().map(|r| r.value).unwrap_or(0)
+    }
+
+    /// 读取当前金币
+    pub fn current_gold(&self) -> i64 {
+        self.gold_reading().map(|r| r.value).unwrap_or(0)
+    }
+
+    /// 距下一波开始的时间（最近一次倒计时读数减去已过去的时间）
+    ///
+    /// 没有倒计时读数，或倒计时已消失（波次已开始）时返回 None。
+    pub fn time_to_next_wave(&self) -> Option<Duration> {
+        self.shared.time_to_next_wave()
+    }
+
+    /// 当前每秒金币收入（不计花费），数据不足时返回 None
+    pub fn income_rate(&self) -> Option<f64> {
+        self.shared.income.lock().ok()?.rate(Instant::now())
+    }
+
+    /// 预计多久后金币达到 `amount`（已达到为 0），没有收入数据时返回 None
+    pub fn gold_eta(&self, amount: i64) -> Option<Duration> {
+        self.shared.income.lock().ok()?.eta(amount, Instant::now())
+    }
+
+    /// 全部监控项的运行状态（按名称排序）
+    pub fn stats(&self) -> Vec<WatcherStats> {
+        self.shared.stats()
+    }
+
+    /// 重置所有监控项（连同过滤器的历史读数），并进入新的回合代数
+    pub fn reset(&self) {
+        self.shared.advance_generation(false, Some(1));
+    }
+
+    /// 当前回合代数
+    pub fn round_generation(&self) -> u64 {
+        self.shared.generation()
+    }
+
+    /// 是否处于回合中（`begin_round` 之后、`end_round` 之前）
+    pub fn in_round(&self) -> bool {
+        self.shared.in_round.load(Ordering::SeqCst)
+    }
+
+    /// 开始新回合：清空上一轮的读数，代数加一
+    pub fn begin_round(&self) -> u64 {
+        self.start_round(Some(1))
+    }
+
+    /// 开始可能是中途接管的回合：与 `begin_round` 相同，但不要求波次从 1 开始
+    ///
+    /// 中途重新启动时屏幕上已是第 N 波，`begin_round` 会把它当作上一轮的残留值拒绝，
+    /// 直到 `reanchor_after` 次拒绝后才重新定基。
+    pub fn begin_round_resumable(&self) -> u64 {
+        self.start_round(None)
+    }
+
+    fn start_round(&self, wave_start: Option<i64>) -> u64 {
+        let generation = self.shared.advance_generation(false, wave_start);
+        self.shared.in_round.store(true, Ordering::SeqCst);
+        println!("[Monitor] 回合开始 (代数 {})", generation);
+        self.shared.emit(MonitorEvent::RoundBegan {
+            generation,
+            auto: false,
+            at: Instant::now(),
+        });
+        generation
+    }
+
+    /// 结束当前回合：清空读数，代数加一，之后的等待不会被本轮的值满足
+    pub fn end_round(&self) {
+        let ended = self.shared.generation();
+        self.shared.advance_generation(false, Some(1));
+        self.shared.in_round.store(false, Ordering::SeqCst);
+        println!("[Monitor] 回合结束 (代数 {})", ended);
+        self.shared.emit(MonitorEvent::RoundEnded {
+            generation: ended,
+            at: Instant::now(),
+        });
+    }
+
+    /// 监控线程是否仍在运行
+    pub fn is_running(&self) -> bool {
+        self.shared.running.load(Ordering::Relaxed) && self.threads.iter().any(|t| !t.is_finished())
+    }
+
+    /// 订阅监控事件
+    pub fn subscribe(&self) -> Receiver<MonitorEvent> {
+        let (tx, rx) = mpsc::channel();
+        if let Ok(mut subs) = self.shared.subscribers.lock() {
+            subs.push(tx);
+        }
+        rx
+    }
+
+    /// 通知线程退出并等待全部结束（保留最后读到的状态）
+    pub fn shutdown(&mut self) {
+        if self.threads.is_empty() {
+            return;
+        }
+        self.shared.running.store(false, Ordering::Relaxed);
+        if let Ok(mut stopped) = self.shared.shutdown.lock() {
+            *stopped = true;
+        }
+        self.shared.wakeup.notify_all();
+
+        for handle in self.threads.drain(..) {
+            let _ = handle.join();
+        }
+        println!("[Monitor] 停止后台监控");
+    }
+}
+
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+// ===== 全局活动实例 =====
+
+/// 当前活动的监控实例（停止后保留，以便继续显示最后的读数）
+static ACTIVE: Mutex<Option<Monitor>> = Mutex::new(None);
+
+/// 串行化启动/停止（`ACTIVE` 只在取出/放回实例时短暂加锁）
+static LIFECYCLE: Mutex<()> = Mutex::new(());
+
+/// 策略正在等待的金币目标（`wait_gold` 设置，GUI 显示预计时间）
+static GOLD_TARGET: Mutex<Option<i64>> = Mutex::new(None);
+
+/// 在活动实例上执行操作，没有实例时返回 None
+fn with_active<T>(f: impl FnOnce(&mut Monitor) -> T) -> Option<T> {
+    let mut active = ACTIVE.lock().ok()?;
+    active.as_mut().map(f)
+}
+
+// ===== 公开 API =====
+
+/// 读取当前波次
+pub fn current_wave() -> u32 {
+    with_active(|m| m.current_wave()).unwrap_or(0)
+}
+
+/// 读取当前金币
+pub fn current_gold() -> i64 {
+    with_active(|m| m.current_gold()).unwrap_or(0)
+}
+
+/// 读取当前波次读数（带置信度），还没有接受的读数时返回 None
+pub fn wave_reading() -> Option<Reading<u32>> {
+    with_active(|m| m.wave_reading()).flatten()
+}
+
+/// 读取当前金币读数（带置信度），还没有接受的读数时返回 None
+pub fn gold_reading() -> Option<Reading<i64>> {
+    with_active(|m| m.gold_reading()).flatten()
+}
+
+/// 金币变化反映到读数所需的时间，监控未启动时返回 None
+pub fn gold_settle_time() -> Option<Duration> {
+    with_active(|m| m.gold_settle_time()).flatten()
+}
+
+/// 读取指定监控项的当前值，没有该监控项或还没有读数时返回 None
+///
+/// # Example
+/// ```ignore
+/// if let Some(hp) = monitor::value("core_hp").and_then(|v| v.as_ratio()) { ... }
+/// ```
+pub fn value(name: &str) -> Option<WatchValue> {
+    reading(name).map(|r| r.value)
+}
+
+/// 读取指定监控项的当前读数（带置信度和回合代数）
+pub fn reading(name: &str) -> Option<Reading<WatchValue>> {
+    with_active(|m| m.reading(name)).flatten()
+}
+
+/// 全部监控项的当前值（按名称排序）
+pub fn values() -> Vec<(String, Option<WatchValue>)> {
+    with_active(|m| m.values()).unwrap_or_default()
+}
+
+/// 距下一波开始的时间（需要配置倒计时区域），不在准备阶段时返回 None
+pub fn time_to_next_wave() -> Option<Duration> {
+    with_active(|m| m.time_to_next_wave()).flatten()
+}
+
+/// 当前每秒金币收入（按波次估算，不计花费），数据不足时返回 None
+pub fn income_rate() -> Option<f64> {
+    with_active(|m| m.income_rate()).flatten()
+}
+
+/// 预计多久后金币达到 `amount`（已达到为 0），没有收入数据时返回 None
+pub fn gold_eta(amount: i64) -> Option<Duration> {
+    with_active(|m| m.gold_eta(amount)).flatten()
+}
+
+/// 设置/清除策略正在等待的金币目标
+pub fn set_gold_target(amount: Option<i64>) {
+    if let Ok(mut target) = GOLD_TARGET.lock() {
+        *target = amount;
+    }
+}
+
+/// 策略正在等待的金币目标
+pub fn gold_target() -> Option<i64> {
+    GOLD_TARGET.lock().ok().and_then(|t| *t)
+}
+
+/// 全部监控项的运行状态（读取频率、耗时、失败率等），监控未启动时为空
+pub fn stats() -> Vec<WatcherStats> {
+    with_active(|m| m.stats()).unwrap_or_default()
+}
+
+/// 重置监控状态
+pub fn reset_monitors() {
+    with_active(|m| m.reset());
+}
+
+/// 当前回合代数（监控未启动时为 0）
+pub fn round_generation() -> u64 {
+    with_active(|m| m.round_generation()).unwrap_or(0)
+}
+
+/// 是否处于回合中
+pub fn in_round() -> bool {
+    with_active(|m| m.in_round()).unwrap_or(false)
+}
+
+/// 开始新回合（每轮 `Strategy::start` 之前调用）
+pub fn begin_round() {
+    with_active(|m| m.begin_round());
+}
+
+/// 开始可能是中途接管的回合（见 `Monitor::begin_round_resumable`）
+pub fn begin_round_resumable() {
+    with_active(|m| m.begin_round_resumable());
+}
+
+/// 结束当前回合（每轮 `Strategy::run_waves` 返回之后调用）
+pub fn end_round() {
+    with_active(|m| m.end_round());
+}
+
+/// 监控是否在运行
+pub fn is_running() -> bool {
+    with_active(|m| m.is_running()).unwrap_or(false)
+}
+
+/// 订阅活动实例的监控事件，监控未启动时返回 None
+pub fn subscribe() -> Option<Receiver<MonitorEvent>> {
+    with_active(|m| m.subscribe())
+}
+
+/// 启动后台监控线程（波次、金币及额外监控项）
+///
+/// 已有旧实例时会先等待其线程退出，再启动新实例。
+/// 等待线程退出时不持有 `ACTIVE` 锁，GUI 的读取不会被阻塞。
+pub fn start_monitors(config: MonitorConfig) {
+    let Ok(_lifecycle) = LIFECYCLE.lock() else {
+        return;
+    };
+    let old = {
+        let Ok(mut active) = ACTIVE.lock() else {
+            return;
+        };
+        if active.as_ref().is_some_and(|m| m.is_running()) {
+            println!("[Monitor] 监控已在运行");
+            return;
+        }
+        active.take()
+    };
+
+    if let Some(mut old) = old {
+        old.shutdown();
+    }
+    let monitor = Monitor::start(config);
+    if let Ok(mut active) = ACTIVE.lock() {
+        *active = Some(monitor);
+    }
+}
+
+/// 停止后台监控（等待线程退出）
+///
+/// 先把实例从 `ACTIVE` 中取出，在锁外等待线程退出，之后放回（停止后仍可读取最后的值）。
+pub fn stop_monitors() {
+    let Ok(_lifecycle) = LIFECYCLE.lock() else {
+        return;
+    };
+    let Some(mut monitor) = ACTIVE.lock().ok().and_then(|mut active| active.take()) else {
+        return;
+    };
+    monitor.shutdown();
+    if let Ok(mut active) = ACTIVE.lock() {
+        active.get_or_insert(monitor);
+    }
+}
+
+// ===== 内部实现 =====
+
+/// 监控项读取循环
+fn watcher_loop(shared: &Shared, reader: &Reader, config: WatcherConfig) {
+    let name = config.name.as_str();
+    let Region { x, y, w, h } = config.region;
+    let interval = Duration::from_millis(config.interval_ms);
+
+    println!(
+        "[Monitor:{}] 启动 | 区域: ({},{},{},{}) | 间隔: {}ms | 解析: {:?}",
+        name, x, y, w, h, config.interval_ms, config.parser
+    );
+
+    while shared.alive() {
+        let generation = shared.generation();
+        let started = Instant::now();
+        match reader.read() {
+            Ok(read) => match read.value {
+                // 波次 0 视为误读
+                Some((WatchValue::Int(wave), _)) if name == WAVE && wave <= 0 => {
+                    shared.read_failed(name, format!("无法解析: {:?}", read.raw));
+                    shared.record(name, started, false, read.raw);
+                }
+                Some((value, score)) => {
+                    shared.record(name, started, true, read.raw);
+                    if let Some((old, new)) = shared.apply(name, generation, value, score) {
+                        on_changed(shared, name, old, new);
+                    }
+                }
+                None => {
+                    shared.read_failed(name, format!("无法解析: {:?}", read.raw));
+                    shared.record(name, started, false, read.raw);
+                }
+            },
+            Err(e) => {
+                shared.read_failed(name, e.to_string());
+                shared.record(name, started, false, format!("错误: {}", e));
+            }
+        }
+
+        shared.sleep(interval);
+    }
+
+    shared.running.store(false, Ordering::Relaxed);
+    println!("[Monitor:{}] 已停止", name);
+}
+
+/// 值变化时推送事件；波次从更高值回到 1 时自动进入新回合
+fn on_changed(shared: &Shared, name: &str, old: Option<WatchValue>, new: WatchValue) {
+    let int = |v: Option<&WatchValue>| v.and_then(|v| v.as_int()).unwrap_or(0);
+
+    match name {
+        WAVE => {
+            let (old, new) = (int(old.as_ref()) as u32, int(Some(&new)) as u32);
+            println!("[Monitor:Wave] 波次: {} → {}", old, new);
+            shared.emit(MonitorEvent::WaveChanged {
+                old,
+                new,
+                at: Instant::now(),
+            });
+
+            // 波次从更高值回到 1：上一轮已结束，自动进入新回合
+            if old > 1 && new == 1 {
+                let generation = shared.advance_generation(true, None);
+                shared.in_round.store(true, Ordering::SeqCst);
+                println!(
+                    "[Monitor:Wave] 波次回到 1，进入新回合 (代数 {})",
+                    generation
+                );
+                shared.emit(MonitorEvent::RoundBegan {
+                    generation,
+                    auto: true,
+                    at: Instant::now(),
+                });
+            }
+        }
+        GOLD => {
+            let (old, new, at) = (int(old.as_ref()), int(Some(&new)), Instant::now());
+            shared.record_gold(new, at);
+            shared.emit(MonitorEvent::GoldChanged { old, new, at });
+        }
+        _ => shared.emit(MonitorEvent::ValueChanged {
+            watcher: name.to_string(),
+            old,
+            new,
+            at: Instant::now(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::watcher::parse_integer;
+    use super::*;
+
+    #[test]
+    fn test_parse_wave_number() {
+        assert_eq!(parse_integer("02"), Some(2));
+        assert_eq!(parse_integer("10"), Some(10));
+        assert_eq!(parse_integer("1"), Some(1));
+        assert_eq!(parse_integer("波次3"), Some(3));
+        assert_eq!(parse_integer("没有数字"), None);
+    }
+
+    #[test]
+    fn test_parse_gold() {
+        assert_eq!(parse_integer("$3,999,600"), Some(3999600));
+        assert_eq!(parse_integer("3.979,600"), Some(3979600));
+        assert_eq!(parse_integer("4000000"), Some(4000000));
+        assert_eq!(parse_integer("没有数字"), None);
+    }
+
+    #[test]
+    fn test_shared_sleep_wakes_on_shutdown() {
+        let shared = Arc::new(Shared::new(&MonitorConfig::default()));
+        let sleeper = shared.clone();
+        let start = Instant::now();
+        let handle = thread::spawn(move || sleeper.sleep(Duration::from_secs(10)));
+
+        thread::sleep(Duration::from_millis(50));
+        *shared.shutdown.lock().unwrap() = true;
+        shared.wakeup.notify_all();
+        handle.join().unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_advance_generation_discards_stale_frames() {
+        let shared = Shared::new(&MonitorConfig::default());
+        let stale = shared.generation();
+        assert!(shared.apply(GOLD, stale, WatchValue::Int(5000), 1.0).is_some());
+
+        let generation = shared.advance_generation(false, Some(1));
+        assert_eq!(generation, stale + 1);
+        assert!(shared.int_reading(GOLD).is_none());
+
+        // 截图发生在代数切换之前的读数被丢弃
+        assert!(shared.apply(GOLD, stale, WatchValue::Int(5000), 1.0).is_none());
+        let (_, gold) = shared.apply(GOLD, generation, WatchValue::Int(800), 1.0).unwrap();
+        assert_eq!(gold, WatchValue::Int(800));
+        let reading = shared.int_reading(GOLD).unwrap();
+        assert_eq!(reading.generation, generation);
+    }
+
+    #[test]
+    fn test_misread_wave_one_keeps_round() {
+        let shared = Shared::new(&MonitorConfig::default());
+        let generation = shared.generation();
+        let push = |wave: i64| {
+            if let Some((old, new)) = shared.apply(WAVE, generation, WatchValue::Int(wave), 1.0) {
+                on_changed(&shared, WAVE, old, new);
+            }
+        };
+        push(12);
+        push(12);
+        // 第 12 波时 OCR 短暂把 "12" 读成 "1"
+        for wave in [1, 1, 1, 12, 1, 1] {
+            push(wave);
+        }
+        assert_eq!(shared.generation(), generation);
+        assert_eq!(shared.int_reading(WAVE).map(|r| r.value), Some(12));
+    }
+
+    #[test]
+    fn test_settle_time() {
+        let config = MonitorConfig {
+            gold_interval_ms: 300,
+            ..MonitorConfig::default()
+        };
+        let shared = Shared::new(&config);
+        // 默认金币中值窗口为 3
+        assert_eq!(shared.settle_time(GOLD), Some(Duration::from_millis(1200)));
+        assert_eq!(shared.settle_time("unknown"), None);
+    }
+
+    #[test]
+    fn test_emit_drops_closed_subscribers() {
+        let shared = Shared::new(&MonitorConfig::default());
+        let (tx_alive, rx_alive) = mpsc::channel();
+        let (tx_closed, rx_closed) = mpsc::channel();
+        shared
+            .subscribers
+            .lock()
+            .unwrap()
+            .extend([tx_alive, tx_closed]);
+        drop(rx_closed);
+
+        shared.read_failed("wave", "test".to_string());
+
+        assert_eq!(shared.subscribers.lock().unwrap().len(), 1);
+        assert!(matches!(
+            rx_alive.try_recv(),
+            Ok(MonitorEvent::ReadFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_extra_watchers_share_generation() {
+        let config = MonitorConfig {
+            watchers: vec![
+                WatcherConfig {
+                    name: "boss".to_string(),
+                    region: Region::new(0, 0, 10, 10),
+                    pipeline: Pipeline::Plain,
+                    parser: Parser::Text,
+                    interval_ms: 500,
+                    filter: None,
+                },
+                // 与内置监控项重名，被跳过
+                WatcherConfig {
+                    name: GOLD.to_string(),
+                    region: Region::new(0, 0, 10, 10),
+                    pipeline: Pipeline::Plain,
+                    parser: Parser::Text,
+                    interval_ms: 500,
+                    filter: None,
+                },
+            ],
+            ..MonitorConfig::default()
+        };
+        assert_eq!(config.all_watchers().len(), 3);
+
+        let shared = Shared::new(&config);
+        let boss = WatchValue::Text("深渊领主".to_string());
+        let (old, new) = shared.apply("boss", 0, boss.clone(), 0.9).unwrap();
+        assert_eq!((old, new), (None, boss.clone()));
+        // 相同值不算变化
+        assert!(shared.apply("boss", 0, boss, 0.9).is_none());
+        assert!(shared.apply("unknown", 0, WatchValue::Int(1), 1.0).is_none());
+
+        shared.advance_generation(false, Some(1));
+        assert!(shared.reading("boss").is_none());
+    }
+
+    #[test]
+    fn test_time_to_next_wave() {
+        let config = MonitorConfig {
+            countdown_region: Some(Region::new(0, 0, 10, 10)),
+            ..MonitorConfig::default()
+        };
+        let shared = Shared::new(&config);
+        assert!(shared.time_to_next_wave().is_none());
+
+        shared.apply(COUNTDOWN, 0, WatchValue::Int(20), 0.9);
+        let remaining = shared.time_to_next_wave().unwrap();
+        assert!(remaining <= Duration::from_secs(20));
+        assert!(remaining > Duration::from_secs(19));
+
+        // 倒计时为 0 且早已过期的读数视为失效
+        if let Some(slot) = shared.slots.get(COUNTDOWN) {
+            let mut slot = slot.lock().unwrap();
+            let latest = slot.latest.as_mut().unwrap();
+            latest.value = WatchValue::Int(0);
+            latest.at -= Duration::from_secs(5);
+        }
+        assert!(shared.time_to_next_wave().is_none());
+    }
+}
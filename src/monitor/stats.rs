@@ -0,0 +1,155 @@
+//! 监控项运行状态统计
+//!
+//! 记录每个监控项最近若干次读取的耗时和结果，用于诊断"读数不动"的原因：
+//! OCR 太慢（延迟高、读取频率低）、区域不对（失败率高、原始文字异常）还是解析失败。
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// 统计窗口（最近多少次读取）
+const WINDOW: usize = 30;
+
+/// 单个监控项的运行状态快照
+#[derive(Debug, Clone)]
+pub struct WatcherStats {
+    /// 监控项名称
+    pub name: String,
+    /// 最近一次成功解析的时间
+    pub last_success: Option<Instant>,
+    /// 每秒读取次数（最近窗口内）
+    pub reads_per_sec: f64,
+    /// 平均读取耗时（截图 + OCR + 解析，最近窗口内）
+    pub avg_latency: Duration,
+    /// 失败率 0.0-1.0（OCR 出错或无法解析，最近窗口内）
+    pub failure_rate: f32,
+    /// 最近一次读取的原始文字（出错时为错误信息）
+    pub last_raw: String,
+    /// 累计读取次数
+    pub total_reads: u64,
+}
+
+impl WatcherStats {
+    /// 距最近一次成功解析的时间，从未成功时为 None
+    pub fn since_success(&self) -> Option<Duration> {
+        self.last_success.map(|t| t.elapsed())
+    }
+
+    /// 超过 `threshold` 没有成功读数（从未成功也算）
+    pub fn is_stale(&self, threshold: Duration) -> bool {
+        match self.since_success() {
+            Some(elapsed) => elapsed > threshold,
+            None => true,
+        }
+    }
+}
+
+/// 一次读取的记录
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    at: Instant,
+    latency: Duration,
+    ok: bool,
+}
+
+/// 状态统计器（每个监控项一个）
+#[derive(Debug, Default)]
+pub(super) struct HealthTracker {
+    samples: VecDeque<Sample>,
+    last_success: Option<Instant>,
+    last_raw: String,
+    total_reads: u64,
+}
+
+impl HealthTracker {
+    /// 记录一次读取
+    ///
+    /// `ok` 为 true 表示解析出了值（不论是否被过滤器接受）
+    pub fn record(&mut self, at: Instant, latency: Duration, ok: bool, raw: String) {
+        if self.samples.len() >= WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(Sample { at, latency, ok });
+        if ok {
+            self.last_success = Some(at);
+        }
+        self.last_raw = raw;
+        self.total_reads += 1;
+    }
+
+    pub fn snapshot(&self, name: &str) -> WatcherStats {
+        let count = self.samples.len();
+
+        let reads_per_sec = match (self.samples.front(), self.samples.back()) {
+            (Some(first), Some(last)) if count > 1 => {
+                let span = last.at.duration_since(first.at).as_secs_f64();
+                if span > 0.0 {
+                    (count - 1) as f64 / span
+                } else {
+                    0.0
+                }
+            }
+            _ => 0.0,
+        };
+
+        let (avg_latency, failure_rate) = if count == 0 {
+            (Duration::ZERO, 0.0)
+        } else {
+            let total: Duration = self.samples.iter().map(|s| s.latency).sum();
+            let failures = self.samples.iter().filter(|s| !s.ok).count();
+            (total / count as u32, failures as f32 / count as f32)
+        };
+
+        WatcherStats {
+            name: name.to_string(),
+            last_success: self.last_success,
+            reads_per_sec,
+            avg_latency,
+            failure_rate,
+            last_raw: self.last_raw.clone(),
+            total_reads: self.total_reads,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_rates() {
+        let mut tracker = HealthTracker::default();
+        let t0 = Instant::now();
+        for i in 0..5u32 {
+            let ok = i % 2 == 0;
+            tracker.record(
+                t0 + Duration::from_millis(500 * i as u64),
+                Duration::from_millis(100 + 20 * i as u64),
+                ok,
+                format!("raw{}", i),
+            );
+        }
+
+        let stats = tracker.snapshot("gold");
+        assert_eq!(stats.total_reads, 5);
+        assert!((stats.reads_per_sec - 2.0).abs() < 1e-9);
+        assert_eq!(stats.avg_latency, Duration::from_millis(140));
+        assert!((stats.failure_rate - 0.4).abs() < 1e-6);
+        assert_eq!(stats.last_raw, "raw4");
+        assert_eq!(stats.last_success, Some(t0 + Duration::from_millis(2000)));
+    }
+
+    #[test]
+    fn test_never_succeeded_is_stale() {
+        let mut tracker = HealthTracker::default();
+        tracker.record(
+            Instant::now(),
+            Duration::from_millis(50),
+            false,
+            "???".into(),
+        );
+        let stats = tracker.snapshot("wave");
+        assert!(stats.is_stale(Duration::from_secs(3600)));
+        assert_eq!(stats.failure_rate, 1.0);
+        assert_eq!(stats.reads_per_sec, 0.0);
+    }
+}
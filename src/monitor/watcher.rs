@@ -0,0 +1,465 @@
+//! 命名监控项
+//!
+//! 每个监控项（watcher）由区域、预处理方式、解析方式和检测间隔组成，
+//! 在独立线程中周期读取屏幕。波次和金币是内置的两个监控项（"wave"、"gold"），
+//! 其他如基地血量、倒计时、Boss 名称等通过 `MonitorConfig::watchers` 配置。
+
+use std::fmt;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::FilterConfig;
+use crate::ocr::{ocr_screen, ocr_screen_color_filter, ocr_screen_small, OcrResultItem};
+use crate::screen::{
+    average_color, capture_region, read_progress_bar, BarSpec, Orientation, Region,
+};
+
+/// 内置波次监控项名称
+pub const WAVE: &str = "wave";
+/// 内置金币监控项名称
+pub const GOLD: &str = "gold";
+/// 内置波次倒计时监控项名称（"怪物即将来袭" 倒计时，配置了区域才启用）
+pub const COUNTDOWN: &str = "countdown";
+
+/// 是否为内置监控项名称（额外监控项不能使用）
+pub fn is_builtin(name: &str) -> bool {
+    [WAVE, GOLD, COUNTDOWN].contains(&name)
+}
+
+/// OCR 前的图像预处理方式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Pipeline {
+    /// 原图直接 OCR（`ocr_screen`）
+    Plain,
+    /// 放大 + Otsu 二值化（`ocr_screen_small`）
+    Small { scale: u32 },
+    /// 颜色过滤（`ocr_screen_color_filter`）
+    ColorFilter {
+        scale: u32,
+        color: (u8, u8, u8),
+        tolerance: f64,
+    },
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::Small { scale: 3 }
+    }
+}
+
+/// 解析方式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Parser {
+    /// 提取所有数字组成整数（"$3,999,600" → 3999600）
+    Integer,
+    /// 原样返回识别到的文字
+    Text,
+    /// 正则匹配，有捕获组时取第一个捕获组，否则取整个匹配
+    Regex { pattern: String },
+    /// 倒计时，"mm:ss" 或秒数，解析为剩余秒数
+    Countdown,
+    /// 进度条填充比例（不走 OCR，直接读像素）
+    ProgressBar {
+        #[serde(with = "hex_color")]
+        color: u32,
+        tolerance: u8,
+        orientation: BarOrientation,
+        /// 扫描线数量（默认 5）
+        #[serde(default = "default_scan_lines")]
+        scan_lines: u32,
+        /// 允许的最大间隙像素（默认 3）
+        #[serde(default = "default_max_gap")]
+        max_gap: u32,
+    },
+    /// 区域平均颜色（不走 OCR；1x1 区域即单个像素）
+    PixelColor,
+}
+
+/// 进度条方向（配置文件用，对应 `screen::Orientation`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarOrientation {
+    LeftToRight,
+    RightToLeft,
+    BottomToTop,
+    TopToBottom,
+}
+
+impl From<BarOrientation> for Orientation {
+    fn from(o: BarOrientation) -> Self {
+        match o {
+            BarOrientation::LeftToRight => Orientation::LeftToRight,
+            BarOrientation::RightToLeft => Orientation::RightToLeft,
+            BarOrientation::BottomToTop => Orientation::BottomToTop,
+            BarOrientation::TopToBottom => Orientation::TopToBottom,
+        }
+    }
+}
+
+/// 监控项配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatcherConfig {
+    /// 名称（`monitor::value(name)` 查询用）
+    pub name: String,
+    /// 屏幕区域，实际屏幕坐标（配置文件中写作 [x, y, w, h]）
+    pub region: Region,
+    /// 预处理方式（进度条/颜色解析时忽略）
+    #[serde(default)]
+    pub pipeline: Pipeline,
+    /// 解析方式
+    pub parser: Parser,
+    /// 检测间隔 (毫秒)
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+    /// 整数读数的过滤配置（None 则不过滤）
+    #[serde(default)]
+    pub filter: Option<FilterConfig>,
+}
+
+fn default_interval_ms() -> u64 {
+    500
+}
+
+fn default_scan_lines() -> u32 {
+    5
+}
+
+fn default_max_gap() -> u32 {
+    3
+}
+
+/// 监控项的值
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchValue {
+    Int(i64),
+    Text(String),
+    /// 比例 0.0-1.0（进度条）
+    Ratio(f64),
+    /// 颜色 0xRRGGBB
+    Color(u32),
+}
+
+impl WatchValue {
+    /// 整数值；文字会尝试提取其中的数字
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(v) => Some(*v),
+            Self::Text(t) => parse_integer(t),
+            _ => None,
+        }
+    }
+
+    /// 文字值
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// 比例值
+    pub fn as_ratio(&self) -> Option<f64> {
+        match self {
+            Self::Ratio(r) => Some(*r),
+            _ => None,
+        }
+    }
+
+    /// 颜色值
+    pub fn as_color(&self) -> Option<u32> {
+        match self {
+            Self::Color(c) => Some(*c),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for WatchValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int(v) => write!(f, "{}", v),
+            Self::Text(t) => write!(f, "{}", t),
+            Self::Ratio(r) => write!(f, "{:.0}%", r * 100.0),
+            Self::Color(c) => write!(f, "#{:06x}", c),
+        }
+    }
+}
+
+/// 一次读取的结果
+#[derive(Debug, Clone)]
+pub(super) struct RawRead {
+    /// 解析出的值和置信度，解析失败为 None
+    pub value: Option<(WatchValue, f32)>,
+    /// 原始文字（失败诊断用）
+    pub raw: String,
+}
+
+/// 监控项读取器：按配置截图、预处理、解析
+pub(super) struct Reader {
+    config: WatcherConfig,
+    regex: Option<Regex>,
+}
+
+impl Reader {
+    pub fn new(config: WatcherConfig) -> Result<Self> {
+        let regex = match &config.parser {
+            Parser::Regex { pattern } => Some(
+                Regex::new(pattern)
+                    .with_context(|| format!("监控项 '{}' 正则无效: {}", config.name, pattern))?,
+            ),
+            _ => None,
+        };
+        Ok(Self { config, regex })
+    }
+
+    pub fn read(&self) -> Result<RawRead> {
+        let region = self.config.region;
+        match &self.config.parser {
+            Parser::ProgressBar {
+                color,
+                tolerance,
+                orientation,
+                scan_lines,
+                max_gap,
+            } => {
+                let spec = BarSpec {
+                    scan_lines: *scan_lines,
+                    max_gap: *max_gap,
+                    ..BarSpec::new(*color, *tolerance, (*orientation).into())
+                };
+                let ratio = read_progress_bar(region, &spec)?;
+                Ok(RawRead {
+                    value: Some((WatchValue::Ratio(ratio), 1.0)),
+                    raw: format!("{:.3}", ratio),
+                })
+            }
+            Parser::PixelColor => {
+                let img = capture_region(region)?;
+                let color = average_color(&img);
+                Ok(RawRead {
+                    value: Some((WatchValue::Color(color), 1.0)),
+                    raw: format!("#{:06x}", color),
+                })
+            }
+            _ => {
+                let results = self.ocr(region)?;
+                Ok(self.parse_ocr(&results))
+            }
+        }
+    }
+
+    fn ocr(&self, region: Region) -> Result<Vec<OcrResultItem>> {
+        match self.config.pipeline {
+            Pipeline::Plain => ocr_screen(region, false, false),
+            Pipeline::Small { scale } => ocr_screen_small(region, scale, false),
+            Pipeline::ColorFilter {
+                scale,
+                color,
+                tolerance,
+            } => ocr_screen_color_filter(region, scale, color, tolerance, false),
+        }
+    }
+
+    fn parse_ocr(&self, results: &[OcrResultItem]) -> RawRead {
+        let raw = join_texts(results);
+        let value = match &self.config.parser {
+            Parser::Integer => {
+                best_parse(results, parse_integer).map(|(v, s)| (WatchValue::Int(v), s))
+            }
+            Parser::Text => {
+                (!raw.is_empty()).then(|| (WatchValue::Text(raw.clone()), mean_score(results)))
+            }
+            // 分和秒可能被识别成两个文字块，先解析拼接后的文字
+            Parser::Countdown => parse_countdown(&raw)
+                .map(|v| (v, mean_score(results)))
+                .or_else(|| best_parse(results, parse_countdown))
+                .map(|(v, s)| (WatchValue::Int(v), s)),
+            Parser::Regex { .. } => self
+                .regex
+                .as_ref()
+                .and_then(|re| re.captures(&raw))
+                .and_then(|caps| caps.get(1).or_else(|| caps.get(0)))
+                .map(|m| {
+                    (
+                        WatchValue::Text(m.as_str().to_string()),
+                        mean_score(results),
+                    )
+                }),
+            Parser::ProgressBar { .. } | Parser::PixelColor => None,
+        };
+        RawRead { value, raw }
+    }
+}
+
+/// 在一帧的多个 OCR 结果中取能解析且置信度最高的一个，返回 (值, 置信度)
+fn best_parse<T>(results: &[OcrResultItem], parse: impl Fn(&str) -> Option<T>) -> Option<(T, f32)> {
+    results
+        .iter()
+        .filter_map(|r| parse(&r.text).map(|v| (v, r.score)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// 按从左到右的顺序拼接所有文字
+fn join_texts(results: &[OcrResultItem]) -> String {
+    let mut sorted: Vec<&OcrResultItem> = results.iter().collect();
+    sorted.sort_by_key(|r| (r.box_points[0][0], r.box_points[0][1]));
+    sorted
+        .iter()
+        .map(|r| r.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn mean_score(results: &[OcrResultItem]) -> f32 {
+    if results.is_empty() {
+        return 0.0;
+    }
+    results.iter().map(|r| r.score).sum::<f32>() / results.len() as f32
+}
+
+/// 从文字中提取所有数字组成整数
+/// "02" → Some(2)
+/// "$3,999,600" → Some(3999600)
+/// "波次3" → Some(3)
+pub fn parse_integer(text: &str) -> Option<i64> {
+    let num_str: String = text.chars().filter(|c| c.is_ascii_digit()).collect();
+    if num_str.is_empty() {
+        return None;
+    }
+    num_str.parse().ok()
+}
+
+/// 从倒计时文字中解析剩余秒数
+/// "00:25" → Some(25)
+/// "怪物即将来袭 1：05" → Some(65)
+/// "12秒" → Some(12)
+pub fn parse_countdown(text: &str) -> Option<i64> {
+    let text = text.replace('：', ":");
+    if let Some((min, sec)) = text.rsplit_once(':') {
+        let min: String = min
+            .trim_end()
+            .chars()
+            .rev()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        let min: String = min.chars().rev().collect();
+        let sec: String = sec
+            .trim_start()
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        let (min, sec): (i64, i64) = (min.parse().ok()?, sec.parse().ok()?);
+        return (sec < 60).then_some(min * 60 + sec);
+    }
+    parse_integer(&text)
+}
+
+/// 颜色在配置文件中以 "#RRGGBB" 字符串表示
+pub(crate) mod hex_color {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("#{:06x}", color))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        u32::from_str_radix(s.trim().trim_start_matches('#'), 16).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(text: &str, x: i32, score: f32) -> OcrResultItem {
+        OcrResultItem {
+            score,
+            ..OcrResultItem::at(text, x, 5)
+        }
+    }
+
+    fn reader(parser: Parser) -> Reader {
+        Reader::new(WatcherConfig {
+            name: "test".to_string(),
+            region: Region::new(0, 0, 10, 10),
+            pipeline: Pipeline::default(),
+            parser,
+            interval_ms: 500,
+            filter: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_ocr_by_parser() {
+        let results = [item("HP", 0, 0.9), item("85/100", 20, 0.7)];
+
+        let int = reader(Parser::Integer).parse_ocr(&results);
+        assert_eq!(int.value.map(|v| v.0), Some(WatchValue::Int(85100)));
+
+        let text = reader(Parser::Text).parse_ocr(&results);
+        assert_eq!(text.raw, "HP 85/100");
+
+        let regex = reader(Parser::Regex {
+            pattern: r"(\d+)/\d+".to_string(),
+        })
+        .parse_ocr(&results);
+        let value = regex.value.unwrap().0;
+        assert_eq!(value, WatchValue::Text("85".to_string()));
+        assert_eq!(value.as_int(), Some(85));
+    }
+
+    #[test]
+    fn test_parse_countdown() {
+        assert_eq!(parse_countdown("00:25"), Some(25));
+        assert_eq!(parse_countdown("怪物即将来袭 1：05"), Some(65));
+        assert_eq!(parse_countdown("12秒"), Some(12));
+        assert_eq!(parse_countdown("0:75"), None);
+        assert_eq!(parse_countdown("怪物即将来袭"), None);
+
+        let split = [item("01:", 0, 0.8), item("30", 20, 0.9)];
+        let read = reader(Parser::Countdown).parse_ocr(&split);
+        assert_eq!(read.value.map(|v| v.0), Some(WatchValue::Int(90)));
+    }
+
+    #[test]
+    fn test_invalid_regex_is_rejected() {
+        let result = Reader::new(WatcherConfig {
+            name: "bad".to_string(),
+            region: Region::new(0, 0, 1, 1),
+            pipeline: Pipeline::Plain,
+            parser: Parser::Regex {
+                pattern: "(".to_string(),
+            },
+            interval_ms: 500,
+            filter: None,
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_watcher_config_json() {
+        let json = r##"[
+            {"name": "core_hp", "region": [100, 50, 300, 12],
+             "parser": {"type": "progress_bar", "color": "#c81e1e", "tolerance": 30, "orientation": "left_to_right"}},
+            {"name": "boss", "region": [800, 40, 320, 40], "pipeline": {"type": "plain"},
+             "parser": {"type": "text"}, "interval_ms": 1000}
+        ]"##;
+        let watchers: Vec<WatcherConfig> = serde_json::from_str(json).unwrap();
+        assert_eq!(watchers[0].pipeline, Pipeline::Small { scale: 3 });
+        assert_eq!(watchers[0].interval_ms, 500);
+        assert!(matches!(
+            watchers[0].parser,
+            Parser::ProgressBar {
+                color: 0xc81e1e,
+                ..
+            }
+        ));
+        assert_eq!(watchers[1].interval_ms, 1000);
+    }
+}
@@ -15,6 +15,8 @@ use std::sync::OnceLock;
 use std::time::Instant;
 use strsim::jaro_winkler;
 
+use crate::screen::Region;
+
 /// OCR 引擎单例
 static OCR_ENGINE: OnceLock<Mutex<OcrEngine>> = OnceLock::new();
 
@@ -54,6 +56,21 @@ impl OcrResultItem {
         let y = (self.box_points[0][1] + self.box_points[2][1]) / 2;
         (x, y)
     }
+
+    /// 测试用：中心在 (x, y) 的 20x10 文字框，置信度 0.9
+    #[cfg(test)]
+    pub(crate) fn at(text: &str, x: i32, y: i32) -> Self {
+        Self {
+            text: text.to_string(),
+            box_points: [
+                [x - 10, y - 5],
+                [x + 10, y - 5],
+                [x + 10, y + 5],
+                [x - 10, y + 5],
+            ],
+            score: 0.9,
+        }
+    }
 }
 
 /// 获取 exe 所在目录
@@ -240,22 +257,22 @@ pub fn ocr_image(img: &RgbImage, use_frame_skip: bool, debug: bool) -> Result<Ve
     Ok(results)
 }
 
+/// 截取屏幕区域（超出屏幕的部分会被裁掉）
+fn capture_screen_region(region: Region) -> Result<(RgbImage, i32, i32)> {
+    let region = region.clamp_to_screen();
+    let img = crate::screen::capture_region(region)?;
+    Ok((img, region.x, region.y))
+}
+
 /// 截取屏幕区域并进行 OCR
 ///
 /// # Arguments
-/// * `x`, `y`, `width`, `height` - 屏幕区域
+/// * `region` - 屏幕区域（实际像素，超出屏幕的部分会被裁掉）
 /// * `use_frame_skip` - 是否启用帧差跳过
 /// * `debug` - 是否输出调试信息
-pub fn ocr_screen(
-    x: i32,
-    y: i32,
-    width: i32,
-    height: i32,
-    use_frame_skip: bool,
-    debug: bool,
-) -> Result<Vec<OcrResultItem>> {
+pub fn ocr_screen(region: Region, use_frame_skip: bool, debug: bool) -> Result<Vec<OcrResultItem>> {
     // 截取屏幕区域
-    let img = crate::screen::capture_region(x, y, width, height)?;
+    let (img, x, y) = capture_screen_region(region)?;
 
     // 执行 OCR
     let mut results = ocr_image(&img, use_frame_skip, debug)?;
@@ -336,22 +353,19 @@ fn preprocess_color_filter(
 /// 只保留接近目标颜色的像素，其余全部置黑后再 OCR。
 ///
 /// # Arguments
-/// * `x`, `y`, `width`, `height` - 屏幕区域
+/// * `region` - 屏幕区域（实际像素）
 /// * `scale` - 放大倍数，推荐 3
 /// * `target_color` - 目标颜色 (R, G, B)
 /// * `tolerance` - 颜色距离容差（推荐 25-50）
 /// * `debug` - 是否输出调试信息
 pub fn ocr_screen_color_filter(
-    x: i32,
-    y: i32,
-    width: i32,
-    height: i32,
+    region: Region,
     scale: u32,
     target_color: (u8, u8, u8),
     tolerance: f64,
     debug: bool,
 ) -> Result<Vec<OcrResultItem>> {
-    let img = crate::screen::capture_region(x, y, width, height)?;
+    let (img, x, y) = capture_screen_region(region)?;
     let processed = preprocess_color_filter(
         &img,
         scale,
@@ -383,18 +397,11 @@ pub fn ocr_screen_color_filter(
 /// 与 `ocr_screen` 的区别：先对截图进行放大+二值化预处理，适合 30-100px 级别的小区域。
 ///
 /// # Arguments
-/// * `x`, `y`, `width`, `height` - 屏幕区域
+/// * `region` - 屏幕区域（实际像素）
 /// * `scale` - 放大倍数，推荐 3
 /// * `debug` - 是否输出调试信息
-pub fn ocr_screen_small(
-    x: i32,
-    y: i32,
-    width: i32,
-    height: i32,
-    scale: u32,
-    debug: bool,
-) -> Result<Vec<OcrResultItem>> {
-    let img = crate::screen::capture_region(x, y, width, height)?;
+pub fn ocr_screen_small(region: Region, scale: u32, debug: bool) -> Result<Vec<OcrResultItem>> {
+    let (img, x, y) = capture_screen_region(region)?;
     let processed = preprocess_small_region(&img, scale);
 
     if debug {
@@ -463,7 +470,7 @@ mod tests {
         init_ocr().expect("OCR 初始化失败");
 
         // 测试屏幕左上角区域 (0, 0) 到 (400, 300)
-        let results = ocr_screen(0, 0, 400, 300, false, true).expect("OCR 失败");
+        let results = ocr_screen(Region::new(0, 0, 400, 300), false, true).expect("OCR 失败");
 
         println!("识别到 {} 个文字区域:", results.len());
         for r in &results {
@@ -484,7 +491,7 @@ mod tests {
     fn test_ocr_fullscreen() {
         init_ocr().expect("OCR 初始化失败");
 
-        let results = ocr_screen(0, 0, 1920, 1080, false, true).expect("OCR 失败");
+        let results = ocr_screen(Region::new(0, 0, 1920, 1080), false, true).expect("OCR 失败");
 
         println!("全屏识别到 {} 个文字区域", results.len());
         for r in &results {
@@ -506,7 +513,7 @@ mod tests {
 
         println!("测试区域: ({}, {}) - {}x{}", x, y, width, height);
 
-        let results = ocr_screen(x, y, width, height, false, true).expect("OCR 失败");
+        let results = ocr_screen(Region::new(x, y, width, height), false, true).expect("OCR 失败");
 
         println!("识别结果:");
         if results.is_empty() {
@@ -523,7 +530,7 @@ mod tests {
     fn test_find_specific_text() {
         init_ocr().expect("OCR 初始化失败");
 
-        let results = ocr_screen(0, 0, 1920, 1080, false, false).expect("OCR 失败");
+        let results = ocr_screen(Region::new(0, 0, 1920, 1080), false, false).expect("OCR 失败");
 
         // 查找包含 "开始" 的文字
         if let Some(item) = find_text_contains(&results, "开始") {
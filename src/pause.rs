@@ -0,0 +1,176 @@
+//! 输入暂停闸门
+//!
+//! 后台弹窗处理器（`game::interrupt`）处理弹窗时调用 `pause()` 暂停其它线程的键鼠输入，
+//! 处理完（`PauseGuard` 释放）后恢复。`input` 模块的键鼠函数在发送输入前调用 `wait_if_paused()`，
+//! 持有暂停的线程本身不受影响。松开按键（`key_up`）不经过闸门，避免按键卡在按下状态。
+//!
+//! "移动 → 点击" 这类由多个输入组成的动作用 `action()` 包住：动作进行中不会被暂停，
+//! 暂停会等动作结束后才生效。
+
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+use std::time::Duration;
+
+use crate::stop_flag::should_stop;
+
+#[derive(Default)]
+struct State {
+    /// 当前持有暂停的线程
+    owner: Option<ThreadId>,
+    /// 正在执行不可打断动作（`action()`）的线程，可重复（嵌套）
+    actions: Vec<ThreadId>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    owner: None,
+    actions: Vec::new(),
+});
+static RESUMED: Condvar = Condvar::new();
+
+/// 暂停期间检查停止信号的间隔
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+fn lock() -> MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 暂停句柄，释放时恢复输入
+pub struct PauseGuard(());
+
+impl Drop for PauseGuard {
+    fn drop(&mut self) {
+        lock().owner = None;
+        RESUMED.notify_all();
+    }
+}
+
+/// 暂停其它线程的键鼠输入，直到返回的 `PauseGuard` 被释放
+///
+/// 已有其它线程持有暂停，或其它线程正在执行 `action()` 动作时，等待其结束后再暂停。
+pub fn pause() -> PauseGuard {
+    let me = thread::current().id();
+    let mut state = lock();
+    while state.owner.is_some_and(|id| id != me) || state.actions.iter().any(|&id| id != me) {
+        state = RESUMED.wait(state).unwrap_or_else(|e| e.into_inner());
+    }
+    state.owner = Some(me);
+    PauseGuard(())
+}
+
+/// 是否有线程持有暂停
+pub fn is_paused() -> bool {
+    lock().owner.is_some()
+}
+
+/// 阻塞到暂停被释放（或收到停止信号），返回之后仍持有锁
+fn wait_unpaused(mut state: MutexGuard<'static, State>) -> MutexGuard<'static, State> {
+    let me = thread::current().id();
+    while state.owner.is_some_and(|id| id != me) && !should_stop() {
+        state = RESUMED
+            .wait_timeout(state, STOP_CHECK_INTERVAL)
+            .unwrap_or_else(|e| e.into_inner())
+            .0;
+    }
+    state
+}
+
+/// 如果被其它线程暂停，阻塞到恢复或收到停止信号
+pub fn wait_if_paused() {
+    drop(wait_unpaused(lock()));
+}
+
+/// 不可打断动作的句柄，释放时允许暂停
+pub struct ActionGuard(());
+
+impl Drop for ActionGuard {
+    fn drop(&mut self) {
+        let me = thread::current().id();
+        let mut state = lock();
+        if let Some(i) = state.actions.iter().position(|&id| id == me) {
+            state.actions.swap_remove(i);
+        }
+        drop(state);
+        RESUMED.notify_all();
+    }
+}
+
+/// 开始一个不可打断的动作（如 "移动 → 点击"），直到返回的 `ActionGuard` 被释放
+///
+/// 先等待当前的暂停结束；动作期间其它线程的 `pause()` 会等待，
+/// 避免弹窗处理在移动和点击之间把鼠标移走，恢复后点到别处。
+pub fn action() -> ActionGuard {
+    let mut state = wait_unpaused(lock());
+    state.actions.push(thread::current().id());
+    ActionGuard(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    #[test]
+    fn test_pause_blocks_other_threads_only() {
+        let guard = pause();
+        assert!(is_paused());
+        // 持有暂停的线程不受影响
+        wait_if_paused();
+
+        let passed = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let passed = passed.clone();
+            thread::spawn(move || {
+                wait_if_paused();
+                passed.store(true, Ordering::SeqCst);
+            })
+        };
+
+        thread::sleep(Duration::from_millis(100));
+        assert!(!passed.load(Ordering::SeqCst));
+
+        let released = Instant::now();
+        drop(guard);
+        worker.join().unwrap();
+        assert!(passed.load(Ordering::SeqCst));
+        assert!(released.elapsed() < Duration::from_secs(1));
+        assert!(!is_paused());
+    }
+
+    #[test]
+    fn test_pause_waits_for_action() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let moved = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let (events, moved) = (events.clone(), moved.clone());
+            thread::spawn(move || {
+                let _action = action();
+                events.lock().unwrap().push("move");
+                moved.store(true, Ordering::SeqCst);
+                // 弹窗处理在移动和点击之间请求暂停
+                thread::sleep(Duration::from_millis(150));
+                wait_if_paused();
+                events.lock().unwrap().push("click");
+            })
+        };
+
+        while !moved.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(5));
+        }
+        let guard = pause();
+        events.lock().unwrap().push("pause");
+        drop(guard);
+        worker.join().unwrap();
+
+        assert_eq!(*events.lock().unwrap(), vec!["move", "click", "pause"]);
+
+        // 持有暂停的线程可以在自己的动作中继续输入
+        let guard = pause();
+        let nested = action();
+        wait_if_paused();
+        drop(nested);
+        drop(guard);
+        assert!(!is_paused());
+    }
+}
@@ -2,10 +2,12 @@
 //!
 //! 使用 win-screenshot 进行屏幕区域截图
 
+use std::fmt;
 use std::sync::OnceLock;
 
 use anyhow::{anyhow, Context, Result};
 use image::{DynamicImage, RgbImage};
+use serde::{Deserialize, Serialize};
 use win_screenshot::prelude::*;
 
 // ===== 分辨率与坐标缩放 =====
@@ -61,9 +63,9 @@ pub fn scale_y(base: i32) -> i32 {
     (base as f64 * sy).round() as i32
 }
 
-/// 缩放区域 (x, y, w, h) — x/w 按 X 轴，y/h 按 Y 轴
-pub fn scale_region(x: i32, y: i32, w: i32, h: i32) -> (i32, i32, i32, i32) {
-    (scale_x(x), scale_y(y), scale_x(w), scale_y(h))
+/// 缩放区域（基准 1920x1080 → 实际分辨率）— x/w 按 X 轴，y/h 按 Y 轴
+pub fn scale_region(x: i32, y: i32, w: i32, h: i32) -> Region {
+    Region::new(x, y, w, h).base_to_screen()
 }
 
 // ===== 开发分辨率坐标缩放（4K → 实际） =====
@@ -85,25 +87,271 @@ pub fn dev_y(y: i32) -> i32 {
 }
 
 /// 全屏区域（实际分辨率）
-pub fn full_screen_region() -> (i32, i32, i32, i32) {
+pub fn full_screen_region() -> Region {
     let (w, h) = get_screen_resolution();
-    (0, 0, w as i32, h as i32)
+    Region::new(0, 0, w as i32, h as i32)
+}
+
+// ===== 坐标类型 =====
+//
+// 三套坐标系各用一个类型，换算必须显式调用，避免把基准坐标当成屏幕像素传进去：
+// - `BasePoint`：1920x1080 基准坐标（`scale_x` / `scale_y`）
+// - `DevPoint`：3840x2160 开发坐标（`dev_x` / `dev_y`）
+// - `ScreenPoint`：实际屏幕像素，鼠标点击、截图使用
+//
+// `BasePoint` / `DevPoint` 可以 `into()` 成 `ScreenPoint`（按当前分辨率缩放）。
+
+/// 把 `v` 从 `from` 像素宽的坐标系换算到 `to` 像素宽的坐标系
+fn rescale(v: i32, from: u32, to: u32) -> i32 {
+    (v as f64 * to as f64 / from as f64).round() as i32
+}
+
+/// 1920x1080 基准坐标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BasePoint {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// 3840x2160 开发坐标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct DevPoint {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// 实际屏幕像素坐标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ScreenPoint {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl BasePoint {
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// 换算到实际分辨率
+    pub fn to_screen(self) -> ScreenPoint {
+        self.to_screen_at(get_screen_resolution())
+    }
+
+    /// 换算到分辨率为 `resolution` 的屏幕
+    pub fn to_screen_at(self, (w, h): (u32, u32)) -> ScreenPoint {
+        ScreenPoint::new(
+            rescale(self.x, BASE_WIDTH, w),
+            rescale(self.y, BASE_HEIGHT, h),
+        )
+    }
+
+    /// 偏移（基准像素）
+    pub fn offset(self, dx: i32, dy: i32) -> Self {
+        Self::new(self.x + dx, self.y + dy)
+    }
+}
+
+impl DevPoint {
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// 换算到实际分辨率
+    pub fn to_screen(self) -> ScreenPoint {
+        self.to_screen_at(get_screen_resolution())
+    }
+
+    /// 换算到分辨率为 `resolution` 的屏幕
+    pub fn to_screen_at(self, (w, h): (u32, u32)) -> ScreenPoint {
+        ScreenPoint::new(
+            rescale(self.x, DEV_WIDTH, w),
+            rescale(self.y, DEV_HEIGHT, h),
+        )
+    }
+
+    /// 换算到 1920x1080 基准坐标
+    pub fn to_base(self) -> BasePoint {
+        BasePoint::new(
+            rescale(self.x, DEV_WIDTH, BASE_WIDTH),
+            rescale(self.y, DEV_HEIGHT, BASE_HEIGHT),
+        )
+    }
+}
+
+impl ScreenPoint {
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// 换算回 1920x1080 基准坐标
+    pub fn to_base(self) -> BasePoint {
+        self.to_base_at(get_screen_resolution())
+    }
+
+    /// 从分辨率为 `resolution` 的屏幕换算回基准坐标
+    pub fn to_base_at(self, (w, h): (u32, u32)) -> BasePoint {
+        BasePoint::new(
+            rescale(self.x, w, BASE_WIDTH),
+            rescale(self.y, h, BASE_HEIGHT),
+        )
+    }
+
+    /// 偏移（屏幕像素）
+    pub fn offset(self, dx: i32, dy: i32) -> Self {
+        Self::new(self.x + dx, self.y + dy)
+    }
+}
+
+impl From<BasePoint> for ScreenPoint {
+    fn from(p: BasePoint) -> Self {
+        p.to_screen()
+    }
+}
+
+impl From<DevPoint> for ScreenPoint {
+    fn from(p: DevPoint) -> Self {
+        p.to_screen()
+    }
+}
+
+impl From<ScreenPoint> for (i32, i32) {
+    fn from(p: ScreenPoint) -> Self {
+        (p.x, p.y)
+    }
+}
+
+impl fmt::Display for BasePoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}) [1080p]", self.x, self.y)
+    }
+}
+
+impl fmt::Display for DevPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}) [4K]", self.x, self.y)
+    }
+}
+
+impl fmt::Display for ScreenPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+/// 矩形区域 (x, y, w, h)
+///
+/// 本身不区分坐标系：配置、常量中的区域用 `base_to_screen` / `dev_to_screen` 换算到屏幕，
+/// 截图、OCR 接收的是屏幕像素区域。序列化为 `[x, y, w, h]`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(from = "(i32, i32, i32, i32)", into = "(i32, i32, i32, i32)")]
+pub struct Region {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+impl Region {
+    pub const fn new(x: i32, y: i32, w: i32, h: i32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    pub fn right(&self) -> i32 {
+        self.x + self.w
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.y + self.h
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.w <= 0 || self.h <= 0
+    }
+
+    /// 中心点
+    pub fn center(&self) -> (i32, i32) {
+        (self.x + self.w / 2, self.y + self.h / 2)
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+
+    /// 按比例缩放 — x/w 乘 `sx`，y/h 乘 `sy`
+    pub fn scale(&self, sx: f64, sy: f64) -> Self {
+        let round = |v: i32, s: f64| (v as f64 * s).round() as i32;
+        Self::new(
+            round(self.x, sx),
+            round(self.y, sy),
+            round(self.w, sx),
+            round(self.h, sy),
+        )
+    }
+
+    /// 1920x1080 基准区域 → 实际分辨率
+    pub fn base_to_screen(&self) -> Self {
+        let (sx, sy) = get_scale_factors();
+        self.scale(sx, sy)
+    }
+
+    /// 3840x2160 开发区域 → 实际分辨率
+    pub fn dev_to_screen(&self) -> Self {
+        let (w, h) = get_screen_resolution();
+        self.scale(w as f64 / DEV_WIDTH as f64, h as f64 / DEV_HEIGHT as f64)
+    }
+
+    /// 两个区域的交集（不相交时为 None）
+    pub fn intersect(&self, other: &Region) -> Option<Self> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let region = Self::new(
+            x,
+            y,
+            self.right().min(other.right()) - x,
+            self.bottom().min(other.bottom()) - y,
+        );
+        (!region.is_empty()).then_some(region)
+    }
+
+    /// 裁剪到 `bounds` 以内；完全在外面时得到 `bounds` 边上的空区域
+    pub fn clamp_to(&self, bounds: &Region) -> Self {
+        self.intersect(bounds).unwrap_or_else(|| {
+            let x = self.x.clamp(bounds.x, bounds.right());
+            let y = self.y.clamp(bounds.y, bounds.bottom());
+            Self::new(x, y, 0, 0)
+        })
+    }
+
+    /// 裁剪到屏幕以内
+    pub fn clamp_to_screen(&self) -> Self {
+        self.clamp_to(&full_screen_region())
+    }
+}
+
+impl From<(i32, i32, i32, i32)> for Region {
+    fn from((x, y, w, h): (i32, i32, i32, i32)) -> Self {
+        Self::new(x, y, w, h)
+    }
+}
+
+impl From<Region> for (i32, i32, i32, i32) {
+    fn from(r: Region) -> Self {
+        (r.x, r.y, r.w, r.h)
+    }
 }
 
 /// 截取屏幕指定区域
 ///
 /// # Arguments
-/// * `x` - 左上角 X 坐标
-/// * `y` - 左上角 Y 坐标
-/// * `width` - 区域宽度
-/// * `height` - 区域高度
+/// * `region` - 截图区域（实际屏幕坐标，基准坐标先用 `Region::base_to_screen` 换算）
 ///
 /// # Returns
-/// RGB 格式的图像
-pub fn capture_region(x: i32, y: i32, width: i32, height: i32) -> Result<RgbImage> {
+/// RGB 格式的图像，超出屏幕的部分被裁掉；区域完全在屏幕外时返回错误
+pub fn capture_region(region: Region) -> Result<RgbImage> {
     // 使用 win-screenshot 截取屏幕
     let buf = capture_display()
         .map_err(|e| anyhow!("截取屏幕失败: {:?}", e))?;
+    let screen = Region::new(0, 0, buf.width as i32, buf.height as i32);
 
     // 转换为 image crate 的格式
     let img = DynamicImage::ImageRgba8(
@@ -111,8 +359,11 @@ pub fn capture_region(x: i32, y: i32, width: i32, height: i32) -> Result<RgbImag
             .context("无法创建图像缓冲区")?,
     );
 
-    // 裁剪到指定区域
-    let cropped = img.crop_imm(x as u32, y as u32, width as u32, height as u32);
+    // 裁剪到指定区域（先限制在屏幕以内，负坐标直接转 u32 会变成极大的偏移）
+    let Region { x, y, w, h } = region
+        .intersect(&screen)
+        .with_context(|| format!("截图区域 {:?} 不在屏幕内", region))?;
+    let cropped = img.crop_imm(x as u32, y as u32, w as u32, h as u32);
 
     Ok(cropped.to_rgb8())
 }
@@ -182,7 +433,11 @@ pub fn check_pixel_color(x: i32, y: i32, expected_color: u32) -> Result<bool> {
 /// 颜色在容差范围内返回 true，否则返回 false
 pub fn check_pixel_color_tolerance(x: i32, y: i32, expected_color: u32, tolerance: u8) -> Result<bool> {
     let actual_color = get_pixel_color(x, y)?;
+    Ok(color_within_tolerance(actual_color, expected_color, tolerance))
+}
 
+/// 判断两个颜色 (0xRRGGBB) 每个通道的差值是否都在容差内
+pub fn color_within_tolerance(actual_color: u32, expected_color: u32, tolerance: u8) -> bool {
     let ar = ((actual_color >> 16) & 0xFF) as i32;
     let ag = ((actual_color >> 8) & 0xFF) as i32;
     let ab = (actual_color & 0xFF) as i32;
@@ -193,16 +448,214 @@ pub fn check_pixel_color_tolerance(x: i32, y: i32, expected_color: u32, toleranc
 
     let t = tolerance as i32;
 
-    Ok((ar - er).abs() <= t && (ag - eg).abs() <= t && (ab - eb).abs() <= t)
+    (ar - er).abs() <= t && (ag - eg).abs() <= t && (ab - eb).abs() <= t
+}
+
+/// 图像像素转为 0xRRGGBB
+fn rgb_to_u32(pixel: &image::Rgb<u8>) -> u32 {
+    ((pixel[0] as u32) << 16) | ((pixel[1] as u32) << 8) | pixel[2] as u32
+}
+
+/// 计算图像的平均颜色 (0xRRGGBB 格式)
+pub fn average_color(img: &RgbImage) -> u32 {
+    let count = (img.width() as u64 * img.height() as u64).max(1);
+    let mut sum = [0u64; 3];
+    for pixel in img.pixels() {
+        for (s, c) in sum.iter_mut().zip(pixel.0) {
+            *s += c as u64;
+        }
+    }
+    let [r, g, b] = sum.map(|s| (s / count) as u32);
+    (r << 16) | (g << 8) | b
+}
+
+/// 进度条方向（填充增长的方向）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    /// 从左向右填充
+    LeftToRight,
+    /// 从右向左填充
+    RightToLeft,
+    /// 从下向上填充
+    BottomToTop,
+    /// 从上向下填充
+    TopToBottom,
+}
+
+/// 进度条读取参数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BarSpec {
+    /// 填充颜色 (0xRRGGBB)
+    pub fill_color: u32,
+    /// 颜色容差（每个通道，同 `check_pixel_color_tolerance`）
+    pub tolerance: u8,
+    /// 填充方向
+    pub orientation: Orientation,
+    /// 沿条的厚度方向取几条扫描线，结果取中值（抗文字/图标遮挡）
+    pub scan_lines: u32,
+    /// 填充段中允许的最大连续不匹配像素数（抗分隔线/闪烁噪点）
+    pub max_gap: u32,
+}
+
+impl BarSpec {
+    /// 默认 5 条扫描线，允许 3 像素间隙
+    pub fn new(fill_color: u32, tolerance: u8, orientation: Orientation) -> Self {
+        Self {
+            fill_color,
+            tolerance,
+            orientation,
+            scan_lines: 5,
+            max_gap: 3,
+        }
+    }
+}
+
+/// 读取屏幕上进度条的填充比例 (0.0-1.0)
+///
+/// # Example
+/// ```ignore
+/// // 基地血量低于 50% 时升级陷阱
+/// let region = scale_region(760, 40, 400, 12);
+/// let hp = read_progress_bar(region, &BarSpec::new(0xC81E1E, 30, Orientation::LeftToRight))?;
+/// if hp < 0.5 { upgrade_trap(...)?; }
+/// ```
+pub fn read_progress_bar(region: Region, spec: &BarSpec) -> Result<f64> {
+    let img = capture_region(region)?;
+    Ok(bar_fill_ratio(&img, spec))
+}
+
+/// 计算图像中进度条的填充比例 (0.0-1.0)
+///
+/// 在条的厚度方向中间 60% 范围内均匀取 `scan_lines` 条扫描线，每条从起点开始扫描，
+/// 连续不匹配超过 `max_gap` 像素即认为填充结束，各扫描线的比例取中值。
+pub fn bar_fill_ratio(img: &RgbImage, spec: &BarSpec) -> f64 {
+    let (w, h) = img.dimensions();
+    let (len, thickness) = match spec.orientation {
+        Orientation::LeftToRight | Orientation::RightToLeft => (w, h),
+        Orientation::BottomToTop | Orientation::TopToBottom => (h, w),
+    };
+    if len == 0 || thickness == 0 {
+        return 0.0;
+    }
+
+    let lines = spec.scan_lines.max(1);
+    let mut ratios: Vec<f64> = (0..lines)
+        .map(|k| {
+            let offset = (thickness as f64 * (0.2 + 0.6 * (k as f64 + 0.5) / lines as f64)) as u32;
+            let offset = offset.min(thickness - 1);
+            scan_line_fill(img, spec, len, offset) as f64 / len as f64
+        })
+        .collect();
+
+    ratios.sort_by(|a, b| a.total_cmp(b));
+    ratios[ratios.len() / 2]
+}
+
+/// 沿一条扫描线计算填充长度（最后一个匹配像素的位置 + 1）
+fn scan_line_fill(img: &RgbImage, spec: &BarSpec, len: u32, offset: u32) -> u32 {
+    let (w, h) = img.dimensions();
+    let mut filled = 0;
+    let mut gap = 0;
+
+    for i in 0..len {
+        let (x, y) = match spec.orientation {
+            Orientation::LeftToRight => (i, offset),
+            Orientation::RightToLeft => (w - 1 - i, offset),
+            Orientation::TopToBottom => (offset, i),
+            Orientation::BottomToTop => (offset, h - 1 - i),
+        };
+        if color_within_tolerance(rgb_to_u32(img.get_pixel(x, y)), spec.fill_color, spec.tolerance) {
+            filled = i + 1;
+            gap = 0;
+        } else {
+            gap += 1;
+            if gap > spec.max_gap {
+                break;
+            }
+        }
+    }
+    filled
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_bar_fill_ratio() {
+        // 10x3 的条，左侧 4 列为红色
+        let mut img = RgbImage::from_pixel(10, 3, image::Rgb([20, 20, 20]));
+        for x in 0..4 {
+            for y in 0..3 {
+                img.put_pixel(x, y, image::Rgb([200, 30, 30]));
+            }
+        }
+        let spec = BarSpec::new(0xC81E1E, 10, Orientation::LeftToRight);
+        let ratio = bar_fill_ratio(&img, &spec);
+        assert!((ratio - 0.4).abs() < 1e-9);
+        assert_eq!(average_color(&RgbImage::from_pixel(2, 2, image::Rgb([1, 2, 3]))), 0x010203);
+    }
+
+    #[test]
+    fn test_bar_fill_ratio_noise() {
+        // 100x10 的条从左往右填充 60%：中间有分隔线，一条扫描线被图标遮挡，空白处有同色噪点
+        let red = image::Rgb([200, 30, 30]);
+        let mut img = RgbImage::from_pixel(100, 10, image::Rgb([20, 20, 20]));
+        for x in 0..60 {
+            for y in 0..10 {
+                img.put_pixel(x, y, red);
+            }
+        }
+        for y in 0..10 {
+            img.put_pixel(30, y, image::Rgb([0, 0, 0]));
+            img.put_pixel(31, y, image::Rgb([0, 0, 0]));
+        }
+        for x in 0..60 {
+            img.put_pixel(x, 5, image::Rgb([255, 255, 255]));
+        }
+        img.put_pixel(90, 3, red);
+
+        let spec = BarSpec::new(0xC81E1E, 10, Orientation::LeftToRight);
+        let ratio = bar_fill_ratio(&img, &spec);
+        assert!((ratio - 0.6).abs() < 1e-9);
+
+        let empty = RgbImage::from_pixel(50, 4, image::Rgb([20, 20, 20]));
+        assert_eq!(bar_fill_ratio(&empty, &spec), 0.0);
+    }
+
+    #[test]
+    fn test_point_conversions() {
+        let uhd = (3840, 2160);
+        assert_eq!(BasePoint::new(800, 400).to_screen_at(uhd), ScreenPoint::new(1600, 800));
+        assert_eq!(DevPoint::new(1600, 800).to_screen_at((1920, 1080)), ScreenPoint::new(800, 400));
+        assert_eq!(DevPoint::new(1601, 800).to_base(), BasePoint::new(801, 400));
+        assert_eq!(ScreenPoint::new(1600, 800).to_base_at(uhd), BasePoint::new(800, 400));
+        assert_eq!(BasePoint::new(800, 400).offset(-15, 5), BasePoint::new(785, 405));
+    }
+
+    #[test]
+    fn test_region() {
+        let r = Region::new(100, 200, 50, 40);
+        assert_eq!(r.scale(2.0, 0.5), Region::new(200, 100, 100, 20));
+        assert_eq!(r.center(), (125, 220));
+        assert!(r.contains(100, 200) && !r.contains(150, 200));
+
+        let other = Region::new(120, 180, 100, 30);
+        assert_eq!(r.intersect(&other), Some(Region::new(120, 200, 30, 10)));
+        assert_eq!(r.intersect(&Region::new(150, 200, 10, 10)), None);
+
+        let screen = Region::new(0, 0, 1920, 1080);
+        assert_eq!(
+            Region::new(1900, -10, 100, 50).clamp_to(&screen),
+            Region::new(1900, 0, 20, 40)
+        );
+        assert!(Region::new(2000, 500, 10, 10).clamp_to(&screen).is_empty());
+        assert_eq!(<(i32, i32, i32, i32)>::from(r), (100, 200, 50, 40));
+    }
+
     #[test]
     fn test_capture_region() {
-        let img = capture_region(0, 0, 100, 100).unwrap();
+        let img = capture_region(Region::new(0, 0, 100, 100)).unwrap();
         assert_eq!(img.width(), 100);
         assert_eq!(img.height(), 100);
     }
@@ -0,0 +1,482 @@
+//! 回合时间线记录
+//!
+//! 每轮游戏写一个 `sessions/round_<时间戳>_<轮次>.jsonl`（exe 同目录），每行一个 JSON 事件：
+//! 波次变化、金币变化、额外监控项变化、OCR 失败、策略步骤（放置/升级/购买/等待）和结算截图。
+//! 可选同时写一个按波次汇总的 `.csv`，用于调整 `wait_gold(15000)` 这类阈值。
+//!
+//! 监控事件由后台线程通过 `monitor::subscribe()` 转发；策略步骤由 `game::common` 的
+//! 公共函数调用 `timeline::step` 记录。没有活动时间线时所有记录函数都是空操作。
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{LineWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::monitor::{self, MonitorEvent};
+
+/// 同一监控项的读取失败，最多每隔多久写一行（其余只计数）
+const FAILURE_LOG_INTERVAL: Duration = Duration::from_secs(1);
+
+// ===== 事件 =====
+
+/// 时间线事件（JSONL 中的 `kind` 字段）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Entry {
+    /// 时间线开始
+    Session {
+        map: String,
+        round: u32,
+        unix_ms: u64,
+    },
+    /// 监控进入新回合代数
+    RoundBegan { generation: u64, auto: bool },
+    /// 监控回合结束
+    RoundEnded { generation: u64 },
+    /// 波次变化
+    Wave { old: u32, new: u32 },
+    /// 金币变化
+    Gold { old: i64, new: i64 },
+    /// 额外监控项变化
+    Value {
+        watcher: String,
+        old: Option<String>,
+        new: String,
+    },
+    /// 读取失败（`repeats` 为上次记录以来被合并的失败次数）
+    ReadFailed {
+        watcher: String,
+        error: String,
+        repeats: u32,
+    },
+    /// 策略步骤
+    Step { name: String, detail: String },
+    /// 截图文件
+    Screenshot { path: String },
+    /// 时间线结束
+    Finished { duration_secs: f64, waves: usize },
+}
+
+/// JSONL 中的一行：相对时间线开始的秒数 + 事件
+#[derive(Serialize)]
+struct Line<'a> {
+    t: f64,
+    #[serde(flatten)]
+    entry: &'a Entry,
+}
+
+fn format_line(t: f64, entry: &Entry) -> String {
+    let t = (t * 1000.0).round() / 1000.0;
+    serde_json::to_string(&Line { t, entry }).unwrap_or_default()
+}
+
+// ===== 波次汇总 =====
+
+/// 一个波次的汇总（CSV 的一行）
+#[derive(Debug, Clone, PartialEq)]
+struct WaveRow {
+    /// 波次（0 = 第一波之前的准备阶段）
+    wave: u32,
+    start: f64,
+    end: f64,
+    gold_start: Option<i64>,
+    gold_end: Option<i64>,
+    /// 金币上涨总量
+    income: i64,
+    /// 金币下降总量（购买/放置）
+    spent: i64,
+    steps: u32,
+    read_failures: u32,
+}
+
+impl WaveRow {
+    fn new(wave: u32, start: f64, gold: Option<i64>) -> Self {
+        Self {
+            wave,
+            start,
+            end: start,
+            gold_start: gold,
+            gold_end: gold,
+            income: 0,
+            spent: 0,
+            steps: 0,
+            read_failures: 0,
+        }
+    }
+}
+
+/// 按波次累计的汇总
+#[derive(Debug, Default)]
+struct Summary {
+    rows: Vec<WaveRow>,
+}
+
+impl Summary {
+    fn current(&mut self, t: f64) -> &mut WaveRow {
+        if self.rows.is_empty() {
+            self.rows.push(WaveRow::new(0, t, None));
+        }
+        let row = self.rows.last_mut().unwrap();
+        row.end = t;
+        row
+    }
+
+    fn add(&mut self, t: f64, entry: &Entry) {
+        match entry {
+            Entry::Wave { new, .. } => {
+                let gold = self.current(t).gold_end;
+                self.rows.push(WaveRow::new(*new, t, gold));
+            }
+            Entry::Gold { old, new } => {
+                let row = self.current(t);
+                if row.gold_end.is_none() {
+                    row.gold_start = Some(*old);
+                }
+                row.gold_end = Some(*new);
+                let delta = new - old;
+                if delta > 0 {
+                    row.income += delta;
+                } else {
+                    row.spent -= delta;
+                }
+            }
+            Entry::Step { .. } => self.current(t).steps += 1,
+            // 被合并（未写入）的失败在跳过时已单独计数
+            Entry::ReadFailed { .. } => self.current(t).read_failures += 1,
+            _ => {
+                self.current(t);
+            }
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        let mut out = String::from(
+            "wave,start_s,duration_s,gold_start,gold_end,income,spent,income_per_s,steps,read_failures\n",
+        );
+        let opt = |v: Option<i64>| v.map(|v| v.to_string()).unwrap_or_default();
+        for row in &self.rows {
+            let duration = row.end - row.start;
+            let rate = if duration > 0.0 {
+                row.income as f64 / duration
+            } else {
+                0.0
+            };
+            out.push_str(&format!(
+                "{},{:.1},{:.1},{},{},{},{},{:.1},{},{}\n",
+                row.wave,
+                row.start,
+                duration,
+                opt(row.gold_start),
+                opt(row.gold_end),
+                row.income,
+                row.spent,
+                rate,
+                row.steps,
+                row.read_failures
+            ));
+        }
+        out
+    }
+}
+
+// ===== 时间线 =====
+
+struct Session {
+    id: u64,
+    start: Instant,
+    jsonl_path: PathBuf,
+    csv_path: Option<PathBuf>,
+    out: LineWriter<File>,
+    summary: Summary,
+    /// 每个监控项上次写入失败的时间和之后被合并的次数
+    failures: HashMap<String, (Instant, u32)>,
+}
+
+impl Session {
+    fn write(&mut self, at: Instant, entry: Entry) {
+        let t = at.saturating_duration_since(self.start).as_secs_f64();
+
+        let entry = match entry {
+            Entry::ReadFailed { watcher, error, .. } => {
+                match self.failures.get_mut(&watcher) {
+                    Some((last, skipped))
+                        if at.saturating_duration_since(*last) < FAILURE_LOG_INTERVAL =>
+                    {
+                        *skipped += 1;
+                        self.summary.current(t).read_failures += 1;
+                        return;
+                    }
+                    _ => {}
+                }
+                let repeats = self
+                    .failures
+                    .insert(watcher.clone(), (at, 0))
+                    .map_or(0, |(_, skipped)| skipped);
+                Entry::ReadFailed {
+                    watcher,
+                    error,
+                    repeats,
+                }
+            }
+            entry => entry,
+        };
+
+        self.summary.add(t, &entry);
+        let _ = writeln!(self.out, "{}", format_line(t, &entry));
+    }
+
+    fn finish(mut self) {
+        let duration_secs = self.start.elapsed().as_secs_f64();
+        let waves = self.summary.rows.iter().filter(|r| r.wave > 0).count();
+        self.write(
+            Instant::now(),
+            Entry::Finished {
+                duration_secs,
+                waves,
+            },
+        );
+        let _ = self.out.flush();
+
+        if let Some(csv) = &self.csv_path {
+            if let Err(e) = fs::write(csv, self.summary.to_csv()) {
+                println!("[Timeline] 写入汇总失败: {}", e);
+            }
+        }
+        println!("[Timeline] 已保存: {}", self.jsonl_path.display());
+    }
+}
+
+/// 当前活动的时间线
+static ACTIVE: Mutex<Option<Session>> = Mutex::new(None);
+
+/// 时间线编号（转发线程据此判断自己是否过期）
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 时间线目录（exe 同目录的 sessions/）
+pub fn sessions_dir() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(|p| p.to_path_buf()))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("sessions")
+}
+
+/// 开始记录一轮游戏的时间线（会先结束上一条未结束的时间线）
+///
+/// # Arguments
+/// * `map` - 地图名称
+/// * `round` - 轮次（从 1 开始）
+/// * `csv` - 结束时是否额外写入按波次汇总的 CSV
+///
+/// # Returns
+/// JSONL 文件路径
+pub fn begin_round(map: &str, round: u32, csv: bool) -> Result<PathBuf> {
+    end_round();
+
+    let dir = sessions_dir();
+    fs::create_dir_all(&dir).with_context(|| format!("无法创建目录: {}", dir.display()))?;
+
+    let unix_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let stem = format!("round_{}_{}", unix_ms / 1000, round);
+    let jsonl_path = dir.join(format!("{}.jsonl", stem));
+    let file = File::create(&jsonl_path)
+        .with_context(|| format!("无法创建时间线文件: {}", jsonl_path.display()))?;
+
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    let mut session = Session {
+        id,
+        start: Instant::now(),
+        jsonl_path: jsonl_path.clone(),
+        csv_path: csv.then(|| dir.join(format!("{}.csv", stem))),
+        out: LineWriter::new(file),
+        summary: Summary::default(),
+        failures: HashMap::new(),
+    };
+    session.write(
+        session.start,
+        Entry::Session {
+            map: map.to_string(),
+            round,
+            unix_ms,
+        },
+    );
+
+    if let Ok(mut active) = ACTIVE.lock() {
+        *active = Some(session);
+    }
+
+    // 转发监控事件，时间线结束或监控停止后退出
+    if let Some(rx) = monitor::subscribe() {
+        thread::spawn(move || loop {
+            match rx.recv_timeout(Duration::from_millis(500)) {
+                Ok(event) => record_event(id, event),
+                Err(RecvTimeoutError::Timeout) => {
+                    if active_id() != Some(id) {
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        });
+    }
+
+    println!("[Timeline] 开始记录: {}", jsonl_path.display());
+    Ok(jsonl_path)
+}
+
+/// 结束当前时间线，写入汇总（没有活动时间线时无操作）
+pub fn end_round() {
+    let session = ACTIVE.lock().ok().and_then(|mut a| a.take());
+    if let Some(session) = session {
+        session.finish();
+    }
+}
+
+/// 记录一个策略步骤
+///
+/// # Example
+/// ```ignore
+/// timeline::step("place_trap", format!("key={} @ ({}, {})", key, x, y));
+/// ```
+pub fn step(name: &str, detail: impl Into<String>) {
+    record(Entry::Step {
+        name: name.to_string(),
+        detail: detail.into(),
+    });
+}
+
+/// 记录截图文件路径
+pub fn screenshot(path: &str) {
+    record(Entry::Screenshot {
+        path: path.to_string(),
+    });
+}
+
+fn active_id() -> Option<u64> {
+    ACTIVE.lock().ok()?.as_ref().map(|s| s.id)
+}
+
+fn record(entry: Entry) {
+    if let Ok(mut active) = ACTIVE.lock() {
+        if let Some(session) = active.as_mut() {
+            session.write(Instant::now(), entry);
+        }
+    }
+}
+
+/// 把监控事件写入编号为 `id` 的时间线（已被新时间线取代时丢弃）
+fn record_event(id: u64, event: MonitorEvent) {
+    let (at, entry) = match event {
+        MonitorEvent::WaveChanged { old, new, at } => (at, Entry::Wave { old, new }),
+        MonitorEvent::GoldChanged { old, new, at } => (at, Entry::Gold { old, new }),
+        MonitorEvent::ValueChanged {
+            watcher,
+            old,
+            new,
+            at,
+        } => (
+            at,
+            Entry::Value {
+                watcher,
+                old: old.map(|v| v.to_string()),
+                new: new.to_string(),
+            },
+        ),
+        MonitorEvent::RoundBegan {
+            generation,
+            auto,
+            at,
+        } => (at, Entry::RoundBegan { generation, auto }),
+        MonitorEvent::RoundEnded { generation, at } => (at, Entry::RoundEnded { generation }),
+        MonitorEvent::ReadFailed { watcher, error, at } => (
+            at,
+            Entry::ReadFailed {
+                watcher,
+                error,
+                repeats: 0,
+            },
+        ),
+    };
+
+    if let Ok(mut active) = ACTIVE.lock() {
+        if let Some(session) = active.as_mut().filter(|s| s.id == id) {
+            session.write(at, entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_line() {
+        let line = format_line(1.23456, &Entry::Wave { old: 1, new: 2 });
+        assert_eq!(line, r#"{"t":1.235,"kind":"wave","old":1,"new":2}"#);
+    }
+
+    #[test]
+    fn test_summary_rows() {
+        let mut summary = Summary::default();
+        summary.add(0.0, &Entry::Gold { old: 0, new: 1000 });
+        summary.add(
+            1.0,
+            &Entry::Step {
+                name: "buy_traps".into(),
+                detail: String::new(),
+            },
+        );
+        summary.add(2.0, &Entry::Wave { old: 0, new: 1 });
+        summary.add(
+            4.0,
+            &Entry::Gold {
+                old: 1000,
+                new: 1600,
+            },
+        );
+        summary.add(
+            5.0,
+            &Entry::Gold {
+                old: 1600,
+                new: 600,
+            },
+        );
+        summary.add(
+            6.0,
+            &Entry::ReadFailed {
+                watcher: "gold".into(),
+                error: "x".into(),
+                repeats: 2,
+            },
+        );
+
+        assert_eq!(summary.rows.len(), 2);
+        let prep = &summary.rows[0];
+        assert_eq!((prep.wave, prep.steps, prep.income), (0, 1, 1000));
+        let wave1 = &summary.rows[1];
+        assert_eq!(wave1.gold_start, Some(1000));
+        assert_eq!(wave1.gold_end, Some(600));
+        assert_eq!(
+            (wave1.income, wave1.spent, wave1.read_failures),
+            (600, 1000, 1)
+        );
+
+        let csv = summary.to_csv();
+        assert_eq!(csv.lines().count(), 3);
+        assert_eq!(
+            csv.lines().nth(2),
+            Some("1,2.0,4.0,1000,600,600,1000,150.0,0,1")
+        );
+    }
+}